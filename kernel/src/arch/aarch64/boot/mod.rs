@@ -1,7 +1,7 @@
 // Import dependencies
 use core::{arch::asm, slice, ptr};
 
-use super::{cpu::park_non_main_cores, interrupts::{self, setup_interrupts}};
+use super::{cpu::{self, percpu, CORE_COUNT}, interrupts::{self, setup_interrupts}};
 // Link with global labels
 extern "C" {
     #[link_name = "__boot_stacks_start__"]
@@ -17,8 +17,18 @@ extern "C" {
 }
 // Define constants
 const CORE_ID_MASK: u8 = 0b11;
-const ASSUMED_CORES: usize = 4;
+const ASSUMED_CORES: usize = CORE_COUNT;
 const STACK_ALIGNMENT_MASK: usize = !(0x8);
+const CURRENT_EL_2: u64 = 0b10 << 2;
+/// EL1 executes in AArch64
+const HCR_EL2_RW: u64 = 1 << 31;
+/// EL1PCEN | EL1PCTEN (physical timer and counter accessible from EL1)
+const CNTHCTL_EL2_EL1_ACCESS: u64 = 0b11;
+/// Only the RES1 bits set (no FP/SIMD traps)
+const CPTR_EL2_NO_TRAPS: u64 = 0x33ff;
+/// DAIF masked, EL1h
+const SPSR_EL1H_MASKED: u64 = 0x3c5;
+const CPACR_EL1_FPEN: u64 = 0b11 << 20;
 
 // Define very initial functions
 #[export_name = "_start"]
@@ -46,6 +56,32 @@ unsafe extern "C" fn boot_entry() -> ! {
             // Align Stack
             and x0, x0, {stack_alignment_mask}
 
+            // Drop from EL2 to EL1 (when needed), keeping the stack
+            mrs x1, CurrentEL
+            cmp x1, {current_el_2}
+            b.ne 2f
+            msr SP_EL1, x0
+            // EL1 runs in AArch64 and can use the counters and FP/SIMD
+            mov x1, {hcr_el2_rw}
+            msr HCR_EL2, x1
+            mrs x1, CNTHCTL_EL2
+            orr x1, x1, {cnthctl_el2_el1_access}
+            msr CNTHCTL_EL2, x1
+            msr CNTVOFF_EL2, xzr
+            mov x1, {cptr_el2_no_traps}
+            msr CPTR_EL2, x1
+            // Return to EL1h with interrupts masked
+            mov x1, {spsr_el1h_masked}
+            msr SPSR_EL2, x1
+            adr x1, 2f
+            msr ELR_EL2, x1
+            eret
+        2:
+            // Enable FP/SIMD at EL1
+            mov x1, {cpacr_el1_fpen}
+            msr CPACR_EL1, x1
+            isb
+
             // Assign Stack Pointer
             mov sp, x0
 
@@ -57,6 +93,12 @@ unsafe extern "C" fn boot_entry() -> ! {
         core_id_mask = const CORE_ID_MASK,
         core_count = const ASSUMED_CORES,
         stack_alignment_mask = const STACK_ALIGNMENT_MASK,
+        current_el_2 = const CURRENT_EL_2,
+        hcr_el2_rw = const HCR_EL2_RW,
+        cnthctl_el2_el1_access = const CNTHCTL_EL2_EL1_ACCESS,
+        cptr_el2_no_traps = const CPTR_EL2_NO_TRAPS,
+        spsr_el1h_masked = const SPSR_EL1H_MASKED,
+        cpacr_el1_fpen = const CPACR_EL1_FPEN,
         rust_entrypoint = sym start,
         options(noreturn)
     )
@@ -64,13 +106,26 @@ unsafe extern "C" fn boot_entry() -> ! {
 // Define Rust entrypoint (stack is needed)
 unsafe fn start() -> ! {
     // Keep only Core 0 for setup the system
-    park_non_main_cores();
+    if cpu::this_core() != 0 {
+        cpu::wait_for_release();
+        start_secondary()
+    }
     // Initialize BSS
     clear_bss();
+    // Replicate per-CPU data and point TPIDR_EL1 to this core's copy
+    percpu::init_areas();
+    percpu::init_core();
     // Setup Interruptions
     setup_interrupts();
-    // Loop
-    loop {}
+    // Run the kernel
+    crate::main()
+}
+
+/// Entrypoint of the other cores, once the boot core set up the system
+unsafe fn start_secondary() -> ! {
+    percpu::init_core();
+    interrupts::setup_core();
+    crate::secondary_main()
 }
 
 // Define helpers
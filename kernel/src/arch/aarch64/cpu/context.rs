@@ -1,4 +1,5 @@
 // Import dependencies
+use core::{arch::asm, mem::size_of, ptr};
 use bitflags::bitflags;
 // Define Macros
 #[macro_export]
@@ -6,7 +7,7 @@ macro_rules! asm_push_context {
     // True for interruptions
     (true) => {
         concat!(
-            // Store registers x30-x0 + sp0 (user stack pointer)
+            // Store registers q31-q0, FPCR/FPSR, x30-x0 + sp0 (user stack pointer)
             concat!($crate::asm_push_context!(@reg_store), "\n"),
             // Store interrupt specific data
             "
@@ -21,7 +22,7 @@ macro_rules! asm_push_context {
     };
     (false) => {
         concat!(
-            // Store registers x30-x0 + sp0 (user stack pointer)
+            // Store registers q31-q0, FPCR/FPSR, x30-x0 + sp0 (user stack pointer)
             concat!($crate::asm_push_context!(@reg_store), "\n"),
             // Store context switch specific data
             "
@@ -31,10 +32,12 @@ macro_rules! asm_push_context {
                 str x30, [sp, #8]
                 // Save auxiliary register (to load system registers)
                 str x18, [sp]
-                // Compute Flags (NZCV | CurrentEL | SpSel)
+                // Compute Flags (NZCV | DAIF | CurrentEL | SpSel)
                 mov x30, xzr
                 mrs x18, NZCV
                 orr x30, x30, x18
+                mrs x18, DAIF
+                orr x30, x30, x18
                 mrs x18, CurrentEl
                 orr x30, x30, x18
                 mrs x18, SPSel
@@ -52,6 +55,27 @@ macro_rules! asm_push_context {
     // Implementation details
     (@reg_store) => {
         "
+            // Store q31-q0 (FP/SIMD is enabled at boot, so the compiler uses it anywhere)
+            stp q30, q31, [sp, #-32]!
+            stp q28, q29, [sp, #-32]!
+            stp q26, q27, [sp, #-32]!
+            stp q24, q25, [sp, #-32]!
+            stp q22, q23, [sp, #-32]!
+            stp q20, q21, [sp, #-32]!
+            stp q18, q19, [sp, #-32]!
+            stp q16, q17, [sp, #-32]!
+            stp q14, q15, [sp, #-32]!
+            stp q12, q13, [sp, #-32]!
+            stp q10, q11, [sp, #-32]!
+            stp  q8,  q9, [sp, #-32]!
+            stp  q6,  q7, [sp, #-32]!
+            stp  q4,  q5, [sp, #-32]!
+            stp  q2,  q3, [sp, #-32]!
+            stp  q0,  q1, [sp, #-32]!
+
+            // Alloc FPCR and FPSR (stored along USP)
+            sub  sp,  sp, #16
+
             // Store x30-x1
             stp x29, x30, [sp, #-16]!
             stp x27, x28, [sp, #-16]!
@@ -76,6 +100,10 @@ macro_rules! asm_push_context {
             str  x0, [sp, #8]
             mrs  x0, SP_EL0
             str  x0, [sp]
+            mrs  x0, FPCR
+            str  x0, [sp, #16 * 16]
+            mrs  x0, FPSR
+            str  x0, [sp, #16 * 16 + 8]
             ldr  x0, [sp, #8]
         "
     };
@@ -94,7 +122,7 @@ macro_rules! asm_pop_context {
                 msr SPSR_EL1, x0
                 msr  ELR_EL1, x1
             ",
-            // Load registers sp0 (user stack pointer) + x0-x30
+            // Load registers sp0 (user stack pointer) + x0-x30, FPCR/FPSR, q0-q31
             concat!($crate::asm_pop_context!(@reg_load), "\n"),
         )
     };
@@ -108,7 +136,7 @@ macro_rules! asm_pop_context {
                 msr SPSR_EL1, x0
                 msr  ELR_EL1, x1
             ",
-            // Load registers sp0 (user stack pointer) + x0-x30
+            // Load registers sp0 (user stack pointer) + x0-x30, FPCR/FPSR, q0-q31
             concat!($crate::asm_pop_context!(@reg_load), "\n"),
             // // Include symbol to jump after
             // "99: ret"
@@ -122,6 +150,12 @@ macro_rules! asm_pop_context {
             ldp  x1,  x0, [sp], #16
             msr   SP_EL0, x1 
 
+            // Load FPCR and FPSR
+            ldr  x1, [sp, #16 * 15]
+            msr FPCR, x1
+            ldr  x1, [sp, #16 * 15 + 8]
+            msr FPSR, x1
+
             // Load x1-x30
             ldp  x1,  x2, [sp], #16
             ldp  x3,  x4, [sp], #16
@@ -138,6 +172,25 @@ macro_rules! asm_pop_context {
             ldp x25, x26, [sp], #16
             ldp x27, x28, [sp], #16
             ldp x29, x30, [sp], #16
+
+            // Load q0-q31
+            add  sp,  sp, #16
+            ldp  q0,  q1, [sp], #32
+            ldp  q2,  q3, [sp], #32
+            ldp  q4,  q5, [sp], #32
+            ldp  q6,  q7, [sp], #32
+            ldp  q8,  q9, [sp], #32
+            ldp q10, q11, [sp], #32
+            ldp q12, q13, [sp], #32
+            ldp q14, q15, [sp], #32
+            ldp q16, q17, [sp], #32
+            ldp q18, q19, [sp], #32
+            ldp q20, q21, [sp], #32
+            ldp q22, q23, [sp], #32
+            ldp q24, q25, [sp], #32
+            ldp q26, q27, [sp], #32
+            ldp q28, q29, [sp], #32
+            ldp q30, q31, [sp], #32
         "
     };
 }
@@ -208,8 +261,8 @@ pub struct Context {
     x10: usize,
     x11: usize,
     x12: usize,
-    x14: usize,
     x13: usize,
+    x14: usize,
     x15: usize,
     x16: usize,
     x17: usize,
@@ -227,5 +280,88 @@ pub struct Context {
     x29: usize,
     /// Link Register
     x30: usize, 
+    /// Floating-point control and status
+    fpcr: u64,
+    fpsr: u64,
+    /// FP/SIMD registers
+    q: [u128; 32],
     // Higher address
+}
+
+/// Outcome of an exception handler that may switch threads.
+///
+/// Returned in `x0`/`x1`: the exception exit resumes `resume` and, when
+/// `publish` is not null, stores the interrupted context into it once its
+/// stack is no longer in use.
+#[repr(C)]
+pub struct ContextSwitch {
+    pub resume: *mut Context,
+    pub publish: *mut *mut Context,
+}
+// The exception frame pushed by the macros must match `Context`, FP/SIMD state
+// included, since a preempted thread may be resumed by `switch_to` and back
+const _: () = assert!(size_of::<Context>() == 8 * (3 + 31) + 8 * 2 + 16 * 32);
+
+// Implement structs
+impl Context {
+    /// Builds the initial context of a kernel thread (EL1h, interrupts enabled),
+    /// which starts running `entry` with `arg` as its first argument.
+    pub fn new_kernel(entry: usize, arg: usize) -> Self {
+        Self {
+            flags: Flags::EL_1 | Flags::SP_N,
+            pc: ptr::invalid(entry),
+            usp: ptr::null(),
+            x00: arg,
+            x01: 0, x02: 0, x03: 0, x04: 0, x05: 0, x06: 0, x07: 0, x08: 0, x09: 0,
+            x10: 0, x11: 0, x12: 0, x13: 0, x14: 0, x15: 0, x16: 0, x17: 0, x18: 0, x19: 0,
+            x20: 0, x21: 0, x22: 0, x23: 0, x24: 0, x25: 0, x26: 0, x27: 0, x28: 0,
+            // End of the frame chain
+            x29: 0,
+            x30: 0,
+            fpcr: 0,
+            fpsr: 0,
+            q: [0; 32],
+        }
+    }
+
+    /// Whether IRQs were masked in the saved context
+    pub fn irqs_masked(&self) -> bool {
+        self.flags.contains(Flags::I)
+    }
+}
+impl ContextSwitch {
+    /// Returns to the interrupted context
+    pub fn resume(context: *mut Context) -> Self {
+        Self {
+            resume: context,
+            publish: ptr::null_mut(),
+        }
+    }
+}
+// Define procedures
+/// Saves the running context on its own stack, publishes it into `*prev`
+/// and resumes `next`.
+///
+/// The store into `*prev` has release semantics and is done once this
+/// core no longer uses the previous stack, so other cores may resume the
+/// saved context as soon as they observe it.
+///
+/// # Safety
+///
+/// `next` must be a context saved by `switch_to` or an exception entry (or built
+/// by `Context::new_kernel`) whose stack is not in use, and IRQs should be masked.
+#[naked]
+pub unsafe extern "C" fn switch_to(prev: *mut *mut Context, next: *mut Context) {
+    asm!(
+        // Save the context (returns to the caller when resumed)
+        asm_push_context!(false),
+        // Switch to the next stack, then publish the saved context
+        "mov x9, sp",
+        "mov sp, x1",
+        "stlr x9, [x0]",
+        // Restore the next context
+        asm_pop_context!(false),
+        "eret",
+        options(noreturn)
+    )
 }
\ No newline at end of file
@@ -1,33 +1,94 @@
 // Import dependencies
-use core::arch::asm;
+use core::{arch::asm, sync::atomic::{AtomicBool, Ordering}};
 use super::ExceptionLevel;
 use super::interrupts::VectorTable;
+use crate::sync::once::Lazy;
 // Declare modules
 pub mod context;
+pub mod percpu;
 // Define constants
 pub const CORE_ID_MASK: u64 = 0b11;
+pub const CORE_COUNT: usize = 4;
+const DAIF_IRQ: u64 = 1 << 7;
+const ISAR0_ATOMIC_SHIFT: u64 = 20;
+const ISAR0_ATOMIC_MASK: u64 = 0b1111;
+const ISAR0_ATOMIC_LSE: u64 = 0b0010;
+// Define globals
+/// Set by the boot core once secondary cores may start
+///
+/// Kept out of `.bss`: the secondary cores read it before the boot core clears it.
+#[link_section = ".data.secondary_release"]
+static SECONDARY_RELEASE: AtomicBool = AtomicBool::new(false);
+static LSE_SUPPORTED: Lazy<bool> = Lazy::new(|| unsafe {
+    // ARMv8.1 atomic instructions (ID_AA64ISAR0_EL1.Atomic)
+    (id_aa64isar0() >> ISAR0_ATOMIC_SHIFT) & ISAR0_ATOMIC_MASK >= ISAR0_ATOMIC_LSE
+});
+// Define structs
+/// Masks IRQs on the current core until dropped,
+/// restoring the previous mask afterwards (nesting is allowed).
+pub struct IrqGuard {
+    daif: u64,
+}
+// Implement structs
+impl IrqGuard {
+    pub fn new() -> Self {
+        unsafe {
+            let daif = daif();
+            mask_irq();
+            Self { daif }
+        }
+    }
+}
+
+impl Drop for IrqGuard {
+    fn drop(&mut self) {
+        // Only unmask if IRQs were enabled when the guard was taken
+        if self.daif & DAIF_IRQ == 0 {
+            unsafe { unmask_irq() }
+        }
+    }
+}
 // Define interface functions
-#[inline(always)]
-pub fn park_non_main_cores() {
+/// Holds a secondary core until the boot core calls `release_secondary_cores`.
+pub fn wait_for_release() {
+    while !SECONDARY_RELEASE.load(Ordering::Acquire) {
+        unsafe { wfe() }
+    }
+}
+
+/// Lets the secondary cores waiting in `wait_for_release` continue booting.
+pub fn release_secondary_cores() {
+    SECONDARY_RELEASE.store(true, Ordering::Release);
     unsafe {
-        if core_id() != 0 {
-            park_core()
-        }
+        asm!("dsb sy", options(nostack, preserves_flags));
+        sev();
     }
 }
 
 #[inline(always)]
-pub unsafe fn park_core() {
+pub unsafe fn park_core() -> ! {
     loop {
         wfi();
     }
 }
 
+#[inline(always)]
+pub fn has_lse() -> bool {
+    *LSE_SUPPORTED
+}
+
 #[inline(always)]
 pub unsafe fn core_id() -> u64 {
     mpidr() & CORE_ID_MASK
 }
 
+/// Index of the calling core, for per-core arrays
+#[inline(always)]
+pub fn this_core() -> usize {
+    // SAFETY: Reading MPIDR_EL1 has no side effects
+    unsafe { core_id() as usize }
+}
+
 // Define low-level functions
 #[inline(always)]
 pub unsafe fn mpidr() -> u64 {
@@ -36,11 +97,160 @@ pub unsafe fn mpidr() -> u64 {
     return mpidr;
 }
 
+#[inline(always)]
+pub unsafe fn tpidr_el1() -> u64 {
+    let mut tpidr: u64;
+    asm!("mrs {tpidr}, tpidr_el1", tpidr = out(reg) tpidr, options(nomem, nostack, preserves_flags));
+    return tpidr;
+}
+
+#[inline(always)]
+pub unsafe fn set_tpidr_el1(tpidr: u64) {
+    asm!("msr tpidr_el1, {tpidr}", tpidr = in(reg) tpidr, options(nomem, nostack, preserves_flags))
+}
+
+#[inline(always)]
+pub unsafe fn daif() -> u64 {
+    let mut daif: u64;
+    asm!("mrs {daif}, daif", daif = out(reg) daif, options(nomem, nostack, preserves_flags));
+    return daif;
+}
+
+#[inline(always)]
+pub unsafe fn mask_irq() {
+    asm!("msr daifset, #2", options(nostack, preserves_flags))
+}
+
+#[inline(always)]
+pub unsafe fn unmask_irq() {
+    asm!("msr daifclr, #2", options(nostack, preserves_flags))
+}
+
+#[inline(always)]
+pub fn irqs_masked() -> bool {
+    unsafe { daif() & DAIF_IRQ != 0 }
+}
+
+#[inline(always)]
+pub unsafe fn id_aa64isar0() -> u64 {
+    let mut isar0: u64;
+    asm!("mrs {isar0}, id_aa64isar0_el1", isar0 = out(reg) isar0, options(nomem, nostack, preserves_flags));
+    return isar0;
+}
+
+/// Virtual counter (ticks at `counter_frequency` Hz)
+#[inline(always)]
+pub fn counter() -> u64 {
+    let mut cnt: u64;
+    unsafe { asm!("isb", "mrs {cnt}, cntvct_el0", cnt = out(reg) cnt, options(nomem, nostack, preserves_flags)) };
+    return cnt;
+}
+
+#[inline(always)]
+pub fn counter_frequency() -> u64 {
+    let mut frq: u64;
+    unsafe { asm!("mrs {frq}, cntfrq_el0", frq = out(reg) frq, options(nomem, nostack, preserves_flags)) };
+    return frq;
+}
+
 #[inline(always)]
 pub unsafe fn wfi() {
     asm!("wfi")
 }
 
+/// Invalidates every TLB entry of the calling core.
+#[inline(always)]
+pub unsafe fn flush_tlb_all() {
+    asm!("dsb ishst", "tlbi vmalle1", "dsb nsh", "isb", options(nostack, preserves_flags))
+}
+
+/// Invalidates the TLB entries of the page holding `address` on the calling core (any ASID).
+#[inline(always)]
+pub unsafe fn flush_tlb_page(address: usize) {
+    asm!("dsb ishst", "tlbi vaae1, {page}", "dsb nsh", "isb", page = in(reg) address >> 12, options(nostack, preserves_flags))
+}
+
+/// Size of the smallest data cache line (`CTR_EL0.DminLine`)
+#[inline(always)]
+pub fn dcache_line_size() -> usize {
+    let mut ctr: u64;
+    unsafe { asm!("mrs {ctr}, ctr_el0", ctr = out(reg) ctr, options(nomem, nostack, preserves_flags)) };
+    4 << ((ctr >> 16) & 0xF)
+}
+
+/// Writes the cached data of `start..start + len` back to memory, for devices reading it.
+pub fn clean_dcache_range(start: usize, len: usize) {
+    let line = dcache_line_size();
+    for address in (start & !(line - 1)..start + len).step_by(line) {
+        unsafe { asm!("dc cvac, {address}", address = in(reg) address, options(nostack, preserves_flags)) };
+    }
+    unsafe { asm!("dsb sy", options(nostack, preserves_flags)) };
+}
+
+/// Drops the cached data of `start..start + len` (after writing it back), so the
+/// next reads see what devices wrote.
+pub fn invalidate_dcache_range(start: usize, len: usize) {
+    let line = dcache_line_size();
+    for address in (start & !(line - 1)..start + len).step_by(line) {
+        unsafe { asm!("dc civac, {address}", address = in(reg) address, options(nostack, preserves_flags)) };
+    }
+    unsafe { asm!("dsb sy", options(nostack, preserves_flags)) };
+}
+
+/// Completes the earlier stores before any later device access (like raising an IPI).
+#[inline(always)]
+pub fn store_barrier() {
+    unsafe { asm!("dsb ishst", options(nostack, preserves_flags)) }
+}
+
+#[inline(always)]
+pub unsafe fn wfe() {
+    asm!("wfe", options(nomem, nostack, preserves_flags))
+}
+
+#[inline(always)]
+pub unsafe fn sev() {
+    asm!("sev", options(nomem, nostack, preserves_flags))
+}
+
+/// Sleeps in `wfe` while the byte at `flag` is non-zero.
+///
+/// The load-exclusive arms the exclusive monitor, so a store to the
+/// flag from another core (or a `sev`) wakes this core up.
+#[inline(always)]
+pub unsafe fn wait_while_set(flag: *const u8) {
+    asm!(
+        "sevl",
+        "2:",
+        "wfe",
+        "ldxrb {state:w}, [{flag}]",
+        "cbnz {state:w}, 2b",
+        flag = in(reg) flag,
+        state = out(reg) _,
+        options(nostack)
+    )
+}
+
+/// Atomically swaps the byte at `ptr` (acquire), using the ARMv8.1 `swpab` instruction.
+///
+/// # Safety
+///
+/// The CPU must implement LSE (see `has_lse`).
+#[cfg(feature = "lse")]
+#[inline(always)]
+pub unsafe fn swap_acquire_u8(ptr: *mut u8, value: u8) -> u8 {
+    let mut old: u32;
+    asm!(
+        ".arch_extension lse",
+        "swpab {value:w}, {old:w}, [{ptr}]",
+        ptr = in(reg) ptr,
+        value = in(reg) value as u32,
+        old = out(reg) old,
+        options(nostack)
+    );
+    return old as u8;
+}
+
 #[inline(always)]
 unsafe fn vbar_el1(table: &VectorTable) {
     asm!("msr VBAR_EL1, {}", in(reg) table)
@@ -0,0 +1,179 @@
+// Import dependencies
+use core::{cell::{Cell, UnsafeCell}, marker::PhantomData, ops::{Deref, DerefMut}, ptr, sync::atomic::{AtomicUsize, Ordering}};
+use super::{this_core, tpidr_el1, set_tpidr_el1, IrqGuard, CORE_COUNT};
+// Link with global labels
+extern "C" {
+    #[link_name = "__percpu_start__"]
+    static percpu_start: u8;
+    #[link_name = "__percpu_end__"]
+    static percpu_end: u8;
+
+    #[link_name = "__percpu_areas_start__"]
+    static mut percpu_areas_start: u8;
+}
+// Define Macros
+/// Declares variables with one instance per core.
+///
+/// The initial value is stored in the `.percpu` template section,
+/// which is replicated for every core at boot.
+#[macro_export]
+macro_rules! per_cpu {
+    ($($(#[$attr:meta])* $vis:vis static $name:ident: $ty:ty = $init:expr;)*) => {
+        $(
+            $(#[$attr])*
+            #[link_section = ".percpu"]
+            $vis static $name: $crate::arch::cpu::percpu::PerCpu<$ty> =
+                // SAFETY: Placed in the per-CPU template section
+                unsafe { $crate::arch::cpu::percpu::PerCpu::new($init) };
+        )*
+    };
+}
+// Export Macros
+pub(crate) use per_cpu;
+// Define structs
+/// Per-CPU variable, only declared through [`per_cpu!`].
+///
+/// The static itself is the template: each core accesses its own copy,
+/// located at the template address plus the offset kept in `TPIDR_EL1`.
+#[repr(C)]
+pub struct PerCpu<T> {
+    borrowed: Cell<bool>,
+    value: UnsafeCell<T>,
+}
+
+/// Exclusive access to the current core's instance.
+///
+/// IRQs stay masked while the guard is alive, so the borrow can
+/// neither migrate to another core nor race with an interrupt handler.
+pub struct PerCpuGuard<'var, T> {
+    var: &'var PerCpu<T>,
+    _irq: IrqGuard,
+    _not_send: PhantomData<*mut T>,
+}
+
+// Define globals
+/// Address of the per-CPU copies, recorded by `init_areas`
+static AREAS_START: AtomicUsize = AtomicUsize::new(0);
+
+// Implement structs
+impl<T> PerCpu<T> {
+    /// # Safety
+    ///
+    /// The static must be placed in the `.percpu` section (use [`per_cpu!`]).
+    #[doc(hidden)]
+    pub const unsafe fn new(value: T) -> Self {
+        Self {
+            borrowed: Cell::new(false),
+            value: UnsafeCell::new(value),
+        }
+    }
+
+    /// Borrows the current core's instance.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the variable is already borrowed on this core.
+    pub fn get(&self) -> PerCpuGuard<'_, T> {
+        let irq = IrqGuard::new();
+        // SAFETY: TPIDR_EL1 was set up by `init_core`
+        let var = unsafe { self.local() };
+        assert!(!var.borrowed.replace(true), "Per-CPU variable already borrowed on this core");
+        PerCpuGuard {
+            var,
+            _irq: irq,
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Runs `f` with the current core's instance.
+    pub fn with<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        f(&mut self.get())
+    }
+
+    /// Returns the instance of another core.
+    ///
+    /// # Safety
+    ///
+    /// No guard to that instance may be alive while the reference is used.
+    pub unsafe fn remote(&self, core: usize) -> &T
+    where
+        T: Sync,
+    {
+        assert!(core < CORE_COUNT, "Invalid core: {}", core);
+        let var = &*self.relocate(area_offset(core));
+        &*var.value.get()
+    }
+
+    unsafe fn local(&self) -> &Self {
+        &*self.relocate(tpidr_el1() as usize)
+    }
+
+    fn relocate(&self, offset: usize) -> *const Self {
+        (self as *const Self).cast::<u8>().wrapping_add(offset).cast::<Self>()
+    }
+}
+
+impl<T> Deref for PerCpuGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        // SAFETY: Borrow flag is set and IRQs are masked
+        unsafe { &*self.var.value.get() }
+    }
+}
+
+impl<T> DerefMut for PerCpuGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: Borrow flag is set and IRQs are masked
+        unsafe { &mut *self.var.value.get() }
+    }
+}
+
+impl<T> Drop for PerCpuGuard<'_, T> {
+    fn drop(&mut self) {
+        // Release the borrow before unmasking IRQs (field drop)
+        self.var.borrowed.set(false)
+    }
+}
+
+// Implement thread safety
+// SAFETY: Each core only accesses its own copy without `remote`
+unsafe impl<T: Send> Sync for PerCpu<T> {}
+
+// Define procedures
+/// Replicates the `.percpu` template for every core.
+///
+/// # Safety
+///
+/// Must run once, on the boot core, before any core calls `init_core`.
+pub unsafe fn init_areas() {
+    let template = template();
+    let areas = ptr::addr_of_mut!(percpu_areas_start);
+    AREAS_START.store(areas.addr(), Ordering::Relaxed);
+    for core in 0..CORE_COUNT {
+        let area = areas.wrapping_add(core * template.len());
+        ptr::copy_nonoverlapping(template.as_ptr(), area, template.len());
+    }
+}
+
+/// Points `TPIDR_EL1` of the calling core to its per-CPU area.
+///
+/// # Safety
+///
+/// The areas must have been initialized by `init_areas`.
+pub unsafe fn init_core() {
+    set_tpidr_el1(area_offset(this_core()) as u64);
+}
+
+// Define helpers
+fn template() -> &'static [u8] {
+    // SAFETY: Linker defined range
+    unsafe { core::slice::from_ptr_range(&percpu_start..&percpu_end) }
+}
+
+fn area_offset(core: usize) -> usize {
+    let template = template();
+    let areas = AREAS_START.load(Ordering::Relaxed);
+    debug_assert!(areas != 0, "Per-CPU areas used before init_areas");
+    (areas + core * template.len()).wrapping_sub(template.as_ptr().addr())
+}
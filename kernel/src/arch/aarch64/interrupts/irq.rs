@@ -0,0 +1,72 @@
+// Import dependencies
+use core::{mem, sync::atomic::{AtomicUsize, Ordering}};
+
+// Define constants
+/// Number of interrupt lines that can have a handler
+pub const IRQ_COUNT: usize = 96;
+
+// Define structs
+pub type IrqHandler = fn();
+
+// Define globals
+/// Handlers stored as function addresses (0 when none), read without locks from IRQ context
+static HANDLERS: [AtomicUsize; IRQ_COUNT] = [ATOMIC_NONE; IRQ_COUNT];
+/// Controller handler, finding the pending lines and dispatching them
+static ROOT_HANDLER: AtomicUsize = AtomicUsize::new(0);
+#[allow(clippy::declare_interior_mutable_const)]
+const ATOMIC_NONE: AtomicUsize = AtomicUsize::new(0);
+
+// Define procedures
+/// Installs `handler` for `irq`.
+///
+/// Fails, returning the installed handler, if the line already has one.
+pub fn register(irq: usize, handler: IrqHandler) -> Result<(), IrqHandler> {
+    HANDLERS[irq]
+        .compare_exchange(0, handler as usize, Ordering::AcqRel, Ordering::Acquire)
+        .map(|_| ())
+        // SAFETY: Only function addresses are stored
+        .map_err(|installed| unsafe { to_handler(installed) })
+}
+
+pub fn unregister(irq: usize) -> Option<IrqHandler> {
+    match HANDLERS[irq].swap(0, Ordering::AcqRel) {
+        0 => None,
+        // SAFETY: Only function addresses are stored
+        handler => Some(unsafe { to_handler(handler) }),
+    }
+}
+
+/// Sets the handler called on every IRQ exception, which must dispatch the pending lines.
+pub fn set_root_handler(handler: IrqHandler) {
+    ROOT_HANDLER.store(handler as usize, Ordering::Release);
+}
+
+/// Runs the handler of `irq`, returning `false` if it has none.
+pub fn dispatch(irq: usize) -> bool {
+    match HANDLERS.get(irq).map(|handler| handler.load(Ordering::Acquire)) {
+        None | Some(0) => false,
+        Some(handler) => {
+            // SAFETY: Only function addresses are stored
+            let handler = unsafe { to_handler(handler) };
+            handler();
+            true
+        }
+    }
+}
+
+/// Handles an IRQ exception (called with IRQs masked).
+pub(super) fn handle() {
+    match ROOT_HANDLER.load(Ordering::Acquire) {
+        0 => panic!("IRQ taken without an interrupt controller"),
+        // SAFETY: Only function addresses are stored
+        handler => {
+            let handler = unsafe { to_handler(handler) };
+            handler()
+        }
+    }
+}
+
+// Define helpers
+unsafe fn to_handler(address: usize) -> IrqHandler {
+    mem::transmute::<usize, IrqHandler>(address)
+}
@@ -4,11 +4,12 @@ use crate::exception_handler;
 use self::vector_table::ExceptionStack;
 
 use super::{cpu, ExceptionLevel};
-use super::cpu::context::Context;
-use armv8a_semihosting::hprintln;
+use super::cpu::context::{Context, ContextSwitch};
+use crate::console::println;
 use vector_table::{ExceptionKind, ExceptionRelativeLevel};
 use enum_iterator::all;
 // Define modules
+pub mod irq;
 mod vector_table;
 // Export structs
 pub use vector_table::VectorTable;
@@ -21,16 +22,33 @@ pub unsafe fn setup_interrupts() {
     // Set default handlers
     vt_el1.set_default_handler(exception_handler!(default_handler));
     // Set specific handlers
-    vt_el1.set_kind_handler(ExceptionKind::Sync, exception_handler!(handler_sync));
+    vt_el1.set_kind_handler(ExceptionKind::Sync, exception_handler!(switching handler_sync));
+    vt_el1.set_kind_handler(ExceptionKind::Irq, exception_handler!(switching handler_irq));
     // Update VBARs
     cpu::vbar(ExceptionLevel::El1, &vt_el1);
     
 }
+
+/// Installs the vector tables (set up by `setup_interrupts`) on the calling core.
+pub unsafe fn setup_core() {
+    let vt_el1 = VECTOR_TABLE_EL1.lock();
+    cpu::vbar(ExceptionLevel::El1, &vt_el1);
+}
 // Define Interruption Handlers
 extern "C" fn default_handler(ctx: Context) {
     unimplemented!()
 }
 
-extern "C" fn handler_sync(ctx: Context) {
-    hprintln!("Pemba: {:?}", ctx);
+extern "C" fn handler_sync(ctx: &mut Context) -> ContextSwitch {
+    println!("Pemba: {:?}", ctx);
+    crate::task::scheduler::exception_return(ctx)
+}
+
+extern "C" fn handler_irq(ctx: &mut Context) -> ContextSwitch {
+    let start = cpu::counter();
+    irq::handle();
+    crate::random::add_interrupt_randomness(start);
+    crate::task::softirq::irq_exit();
+    crate::task::stats::account_irq(start);
+    crate::task::scheduler::exception_return(ctx)
 }
\ No newline at end of file
@@ -38,7 +38,8 @@ macro_rules! static_vector_table {
             $crate::static_vector_table!(handler "43"),
             $crate::static_vector_table!(handler "44"),
 
-            // Jump address table
+            // Jump address table (right after the 0x800 bytes of handlers code)
+            ".balign 0x800",
             // Current exception level - Sp 0
             $crate::static_vector_table!(entry "11"),
             $crate::static_vector_table!(entry "12"),
@@ -60,11 +61,16 @@ macro_rules! static_vector_table {
             $crate::static_vector_table!(entry "43"),
             $crate::static_vector_table!(entry "44"),
 
-            // Spinlock - Atomic Bool
-            ".byte 0x0",
+            // Padding up to the size of the table (the lock comes right after it)
+            ".balign {1}",
+            // Spinlock - Atomic Bool (and counters with lock statistics)
+            ".space {2}",
 
             // Format configurations
-            sym $vector_table_name
+            sym $vector_table_name,
+            const core::mem::align_of::<crate::arch::aarch64::interrupts::vector_table::VectorTable>(),
+            const core::mem::size_of::<crate::sync::spin::Spinlock<crate::arch::aarch64::interrupts::vector_table::VectorTable>>()
+                - core::mem::size_of::<crate::arch::aarch64::interrupts::vector_table::VectorTable>()
         );
     };
 
@@ -115,9 +121,41 @@ macro_rules! exception_handler {
             }
         }
     };
+    // Handler returning a `ContextSwitch`, allowing to leave into another context
+    (switching $handler:ident) => {
+        {
+            paste::paste! {
+                #[naked]
+                unsafe extern "C" fn [< __asm_eh_ $handler >]() -> ! {
+                    asm!(
+                        // Restore x29 and x30
+                        "ldp x29, x30, [sp], #16",
+                        // Persist Context (FP/SIMD included, the resumed thread may differ)
+                        $crate::arch::aarch64::cpu::context::asm_push_context!(true),
+                        // Call handler with context as argument
+                        "mov x0, sp",
+                        "bl {handler}",
+                        // Move to the resumed context, then publish the interrupted one
+                        "mov x9, sp",
+                        "mov sp, x0",
+                        "cbz x1, 2f",
+                        "stlr x9, [x1]",
+                        "2:",
+                        $crate::arch::aarch64::cpu::context::asm_pop_context!(true),
+                        "eret",
+                        handler = sym $handler,
+                        options(noreturn)
+                    );
+                }
+                // Return reference to the actual handler
+                [< __asm_eh_ $handler >]
+            }
+        }
+    };
 }
 
 use enum_iterator::Sequence;
+use core::mem::{align_of, size_of};
 // Export macros
 pub(crate) use static_vector_table;
 pub(crate) use exception_handler;
@@ -128,6 +166,13 @@ pub struct VectorTable {
     handlers: [unsafe extern "C" fn() -> !; 16],
 }
 
+// `static_vector_table!` pads the jump table up to the alignment to reach the lock
+const _: () = {
+    let end = 0x800 + size_of::<[unsafe extern "C" fn() -> !; 16]>();
+    let align = align_of::<VectorTable>();
+    assert!(size_of::<VectorTable>() == (end + align - 1) / align * align);
+};
+
 #[repr(usize)]
 #[derive(Clone, Copy, Sequence)]
 pub enum ExceptionRelativeLevel {
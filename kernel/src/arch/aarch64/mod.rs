@@ -1,7 +1,8 @@
 // Define modules
 mod boot;
-mod cpu;
-mod interrupts;
+pub mod cpu;
+pub mod interrupts;
+pub mod timer;
 // Define shared structs and constants
 pub enum ExceptionLevel {
     El1,
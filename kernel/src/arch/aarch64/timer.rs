@@ -0,0 +1,48 @@
+// Import dependencies
+use core::{arch::asm, time::Duration};
+use super::cpu;
+
+// Define constants
+/// CNTP_CTL_EL0 bits
+const CTL_ENABLE: u64 = 1 << 0;
+const CTL_IMASK: u64 = 1 << 1;
+
+// Define procedures
+/// Time elapsed since the counter started
+pub fn uptime() -> Duration {
+    ticks_to_duration(cpu::counter())
+}
+
+pub fn ticks_to_duration(ticks: u64) -> Duration {
+    let frequency = cpu::counter_frequency();
+    let secs = ticks / frequency;
+    let nanos = (ticks % frequency) * 1_000_000_000 / frequency;
+    Duration::new(secs, nanos as u32)
+}
+
+pub fn duration_to_ticks(duration: Duration) -> u64 {
+    let frequency = cpu::counter_frequency() as u128;
+    (duration.as_nanos() * frequency / 1_000_000_000) as u64
+}
+
+/// Arms the physical timer of the calling core to fire after `ticks` counter ticks.
+///
+/// Re-arming also acknowledges a pending timer interrupt.
+pub fn arm(ticks: u64) {
+    let ticks = ticks.min(i32::MAX as u64);
+    unsafe {
+        asm!(
+            "msr cntp_tval_el0, {tval}",
+            "msr cntp_ctl_el0, {ctl}",
+            "isb",
+            tval = in(reg) ticks,
+            ctl = in(reg) CTL_ENABLE,
+            options(nomem, nostack, preserves_flags)
+        )
+    };
+}
+
+/// Stops the physical timer of the calling core.
+pub fn disarm() {
+    unsafe { asm!("msr cntp_ctl_el0, {ctl}", "isb", ctl = in(reg) CTL_IMASK, options(nomem, nostack, preserves_flags)) };
+}
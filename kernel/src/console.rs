@@ -0,0 +1,190 @@
+// Import dependencies
+use core::{fmt::{self, Write}, sync::atomic::{AtomicU8, Ordering}};
+use armv8a_semihosting::hio::{self, HStdout};
+use bitflags::bitflags;
+use crate::arch::cpu::IrqGuard;
+use crate::drivers::{framebuffer::{self, text}, mini_uart, pl011};
+use crate::sync::spin::Spinlock;
+
+// Define macros
+/// Prints to the console.
+#[macro_export]
+macro_rules! print {
+    ($($arg:tt)*) => {
+        $crate::console::print_fmt(format_args!($($arg)*))
+    };
+}
+
+/// Prints to the console, with a newline.
+#[macro_export]
+macro_rules! println {
+    () => {
+        $crate::console::print_fmt(format_args!("\n"))
+    };
+    ($($arg:tt)*) => {
+        $crate::console::print_fmt(format_args!("{}\n", format_args!($($arg)*)))
+    };
+}
+// Export macros
+pub(crate) use print;
+pub(crate) use println;
+
+// Define constants
+/// UART set up by `init`
+#[cfg(not(feature = "mini-uart-console"))]
+pub const DEFAULT_UART: Uart = Uart::Pl011;
+#[cfg(feature = "mini-uart-console")]
+pub const DEFAULT_UART: Uart = Uart::MiniUart;
+#[cfg(not(feature = "semihosting-console"))]
+const DEFAULT_SINKS: Sinks = Sinks::UART.union(Sinks::FRAMEBUFFER);
+#[cfg(feature = "semihosting-console")]
+const DEFAULT_SINKS: Sinks = Sinks::UART.union(Sinks::FRAMEBUFFER).union(Sinks::SEMIHOSTING);
+
+// Define structs
+bitflags! {
+    /// Outputs the console writes to
+    pub struct Sinks: u8 {
+        /// UART selected by `init_uart` (once set up)
+        const UART = 1 << 0;
+        /// Host stdout through semihosting (needs a debugger or QEMU with semihosting enabled)
+        const SEMIHOSTING = 1 << 1;
+        /// Text console on the framebuffer (once set up)
+        const FRAMEBUFFER = 1 << 2;
+    }
+}
+
+/// UART backing the console
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Uart {
+    /// PL011 UART0 (taken by Bluetooth on some boards)
+    Pl011 = 0,
+    /// AUX mini UART
+    MiniUart = 1,
+}
+
+struct Console {
+    /// Opened on first use
+    semihosting: Option<HStdout>,
+}
+
+/// Writer used on panic, which takes no lock
+struct PanicConsole;
+
+// Define globals
+static SINKS: AtomicU8 = AtomicU8::new(DEFAULT_SINKS.bits());
+static UART: AtomicU8 = AtomicU8::new(DEFAULT_UART as u8);
+/// Keeps the lines of concurrent prints apart
+static CONSOLE: Spinlock<Console> = Spinlock::new(Console { semihosting: None });
+
+// Define procedures
+/// Sets up the UART of the console chosen at build time, and the framebuffer
+/// text console if the firmware provides a framebuffer.
+pub fn init() {
+    init_uart(DEFAULT_UART);
+    if let Ok(framebuffer) = framebuffer::init(framebuffer::DEFAULT_WIDTH, framebuffer::DEFAULT_HEIGHT) {
+        text::init(framebuffer);
+    }
+}
+
+/// Sets up `uart` and moves the console to it (to choose it at boot).
+pub fn init_uart(uart: Uart) {
+    match uart {
+        Uart::Pl011 => pl011::init(pl011::DEFAULT_BAUD_RATE),
+        Uart::MiniUart => mini_uart::init(mini_uart::DEFAULT_BAUD_RATE),
+    }
+    UART.store(uart as u8, Ordering::Release);
+}
+
+pub fn uart() -> Uart {
+    match UART.load(Ordering::Acquire) {
+        0 => Uart::Pl011,
+        _ => Uart::MiniUart,
+    }
+}
+
+/// Blocks the calling thread until the console UART receives a byte.
+pub fn read_byte() -> u8 {
+    match uart() {
+        Uart::Pl011 => pl011::read_byte(),
+        Uart::MiniUart => mini_uart::read_byte(),
+    }
+}
+
+/// Selects the outputs of the console.
+pub fn set_sinks(sinks: Sinks) {
+    SINKS.store(sinks.bits(), Ordering::Relaxed);
+}
+
+pub fn sinks() -> Sinks {
+    Sinks::from_bits_truncate(SINKS.load(Ordering::Relaxed))
+}
+
+/// Backend of `print!` and `println!`.
+pub fn print_fmt(args: fmt::Arguments) {
+    let _irq = IrqGuard::new();
+    CONSOLE.lock().write_fmt(args).ok();
+}
+
+/// Prints without taking the console lock, for panics.
+pub fn panic_print_fmt(args: fmt::Arguments) {
+    PanicConsole.write_fmt(args).ok();
+}
+
+// Implement structs
+impl Write for Console {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let sinks = sinks();
+        if sinks.contains(Sinks::UART) {
+            write_uart(s, false);
+        }
+        if sinks.contains(Sinks::SEMIHOSTING) {
+            if self.semihosting.is_none() {
+                self.semihosting = hio::hstdout().ok();
+            }
+            if let Some(hstdout) = &mut self.semihosting {
+                hstdout.write_str(s)?;
+            }
+        }
+        if sinks.contains(Sinks::FRAMEBUFFER) {
+            text::write_str(s);
+        }
+        Ok(())
+    }
+}
+
+impl Write for PanicConsole {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let sinks = sinks();
+        if sinks.contains(Sinks::UART) {
+            write_uart(s, true);
+        }
+        if sinks.contains(Sinks::SEMIHOSTING) {
+            hio::hstdout().and_then(|mut hstdout| hstdout.write_all(s.as_bytes())).map_err(|_| fmt::Error)?;
+        }
+        if sinks.contains(Sinks::FRAMEBUFFER) {
+            text::try_write_str(s);
+        }
+        Ok(())
+    }
+}
+
+// Define helpers
+/// Writes `s` to the console UART if it is set up, turning newlines into CRLF for terminals.
+fn write_uart(s: &str, polled: bool) {
+    let (ready, write): (bool, fn(&[u8])) = match (uart(), polled) {
+        (Uart::Pl011, false) => (pl011::is_ready(), pl011::write_bytes),
+        (Uart::Pl011, true) => (pl011::is_ready(), pl011::write_bytes_polled),
+        (Uart::MiniUart, false) => (mini_uart::is_ready(), mini_uart::write_bytes),
+        (Uart::MiniUart, true) => (mini_uart::is_ready(), mini_uart::write_bytes_polled),
+    };
+    if !ready {
+        return;
+    }
+    for (index, line) in s.split('\n').enumerate() {
+        if index != 0 {
+            write(b"\r\n");
+        }
+        write(line.as_bytes());
+    }
+}
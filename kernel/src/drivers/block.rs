@@ -0,0 +1,21 @@
+// Import dependencies
+use core::fmt;
+
+// Define constants
+/// Size of the blocks transferred by block devices
+pub const BLOCK_SIZE: usize = 512;
+
+// Define structs
+/// Storage addressed in `BLOCK_SIZE` blocks
+pub trait BlockDevice {
+    type Error: fmt::Debug + fmt::Display;
+
+    /// Capacity of the device, in blocks
+    fn block_count(&self) -> u64;
+
+    /// Reads the blocks from `block` on into `buffer` (a whole number of blocks).
+    fn read_blocks(&self, block: u64, buffer: &mut [u8]) -> Result<(), Self::Error>;
+
+    /// Writes `buffer` (a whole number of blocks) to the blocks from `block` on.
+    fn write_blocks(&self, block: u64, buffer: &[u8]) -> Result<(), Self::Error>;
+}
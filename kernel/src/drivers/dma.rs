@@ -0,0 +1,499 @@
+// Import dependencies
+use core::{cell::UnsafeCell, fmt, hint, mem, sync::atomic::{AtomicU16, AtomicU32, AtomicUsize, Ordering}, time::Duration};
+use crate::arch::cpu;
+use crate::task::Event;
+use crate::time::Timer;
+use super::gpu_intc::{self, IRQ_DMA_0, IRQ_DRIVEN};
+use super::mailbox;
+use super::mmio::{registers, ReadOnly, ReadWrite};
+use super::PERIPHERAL_BASE;
+
+// Define constants
+/// BCM2835 DMA controller (channel 15, elsewhere, is left to the GPU)
+const DMA_BASE: usize = PERIPHERAL_BASE + 0x7000;
+const CHANNEL_STRIDE: usize = 0x100;
+const GLOBAL_OFFSET: usize = 0xFE0;
+pub const CHANNEL_COUNT: usize = 15;
+/// Channels from this one on are "lite" (shorter transfers, no 2D mode)
+const FIRST_LITE_CHANNEL: usize = 7;
+/// Channels commonly left to the ARM, if the firmware cannot tell
+const DEFAULT_CHANNEL_MASK: u16 = 0x7F35;
+/// Longest segment of a full and of a lite channel, in bytes
+const MAX_LENGTH: usize = (1 << 30) - 1;
+const MAX_LITE_LENGTH: usize = 0xFFFF;
+/// Most segments in a chain
+pub const MAX_SEGMENTS: usize = 16;
+/// Time between completion checks of the running channels, when not `IRQ_DRIVEN`
+const POLL_INTERVAL: Duration = Duration::from_millis(1);
+/// Channels 11 to 14 share the interrupt line of channel 11
+const SHARED_IRQ_CHANNEL: usize = 11;
+/// GPU bus aliases of the ARM memory (bypassing the GPU L2 cache) and of the peripherals
+const BUS_UNCACHED: usize = 0xC000_0000;
+const BUS_PERIPHERALS: usize = 0x7E00_0000;
+const BUS_ADDRESS_MASK: usize = 0x3FFF_FFFF;
+/// Control and status bits
+const CS_ACTIVE: u32 = 1 << 0;
+const CS_END: u32 = 1 << 1;
+const CS_INT: u32 = 1 << 2;
+const CS_ERROR: u32 = 1 << 8;
+const CS_PRIORITY: u32 = 8 << 16;
+const CS_PANIC_PRIORITY: u32 = 15 << 20;
+const CS_WAIT_FOR_OUTSTANDING_WRITES: u32 = 1 << 28;
+const CS_RESET: u32 = 1 << 31;
+/// Transfer information bits
+const TI_INTEN: u32 = 1 << 0;
+const TI_WAIT_RESP: u32 = 1 << 3;
+const TI_DEST_INC: u32 = 1 << 4;
+const TI_DEST_DREQ: u32 = 1 << 6;
+const TI_SRC_INC: u32 = 1 << 8;
+const TI_SRC_DREQ: u32 = 1 << 10;
+const TI_PERMAP_SHIFT: u32 = 16;
+/// Debug register error bits (write 1 to clear)
+const DEBUG_ERRORS: u32 = 0b111;
+/// Outcome of the last transfer of a channel
+const RESULT_OK: u32 = 0;
+const RESULT_FAILED: u32 = 1 << 31;
+const RESULT_ABORTED: u32 = 1 << 30;
+/// Peripherals pacing transfers (DREQ lines)
+pub const DREQ_PCM_TX: u32 = 2;
+pub const DREQ_PCM_RX: u32 = 3;
+pub const DREQ_PWM: u32 = 5;
+pub const DREQ_SPI_TX: u32 = 6;
+pub const DREQ_SPI_RX: u32 = 7;
+pub const DREQ_EMMC: u32 = 11;
+pub const DREQ_UART_TX: u32 = 12;
+pub const DREQ_SDHOST: u32 = 13;
+pub const DREQ_UART_RX: u32 = 14;
+
+// Define structs
+/// DMA channel owned until dropped
+///
+/// Starting and waiting for transfers borrows it mutably, so that only one
+/// thread at a time drives it.
+pub struct Channel {
+    index: usize,
+}
+
+/// Side of a segment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endpoint {
+    /// Memory buffer at this address, walked through
+    Memory(usize),
+    /// Peripheral register at this (ARM) address, paced by a DREQ line if any
+    Peripheral { address: usize, dreq: Option<u32> },
+}
+
+/// Copy of `length` bytes, one control block of a chain
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Segment {
+    pub source: Endpoint,
+    pub destination: Endpoint,
+    pub length: usize,
+}
+
+/// Called from IRQ context (or from the polling timer when not `IRQ_DRIVEN`)
+/// with the outcome of a transfer that was not aborted
+pub type CompletionHandler = fn(Result<(), DmaError>);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DmaError {
+    /// The chain has no segments, or more than `MAX_SEGMENTS`
+    BadChain,
+    /// A segment is longer than the channel can transfer
+    TooLong,
+    /// The channel is running a transfer
+    Busy,
+    /// The controller reported an error, with the debug register error bits
+    Failed(u32),
+    Aborted,
+}
+
+/// Hardware control block, read by the controller from memory
+#[repr(C, align(32))]
+#[derive(Clone, Copy)]
+struct ControlBlock {
+    transfer_info: u32,
+    source: u32,
+    destination: u32,
+    length: u32,
+    stride: u32,
+    next: u32,
+    _reserved: [u32; 2],
+}
+
+/// Control blocks of a channel, only written by its owner while it is idle
+struct ChainBuffer(UnsafeCell<[ControlBlock; MAX_SEGMENTS]>);
+
+#[repr(C)]
+struct ChannelRegisters {
+    control_status: ReadWrite<u32>,
+    control_block: ReadWrite<u32>,
+    transfer_info: ReadOnly<u32>,
+    source: ReadOnly<u32>,
+    destination: ReadOnly<u32>,
+    length: ReadOnly<u32>,
+    stride: ReadOnly<u32>,
+    next: ReadOnly<u32>,
+    debug: ReadWrite<u32>,
+}
+
+#[repr(C)]
+struct GlobalRegisters {
+    interrupt_status: ReadOnly<u32>,
+    _reserved0: [u32; 3],
+    enable: ReadWrite<u32>,
+}
+
+// Define globals
+/// Channels the firmware leaves to the ARM
+static USABLE: AtomicU16 = AtomicU16::new(0);
+static ALLOCATED: AtomicU16 = AtomicU16::new(0);
+/// Channels with a transfer in flight
+static RUNNING: AtomicU16 = AtomicU16::new(0);
+/// Channels whose transfer is being completed (by the interrupt or an abort)
+static COMPLETING: AtomicU16 = AtomicU16::new(0);
+static RESULTS: [AtomicU32; CHANNEL_COUNT] = [ATOMIC_ZERO_U32; CHANNEL_COUNT];
+/// Completion handlers stored as function addresses (0 when none)
+static HANDLERS: [AtomicUsize; CHANNEL_COUNT] = [ATOMIC_ZERO_USIZE; CHANNEL_COUNT];
+/// Segments of the running chains
+static CHAIN_LENGTHS: [AtomicUsize; CHANNEL_COUNT] = [ATOMIC_ZERO_USIZE; CHANNEL_COUNT];
+static CHAINS: [ChainBuffer; CHANNEL_COUNT] = [CHAIN_EMPTY; CHANNEL_COUNT];
+/// Signalled when a transfer of the channel completes
+static COMPLETIONS: [Event; CHANNEL_COUNT] = [EVENT_NEW; CHANNEL_COUNT];
+/// Completes the running channels when not `IRQ_DRIVEN`
+static POLLER: Timer = Timer::new(poll_running, 0);
+#[allow(clippy::declare_interior_mutable_const)]
+const ATOMIC_ZERO_U32: AtomicU32 = AtomicU32::new(0);
+#[allow(clippy::declare_interior_mutable_const)]
+const ATOMIC_ZERO_USIZE: AtomicUsize = AtomicUsize::new(0);
+#[allow(clippy::declare_interior_mutable_const)]
+const CHAIN_EMPTY: ChainBuffer = ChainBuffer(UnsafeCell::new([ControlBlock::EMPTY; MAX_SEGMENTS]));
+#[allow(clippy::declare_interior_mutable_const)]
+const EVENT_NEW: Event = Event::new();
+
+// Define procedures
+/// Finds the channels left to the ARM, enables them and routes their interrupts.
+pub fn init() {
+    let mask = mailbox::dma_channels().map_or(DEFAULT_CHANNEL_MASK, |mask| mask as u16) & ((1 << CHANNEL_COUNT) - 1);
+    USABLE.store(mask, Ordering::Release);
+    global_regs().enable.modify(|enable| enable | mask as u32);
+    for index in channels(mask) {
+        reset(index);
+        gpu_intc::request(IRQ_DMA_0 + index.min(SHARED_IRQ_CHANNEL), handle_irq);
+    }
+}
+
+/// Takes a free channel, full ones first.
+pub fn allocate() -> Option<Channel> {
+    let usable = USABLE.load(Ordering::Acquire);
+    let mut allocated = ALLOCATED.load(Ordering::Relaxed);
+    loop {
+        let free = usable & !allocated;
+        if free == 0 {
+            return None;
+        }
+        let index = free.trailing_zeros() as usize;
+        match ALLOCATED.compare_exchange_weak(allocated, allocated | 1 << index, Ordering::Acquire, Ordering::Relaxed) {
+            Ok(_) => return Some(Channel { index }),
+            Err(current) => allocated = current,
+        }
+    }
+}
+
+// Implement structs
+impl Channel {
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Lite channels transfer at most 64 KiB per segment
+    pub fn is_lite(&self) -> bool {
+        self.index >= FIRST_LITE_CHANNEL
+    }
+
+    pub fn is_busy(&self) -> bool {
+        is_running(self.index)
+    }
+
+    /// Starts the chain of `segments`, calling `handler` once it completes.
+    ///
+    /// Memory sources are cleaned from the data cache before the transfer,
+    /// and memory destinations invalidated after it.
+    ///
+    /// # Safety
+    /// The memory of the segments must stay valid, and the destinations
+    /// untouched, until the transfer completes (or is aborted).
+    pub unsafe fn start(&mut self, segments: &[Segment], handler: Option<CompletionHandler>) -> Result<(), DmaError> {
+        let index = self.index;
+        if segments.is_empty() || segments.len() > MAX_SEGMENTS {
+            return Err(DmaError::BadChain);
+        }
+        let max_length = if self.is_lite() { MAX_LITE_LENGTH } else { MAX_LENGTH };
+        if segments.iter().any(|segment| segment.length > max_length) {
+            return Err(DmaError::TooLong);
+        }
+        if is_running(index) {
+            return Err(DmaError::Busy);
+        }
+        // SAFETY: The channel is idle and mutably borrowed, so nothing else uses its chain
+        let chain = &mut *CHAINS[index].0.get();
+        for (position, segment) in segments.iter().enumerate() {
+            let last = position + 1 == segments.len();
+            chain[position] = ControlBlock {
+                transfer_info: transfer_info(segment, last),
+                source: bus_address(segment.source),
+                destination: bus_address(segment.destination),
+                length: segment.length as u32,
+                stride: 0,
+                next: if last { 0 } else { memory_bus_address(&chain[position + 1] as *const _ as usize) },
+                _reserved: [0; 2],
+            };
+            // Dirty lines would hide the source, or be evicted over the destination
+            for endpoint in [segment.source, segment.destination] {
+                if let Endpoint::Memory(address) = endpoint {
+                    cpu::clean_dcache_range(address, segment.length);
+                }
+            }
+        }
+        cpu::clean_dcache_range(chain.as_ptr() as usize, segments.len() * mem::size_of::<ControlBlock>());
+        CHAIN_LENGTHS[index].store(segments.len(), Ordering::Relaxed);
+        HANDLERS[index].store(handler.map_or(0, |handler| handler as usize), Ordering::Relaxed);
+        RESULTS[index].store(RESULT_OK, Ordering::Relaxed);
+        RUNNING.fetch_or(1 << index, Ordering::AcqRel);
+        cpu::store_barrier();
+        let regs = channel_regs(index);
+        regs.control_status.write(CS_END | CS_INT);
+        regs.control_block.write(memory_bus_address(chain.as_ptr() as usize));
+        regs.control_status.write(CS_ACTIVE | CS_PRIORITY | CS_PANIC_PRIORITY | CS_WAIT_FOR_OUTSTANDING_WRITES);
+        if !IRQ_DRIVEN && !POLLER.is_pending() {
+            POLLER.start_periodic(POLL_INTERVAL);
+        }
+        Ok(())
+    }
+
+    /// Blocks the calling thread until the transfer completes, returning its outcome.
+    pub fn wait(&mut self) -> Result<(), DmaError> {
+        let index = self.index;
+        COMPLETIONS[index].wait_until(|| !is_running(index));
+        result(index)
+    }
+
+    /// Completes with the outcome of the transfer (when awaited by a kernel executor task).
+    pub async fn finished(&self) -> Result<(), DmaError> {
+        let index = self.index;
+        COMPLETIONS[index].until(move || !is_running(index)).await;
+        result(index)
+    }
+
+    /// Stops the transfer, which completes as `DmaError::Aborted` (unless it
+    /// completed meanwhile) without calling its handler.
+    pub fn abort(&self) {
+        let index = self.index;
+        // Claimed before the reset, so that the interrupt cannot take the stopped channel for a finished one
+        if claim_completion(index) {
+            reset(index);
+            // The handler is only called from IRQ context
+            let _ = complete(index, RESULT_ABORTED);
+        }
+        // Or the interrupt is completing the transfer
+        while is_running(index) {
+            hint::spin_loop();
+        }
+    }
+
+    /// Copies `source` into `destination` (of the same length), blocking until done.
+    pub fn copy(&mut self, destination: &mut [u8], source: &[u8]) -> Result<(), DmaError> {
+        assert!(destination.len() == source.len(), "DMA copy between buffers of different lengths");
+        let max_length = if self.is_lite() { MAX_LITE_LENGTH } else { MAX_LENGTH };
+        let mut segments = [Segment { source: Endpoint::Memory(0), destination: Endpoint::Memory(0), length: 0 }; MAX_SEGMENTS];
+        let mut offset = 0;
+        // Chains of at most `MAX_SEGMENTS` segments of the longest length
+        while offset < source.len() {
+            let mut count = 0;
+            while count < MAX_SEGMENTS && offset < source.len() {
+                let length = (source.len() - offset).min(max_length);
+                segments[count] = Segment {
+                    source: Endpoint::Memory(source.as_ptr() as usize + offset),
+                    destination: Endpoint::Memory(destination.as_mut_ptr() as usize + offset),
+                    length,
+                };
+                offset += length;
+                count += 1;
+            }
+            // SAFETY: Both buffers are borrowed until the transfer completed
+            unsafe { self.start(&segments[..count], None)? };
+            self.wait()?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Channel {
+    fn drop(&mut self) {
+        self.abort();
+        ALLOCATED.fetch_and(!(1 << self.index), Ordering::Release);
+    }
+}
+
+impl ControlBlock {
+    const EMPTY: ControlBlock = ControlBlock { transfer_info: 0, source: 0, destination: 0, length: 0, stride: 0, next: 0, _reserved: [0; 2] };
+}
+
+impl fmt::Display for DmaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DmaError::BadChain => write!(f, "DMA chain empty or longer than {} segments", MAX_SEGMENTS),
+            DmaError::TooLong => write!(f, "DMA segment too long for the channel"),
+            DmaError::Busy => write!(f, "DMA channel busy"),
+            DmaError::Failed(errors) => write!(f, "DMA transfer failed (debug errors {:#x})", errors),
+            DmaError::Aborted => write!(f, "DMA transfer aborted"),
+        }
+    }
+}
+
+// Implement thread safety
+// SAFETY: A chain is only written by the owner of its channel, while the channel is idle
+unsafe impl Sync for ChainBuffer {}
+
+// Define helpers
+fn transfer_info(segment: &Segment, last: bool) -> u32 {
+    let mut info = TI_WAIT_RESP;
+    match segment.source {
+        Endpoint::Memory(_) => info |= TI_SRC_INC,
+        Endpoint::Peripheral { dreq: Some(dreq), .. } => info |= TI_SRC_DREQ | dreq << TI_PERMAP_SHIFT,
+        Endpoint::Peripheral { dreq: None, .. } => {}
+    }
+    match segment.destination {
+        Endpoint::Memory(_) => info |= TI_DEST_INC,
+        Endpoint::Peripheral { dreq: Some(dreq), .. } => info |= TI_DEST_DREQ | dreq << TI_PERMAP_SHIFT,
+        Endpoint::Peripheral { dreq: None, .. } => {}
+    }
+    if last && IRQ_DRIVEN {
+        info |= TI_INTEN;
+    }
+    info
+}
+
+fn bus_address(endpoint: Endpoint) -> u32 {
+    match endpoint {
+        Endpoint::Memory(address) => memory_bus_address(address),
+        Endpoint::Peripheral { address, .. } => (address - PERIPHERAL_BASE + BUS_PERIPHERALS) as u32,
+    }
+}
+
+fn memory_bus_address(address: usize) -> u32 {
+    (address | BUS_UNCACHED) as u32
+}
+
+fn is_running(index: usize) -> bool {
+    RUNNING.load(Ordering::Acquire) & 1 << index != 0
+}
+
+fn result(index: usize) -> Result<(), DmaError> {
+    match RESULTS[index].load(Ordering::Acquire) {
+        RESULT_OK => Ok(()),
+        RESULT_ABORTED => Err(DmaError::Aborted),
+        failed => Err(DmaError::Failed(failed & !RESULT_FAILED)),
+    }
+}
+
+/// Completes the transfer of channel `index` if the controller is done with it,
+/// returning whether it is not running anymore.
+fn poll(index: usize) -> bool {
+    if !is_running(index) {
+        return true;
+    }
+    let regs = channel_regs(index);
+    let status = regs.control_status.read();
+    if status & CS_ACTIVE != 0 && status & CS_ERROR == 0 {
+        return false;
+    }
+    // Unless an abort is completing it
+    if claim_completion(index) {
+        let outcome = if status & CS_ERROR != 0 {
+            let errors = regs.debug.read() & DEBUG_ERRORS;
+            regs.debug.write(errors);
+            reset(index);
+            RESULT_FAILED | errors
+        } else {
+            regs.control_status.write(CS_END | CS_INT);
+            RESULT_OK
+        };
+        if let Some(handler) = complete(index, outcome) {
+            handler(result(index));
+        }
+    }
+    !is_running(index)
+}
+
+/// Claims the completion of the transfer of channel `index`, returning
+/// whether it is running and nobody else is completing it.
+fn claim_completion(index: usize) -> bool {
+    let bit = 1 << index;
+    // Only once, when an abort races with the interrupt
+    if COMPLETING.fetch_or(bit, Ordering::AcqRel) & bit != 0 {
+        return false;
+    }
+    if !is_running(index) {
+        COMPLETING.fetch_and(!bit, Ordering::AcqRel);
+        return false;
+    }
+    true
+}
+
+/// Publishes the outcome of the claimed transfer of channel `index` and
+/// notifies its waiters, returning its completion handler.
+fn complete(index: usize, outcome: u32) -> Option<CompletionHandler> {
+    let bit = 1 << index;
+    // The controller wrote around the cached copies of the destinations
+    // SAFETY: The chain is not changed before the channel stops running
+    let chain = unsafe { &*CHAINS[index].0.get() };
+    for block in &chain[..CHAIN_LENGTHS[index].load(Ordering::Relaxed)] {
+        if block.transfer_info & TI_DEST_INC != 0 {
+            cpu::invalidate_dcache_range(block.destination as usize & BUS_ADDRESS_MASK, block.length as usize);
+        }
+    }
+    RESULTS[index].store(outcome, Ordering::Release);
+    let handler = HANDLERS[index].swap(0, Ordering::AcqRel);
+    RUNNING.fetch_and(!bit, Ordering::AcqRel);
+    COMPLETING.fetch_and(!bit, Ordering::AcqRel);
+    COMPLETIONS[index].notify_all();
+    // SAFETY: Only function addresses are stored
+    (handler != 0).then(|| unsafe { mem::transmute::<usize, CompletionHandler>(handler) })
+}
+
+fn reset(index: usize) {
+    channel_regs(index).control_status.write(CS_RESET);
+}
+
+fn channels(mask: u16) -> impl Iterator<Item = usize> {
+    (0..CHANNEL_COUNT).filter(move |index| mask & 1 << index != 0)
+}
+
+/// Completes the running channels that are done (from the polling timer)
+fn poll_running(_: usize) {
+    for index in channels(RUNNING.load(Ordering::Acquire)) {
+        poll(index);
+    }
+}
+
+/// Handles every DMA line, completing the channels that interrupted
+fn handle_irq() {
+    let pending = global_regs().interrupt_status.read() as u16 & USABLE.load(Ordering::Acquire);
+    for index in channels(pending) {
+        // Acknowledges the interrupt even if the transfer was aborted meanwhile
+        channel_regs(index).control_status.write(CS_INT);
+        poll(index);
+    }
+}
+
+fn channel_regs(index: usize) -> &'static ChannelRegisters {
+    assert!(index < CHANNEL_COUNT, "Invalid DMA channel {}", index);
+    // SAFETY: GPU peripherals are always mapped
+    unsafe { registers(DMA_BASE + index * CHANNEL_STRIDE) }
+}
+
+fn global_regs() -> &'static GlobalRegisters {
+    // SAFETY: GPU peripherals are always mapped
+    unsafe { registers(DMA_BASE + GLOBAL_OFFSET) }
+}
@@ -0,0 +1,531 @@
+// Import dependencies
+use core::{fmt, hint, sync::atomic::{AtomicBool, AtomicU32, Ordering}, time::Duration};
+use crate::arch::cpu::IrqGuard;
+use crate::task::{self, WaitQueue};
+use crate::time::{self, Instant};
+use super::block::{BlockDevice, BLOCK_SIZE};
+use super::gpio::{self, Function, Pull};
+use super::gpu_intc::{self, IRQ_DRIVEN, IRQ_EMMC};
+use super::mailbox::{self, Clock};
+use super::mmio::{registers, ReadOnly, ReadWrite};
+use super::PERIPHERAL_BASE;
+
+// Define constants
+/// Arasan SDHCI controller (the SD card slot)
+const EMMC_BASE: usize = PERIPHERAL_BASE + 0x30_0000;
+/// Base clock of the controller if the firmware cannot tell
+pub const DEFAULT_EMMC_CLOCK: u32 = 200_000_000;
+/// Card clock during identification, and for transfers (default speed)
+const IDENTIFICATION_CLOCK: u32 = 400_000;
+const TRANSFER_CLOCK: u32 = 25_000_000;
+/// SD card pins (ALT3 of GPIO 48..53: clock, command and 4 data lines)
+const SD_PINS: core::ops::Range<usize> = 48..54;
+const RESET_TIMEOUT: Duration = Duration::from_millis(100);
+const COMMAND_TIMEOUT: Duration = Duration::from_millis(100);
+const DATA_TIMEOUT: Duration = Duration::from_millis(500);
+/// Time the card may take to power up (answering ACMD41 as busy)
+const POWER_UP_TIMEOUT: Duration = Duration::from_secs(1);
+const POWER_UP_POLL: Duration = Duration::from_millis(10);
+/// Most blocks a single command can transfer
+const MAX_BLOCKS_PER_COMMAND: usize = 0xFFFF;
+/// Status bits
+const STATUS_CMD_INHIBIT: u32 = 1 << 0;
+const STATUS_DAT_INHIBIT: u32 = 1 << 1;
+const STATUS_CARD_INSERTED: u32 = 1 << 16;
+/// Control 0: 4-bit data bus
+const CONTROL0_4_BIT: u32 = 1 << 1;
+/// Control 1 bits (clock divider in 15:6, data timeout exponent in 19:16)
+const CONTROL1_CLK_INTLEN: u32 = 1 << 0;
+const CONTROL1_CLK_STABLE: u32 = 1 << 1;
+const CONTROL1_CLK_EN: u32 = 1 << 2;
+const CONTROL1_CLK_DIVIDER: u32 = 0x3FF << 6;
+const CONTROL1_DATA_TOUNIT_MAX: u32 = 0xE << 16;
+const CONTROL1_SRST_HC: u32 = 1 << 24;
+const CONTROL1_SRST_CMD: u32 = 1 << 25;
+const CONTROL1_SRST_DATA: u32 = 1 << 26;
+/// Interrupt bits (flags, status enable and signal enable registers)
+const INT_CMD_DONE: u32 = 1 << 0;
+const INT_DATA_DONE: u32 = 1 << 1;
+const INT_WRITE_READY: u32 = 1 << 4;
+const INT_READ_READY: u32 = 1 << 5;
+const INT_CARD_INSERTION: u32 = 1 << 6;
+const INT_CARD_REMOVAL: u32 = 1 << 7;
+const INT_ERROR: u32 = 1 << 15;
+const INT_COMMAND_TIMEOUT: u32 = 1 << 16;
+/// Error summary and every error bit
+const INT_ERRORS: u32 = 0xFFFF_8000;
+/// Command and transfer mode bits
+const TM_BLOCK_COUNT_EN: u32 = 1 << 1;
+const TM_AUTO_CMD12: u32 = 1 << 2;
+const TM_READ: u32 = 1 << 4;
+const TM_MULTI_BLOCK: u32 = 1 << 5;
+const CMD_RESPONSE_136: u32 = 1 << 16;
+const CMD_RESPONSE_48: u32 = 2 << 16;
+const CMD_RESPONSE_48_BUSY: u32 = 3 << 16;
+const CMD_CRC_CHECK: u32 = 1 << 19;
+const CMD_INDEX_CHECK: u32 = 1 << 20;
+const CMD_DATA: u32 = 1 << 21;
+/// Response formats
+const R1: u32 = CMD_RESPONSE_48 | CMD_CRC_CHECK | CMD_INDEX_CHECK;
+const R1B: u32 = CMD_RESPONSE_48_BUSY | CMD_CRC_CHECK | CMD_INDEX_CHECK;
+const R2: u32 = CMD_RESPONSE_136 | CMD_CRC_CHECK;
+const R3: u32 = CMD_RESPONSE_48;
+const R6: u32 = R1;
+const R7: u32 = R1;
+/// Commands (ACMD ones must follow `CMD_APP`)
+const CMD_GO_IDLE: u32 = command(0, 0);
+const CMD_ALL_SEND_CID: u32 = command(2, R2);
+const CMD_SEND_RELATIVE_ADDR: u32 = command(3, R6);
+const CMD_SELECT_CARD: u32 = command(7, R1B);
+const CMD_SEND_IF_COND: u32 = command(8, R7);
+const CMD_SEND_CSD: u32 = command(9, R2);
+const CMD_SET_BLOCKLEN: u32 = command(16, R1);
+const CMD_READ_SINGLE: u32 = command(17, R1 | CMD_DATA | TM_READ);
+const CMD_READ_MULTIPLE: u32 = command(18, R1 | CMD_DATA | TM_READ | TM_MULTI_BLOCK | TM_BLOCK_COUNT_EN | TM_AUTO_CMD12);
+const CMD_WRITE_SINGLE: u32 = command(24, R1 | CMD_DATA);
+const CMD_WRITE_MULTIPLE: u32 = command(25, R1 | CMD_DATA | TM_MULTI_BLOCK | TM_BLOCK_COUNT_EN | TM_AUTO_CMD12);
+const CMD_APP: u32 = command(55, R1);
+const ACMD_SET_BUS_WIDTH: u32 = command(6, R1);
+const ACMD_SD_SEND_OP_COND: u32 = command(41, R3);
+/// CMD8 argument: 2.7-3.6 V and a check pattern echoed by the card
+const IF_COND_CHECK: u32 = 0x1AA;
+/// ACMD41 argument and response bits
+const OCR_VOLTAGE_WINDOW: u32 = 0x00FF_8000;
+const OCR_HIGH_CAPACITY: u32 = 1 << 30;
+const OCR_POWERED_UP: u32 = 1 << 31;
+/// ACMD6 argument selecting the 4-bit bus
+const BUS_WIDTH_4: u32 = 0b10;
+
+// Define structs
+/// SD card found by `init`, used as a block device
+#[derive(Debug, Clone, Copy)]
+pub struct SdCard {
+    /// Relative card address, selecting the card in addressed commands
+    rca: u32,
+    /// SDHC/SDXC, addressed in blocks (SDSC cards are addressed in bytes)
+    high_capacity: bool,
+    blocks: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SdError {
+    /// The slot is empty
+    NoCard,
+    /// The card did not answer a command
+    CommandTimeout,
+    /// A command or transfer failed, with the error interrupt bits
+    Controller(u32),
+    /// The controller did not signal the completion in time
+    Timeout,
+    /// The card does not talk the protocol of SD memory cards
+    UnsupportedCard,
+    /// The blocks are past the end of the card
+    OutOfRange,
+    /// Buffers must hold a whole number of blocks
+    BadLength,
+}
+
+/// Data of a transfer, with its direction
+enum Buffer<'buffer> {
+    Read(&'buffer mut [u8]),
+    Write(&'buffer [u8]),
+}
+
+/// Owner of the controller, released on drop
+struct Controller;
+
+#[repr(C)]
+struct Registers {
+    argument2: ReadWrite<u32>,
+    block_size_count: ReadWrite<u32>,
+    argument1: ReadWrite<u32>,
+    command_transfer_mode: ReadWrite<u32>,
+    response: [ReadOnly<u32>; 4],
+    data: ReadWrite<u32>,
+    status: ReadOnly<u32>,
+    control0: ReadWrite<u32>,
+    control1: ReadWrite<u32>,
+    interrupt: ReadWrite<u32>,
+    interrupt_status_enable: ReadWrite<u32>,
+    interrupt_signal_enable: ReadWrite<u32>,
+    control2: ReadWrite<u32>,
+}
+
+// Define globals
+/// Interrupt flags collected from the controller, until a waiter takes them
+static PENDING: AtomicU32 = AtomicU32::new(0);
+/// Threads waiting for interrupt flags
+static COMPLETION: WaitQueue = WaitQueue::new();
+/// Set while a thread drives the controller
+static BUSY: AtomicBool = AtomicBool::new(false);
+/// Threads waiting for the controller
+static IDLE: WaitQueue = WaitQueue::new();
+
+// Define procedures
+/// Resets the controller and identifies the card in the slot, switching it to
+/// the 4-bit bus at 25 MHz.
+///
+/// Blocks, so it must be called from a thread.
+pub fn init() -> Result<SdCard, SdError> {
+    let _controller = claim();
+    let regs = regs();
+    for pin in SD_PINS {
+        gpio::set_function(pin, Function::Alt3);
+        gpio::set_pull(pin, if pin == SD_PINS.start { Pull::None } else { Pull::Up });
+    }
+    // Reset the host controller
+    regs.control0.write(0);
+    regs.control2.write(0);
+    regs.control1.write(CONTROL1_SRST_HC);
+    wait_clear(|| regs.control1.read(), CONTROL1_SRST_HC, RESET_TIMEOUT)?;
+    if regs.status.read() & STATUS_CARD_INSERTED == 0 {
+        return Err(SdError::NoCard);
+    }
+    let base_clock = mailbox::clock_rate(Clock::Emmc).unwrap_or(DEFAULT_EMMC_CLOCK);
+    set_clock(base_clock, IDENTIFICATION_CLOCK)?;
+    enable_interrupts();
+    // Identification
+    send_command(CMD_GO_IDLE, 0)?;
+    let version_2 = match send_command(CMD_SEND_IF_COND, IF_COND_CHECK) {
+        Ok(echo) if echo & 0xFFF == IF_COND_CHECK => true,
+        Ok(_) => return Err(SdError::UnsupportedCard),
+        // Version 1 cards do not know CMD8
+        Err(SdError::CommandTimeout) => false,
+        Err(error) => return Err(error),
+    };
+    let ocr = power_up(version_2)?;
+    send_command(CMD_ALL_SEND_CID, 0)?;
+    let rca = send_command(CMD_SEND_RELATIVE_ADDR, 0)? & 0xFFFF_0000;
+    send_command(CMD_SEND_CSD, rca)?;
+    let blocks = csd_blocks(response_136()).ok_or(SdError::UnsupportedCard)?;
+    // Transfer state
+    send_command(CMD_SELECT_CARD, rca)?;
+    let high_capacity = ocr & OCR_HIGH_CAPACITY != 0;
+    if !high_capacity {
+        send_command(CMD_SET_BLOCKLEN, BLOCK_SIZE as u32)?;
+    }
+    regs.block_size_count.write(BLOCK_SIZE as u32);
+    // Every SD memory card supports the 4-bit bus
+    send_command(CMD_APP, rca)?;
+    send_command(ACMD_SET_BUS_WIDTH, BUS_WIDTH_4)?;
+    regs.control0.modify(|control| control | CONTROL0_4_BIT);
+    set_clock(base_clock, TRANSFER_CLOCK)?;
+    Ok(SdCard { rca, high_capacity, blocks })
+}
+
+// Implement structs
+impl SdCard {
+    /// SDHC/SDXC card (addressed in blocks)
+    pub fn is_high_capacity(&self) -> bool {
+        self.high_capacity
+    }
+
+    /// Transfers whole blocks between the card and `buffer`, `MAX_BLOCKS_PER_COMMAND` at a time.
+    fn transfer(&self, block: u64, buffer: Buffer) -> Result<(), SdError> {
+        let len = buffer.len();
+        if len % BLOCK_SIZE != 0 {
+            return Err(SdError::BadLength);
+        }
+        let count = (len / BLOCK_SIZE) as u64;
+        if block.checked_add(count).map_or(true, |end| end > self.blocks) {
+            return Err(SdError::OutOfRange);
+        }
+        let _controller = claim();
+        let mut buffer = buffer;
+        let mut block = block;
+        while !buffer.is_empty() {
+            let count = (buffer.len() / BLOCK_SIZE).min(MAX_BLOCKS_PER_COMMAND);
+            let (chunk, rest) = buffer.split_at(count * BLOCK_SIZE);
+            // SDSC cards are at most 2 GB, so byte addresses fit
+            let address = if self.high_capacity { block as u32 } else { block as u32 * BLOCK_SIZE as u32 };
+            transfer_chunk(address, chunk)?;
+            buffer = rest;
+            block += count as u64;
+        }
+        Ok(())
+    }
+}
+
+impl BlockDevice for SdCard {
+    type Error = SdError;
+
+    fn block_count(&self) -> u64 {
+        self.blocks
+    }
+
+    fn read_blocks(&self, block: u64, buffer: &mut [u8]) -> Result<(), SdError> {
+        self.transfer(block, Buffer::Read(buffer))
+    }
+
+    fn write_blocks(&self, block: u64, buffer: &[u8]) -> Result<(), SdError> {
+        self.transfer(block, Buffer::Write(buffer))
+    }
+}
+
+impl<'buffer> Buffer<'buffer> {
+    fn len(&self) -> usize {
+        match self {
+            Buffer::Read(buffer) => buffer.len(),
+            Buffer::Write(buffer) => buffer.len(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn split_at(self, mid: usize) -> (Buffer<'buffer>, Buffer<'buffer>) {
+        match self {
+            Buffer::Read(buffer) => {
+                let (head, tail) = buffer.split_at_mut(mid);
+                (Buffer::Read(head), Buffer::Read(tail))
+            }
+            Buffer::Write(buffer) => {
+                let (head, tail) = buffer.split_at(mid);
+                (Buffer::Write(head), Buffer::Write(tail))
+            }
+        }
+    }
+}
+
+impl Drop for Controller {
+    fn drop(&mut self) {
+        BUSY.store(false, Ordering::Release);
+        IDLE.notify_one();
+    }
+}
+
+impl fmt::Display for SdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SdError::NoCard => write!(f, "no SD card inserted"),
+            SdError::CommandTimeout => write!(f, "SD card did not answer"),
+            SdError::Controller(flags) => write!(f, "SD controller error (interrupt flags {:#010x})", flags),
+            SdError::Timeout => write!(f, "SD controller timed out"),
+            SdError::UnsupportedCard => write!(f, "unsupported SD card"),
+            SdError::OutOfRange => write!(f, "blocks past the end of the SD card"),
+            SdError::BadLength => write!(f, "buffer is not a whole number of blocks"),
+        }
+    }
+}
+
+// Define helpers
+/// Waits for the controller to be free and takes it.
+fn claim() -> Controller {
+    let acquire = || BUSY.compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed).is_ok();
+    IDLE.wait_until(acquire);
+    Controller
+}
+
+/// Transfers at most `MAX_BLOCKS_PER_COMMAND` blocks from the card `address`.
+fn transfer_chunk(address: u32, buffer: Buffer) -> Result<(), SdError> {
+    let regs = regs();
+    let count = buffer.len() / BLOCK_SIZE;
+    regs.block_size_count.write((count as u32) << 16 | BLOCK_SIZE as u32);
+    match buffer {
+        Buffer::Read(buffer) => {
+            send_command(if count == 1 { CMD_READ_SINGLE } else { CMD_READ_MULTIPLE }, address)?;
+            for block in buffer.chunks_exact_mut(BLOCK_SIZE) {
+                wait_interrupt(INT_READ_READY, DATA_TIMEOUT)?;
+                for word in block.chunks_exact_mut(4) {
+                    word.copy_from_slice(&regs.data.read().to_le_bytes());
+                }
+            }
+        }
+        Buffer::Write(buffer) => {
+            send_command(if count == 1 { CMD_WRITE_SINGLE } else { CMD_WRITE_MULTIPLE }, address)?;
+            for block in buffer.chunks_exact(BLOCK_SIZE) {
+                wait_interrupt(INT_WRITE_READY, DATA_TIMEOUT)?;
+                for word in block.chunks_exact(4) {
+                    regs.data.write(u32::from_le_bytes([word[0], word[1], word[2], word[3]]));
+                }
+            }
+        }
+    }
+    wait_interrupt(INT_DATA_DONE, DATA_TIMEOUT).map(|_| ())
+}
+
+/// Issues `command` and returns the first word of its response.
+fn send_command(command: u32, argument: u32) -> Result<u32, SdError> {
+    let regs = regs();
+    let busy = command & CMD_RESPONSE_48_BUSY == CMD_RESPONSE_48_BUSY;
+    let mut inhibit = STATUS_CMD_INHIBIT;
+    if busy || command & CMD_DATA != 0 {
+        inhibit |= STATUS_DAT_INHIBIT;
+    }
+    wait_clear(|| regs.status.read(), inhibit, COMMAND_TIMEOUT)?;
+    // Drops the flags left behind by an earlier failure
+    PENDING.store(0, Ordering::SeqCst);
+    regs.argument1.write(argument);
+    regs.command_transfer_mode.write(command);
+    wait_interrupt(INT_CMD_DONE, COMMAND_TIMEOUT)?;
+    if busy {
+        wait_interrupt(INT_DATA_DONE, DATA_TIMEOUT)?;
+    }
+    Ok(regs.response[0].read())
+}
+
+/// Sends ACMD41 until the card has powered up, returning its OCR.
+fn power_up(version_2: bool) -> Result<u32, SdError> {
+    let argument = if version_2 { OCR_VOLTAGE_WINDOW | OCR_HIGH_CAPACITY } else { OCR_VOLTAGE_WINDOW };
+    let deadline = Instant::now() + POWER_UP_TIMEOUT;
+    loop {
+        let ocr = match send_command(CMD_APP, 0).and_then(|_| send_command(ACMD_SD_SEND_OP_COND, argument)) {
+            Ok(ocr) => ocr,
+            // Not an SD memory card (MMC or SDIO only)
+            Err(SdError::CommandTimeout) => return Err(SdError::UnsupportedCard),
+            Err(error) => return Err(error),
+        };
+        if ocr & OCR_POWERED_UP != 0 {
+            return Ok(ocr);
+        }
+        if Instant::now() >= deadline {
+            return Err(SdError::Timeout);
+        }
+        time::sleep(POWER_UP_POLL);
+    }
+}
+
+/// 136-bit response, without the CRC byte the controller strips
+fn response_136() -> u128 {
+    let regs = regs();
+    regs.response.iter().rev().fold(0, |response, word| response << 32 | word.read() as u128)
+}
+
+/// Capacity in blocks from a CSD register (as found in `response_136`)
+fn csd_blocks(csd: u128) -> Option<u64> {
+    let bits = |high: u32, low: u32| ((csd >> (low - 8)) & ((1 << (high - low + 1)) - 1)) as u64;
+    match bits(127, 126) {
+        // SDSC: (C_SIZE + 1) * 2^(C_SIZE_MULT + 2) blocks of 2^READ_BL_LEN bytes
+        0 => {
+            let blocks = (bits(73, 62) + 1) << (bits(49, 47) + 2);
+            Some((blocks << bits(83, 80)) / BLOCK_SIZE as u64)
+        }
+        // SDHC/SDXC: (C_SIZE + 1) * 512 KiB
+        1 => Some((bits(69, 48) + 1) * 1024),
+        _ => None,
+    }
+}
+
+/// Switches the card clock to at most `target` Hz, from the controller `base` clock.
+fn set_clock(base: u32, target: u32) -> Result<(), SdError> {
+    let regs = regs();
+    wait_clear(|| regs.status.read(), STATUS_CMD_INHIBIT | STATUS_DAT_INHIBIT, COMMAND_TIMEOUT)?;
+    regs.control1.modify(|control| control & !CONTROL1_CLK_EN);
+    // Card clock is `base / (2 * divisor)` (10-bit divisor, split in the register)
+    let divisor = ((base + 2 * target - 1) / (2 * target)).clamp(1, 0x3FF);
+    let divisor_bits = (divisor & 0xFF) << 8 | (divisor >> 8) << 6;
+    regs.control1.modify(|control| {
+        (control & !(CONTROL1_CLK_DIVIDER | 0xF << 16)) | divisor_bits | CONTROL1_DATA_TOUNIT_MAX | CONTROL1_CLK_INTLEN
+    });
+    wait_set(|| regs.control1.read(), CONTROL1_CLK_STABLE, RESET_TIMEOUT)?;
+    regs.control1.modify(|control| control | CONTROL1_CLK_EN);
+    Ok(())
+}
+
+/// Reports every interrupt but card insertion/removal, signalling them when interrupt-driven.
+fn enable_interrupts() {
+    let regs = regs();
+    let interrupts = !(INT_CARD_INSERTION | INT_CARD_REMOVAL);
+    regs.interrupt.write(u32::MAX);
+    regs.interrupt_status_enable.write(interrupts);
+    PENDING.store(0, Ordering::SeqCst);
+    if IRQ_DRIVEN {
+        regs.interrupt_signal_enable.write(interrupts);
+        gpu_intc::request(IRQ_EMMC, handle_irq);
+    }
+}
+
+/// Moves the interrupt flags of the controller to `PENDING`, acknowledging them.
+fn collect() {
+    let regs = regs();
+    let _irq = IrqGuard::new();
+    let flags = regs.interrupt.read();
+    if flags != 0 {
+        regs.interrupt.write(flags);
+        PENDING.fetch_or(flags, Ordering::SeqCst);
+    }
+}
+
+/// Waits for one of the `flags` interrupts and takes it, failing on errors.
+fn wait_interrupt(flags: u32, timeout: Duration) -> Result<(), SdError> {
+    let arrived = || {
+        collect();
+        PENDING.load(Ordering::SeqCst) & (flags | INT_ERRORS) != 0
+    };
+    let arrived = if IRQ_DRIVEN {
+        COMPLETION.wait_until_timeout(arrived, timeout)
+    } else {
+        poll_until(arrived, timeout)
+    };
+    if !arrived {
+        reset_lines();
+        return Err(SdError::Timeout);
+    }
+    let pending = PENDING.fetch_and(!(flags | INT_ERRORS), Ordering::SeqCst);
+    match pending & INT_ERRORS {
+        0 => Ok(()),
+        errors => {
+            reset_lines();
+            if errors & INT_COMMAND_TIMEOUT != 0 {
+                Err(SdError::CommandTimeout)
+            } else {
+                Err(SdError::Controller(errors & !INT_ERROR))
+            }
+        }
+    }
+}
+
+/// Resets the command and data lines after an error, for the next command.
+fn reset_lines() {
+    let regs = regs();
+    let reset = CONTROL1_SRST_CMD | CONTROL1_SRST_DATA;
+    regs.control1.modify(|control| control | reset);
+    wait_clear(|| regs.control1.read(), reset, RESET_TIMEOUT).ok();
+}
+
+fn wait_clear(read: impl Fn() -> u32, bits: u32, timeout: Duration) -> Result<(), SdError> {
+    spin_until(|| read() & bits == 0, timeout)
+}
+
+fn wait_set(read: impl Fn() -> u32, bits: u32, timeout: Duration) -> Result<(), SdError> {
+    spin_until(|| read() & bits == bits, timeout)
+}
+
+/// Busy-waits for short controller state changes.
+fn spin_until(mut condition: impl FnMut() -> bool, timeout: Duration) -> Result<(), SdError> {
+    let deadline = Instant::now() + timeout;
+    while !condition() {
+        if Instant::now() >= deadline {
+            return Err(SdError::Timeout);
+        }
+        hint::spin_loop();
+    }
+    Ok(())
+}
+
+/// Yields until `condition` holds, for when the completion is not interrupt-driven.
+fn poll_until(mut condition: impl FnMut() -> bool, timeout: Duration) -> bool {
+    let deadline = Instant::now() + timeout;
+    while !condition() {
+        if Instant::now() >= deadline {
+            return false;
+        }
+        task::yield_now();
+    }
+    true
+}
+
+fn handle_irq() {
+    collect();
+    COMPLETION.notify_all();
+}
+
+const fn command(index: u32, flags: u32) -> u32 {
+    index << 24 | flags
+}
+
+fn regs() -> &'static Registers {
+    // SAFETY: GPU peripherals are always mapped
+    unsafe { registers(EMMC_BASE) }
+}
@@ -0,0 +1,172 @@
+// Import dependencies
+use core::{ptr, slice};
+use super::mailbox::{self, MailboxError, Message, Tag};
+// Define modules
+pub mod font;
+pub mod text;
+
+// Define constants
+pub const DEFAULT_WIDTH: u32 = 1024;
+pub const DEFAULT_HEIGHT: u32 = 768;
+/// Bits per pixel requested from the firmware
+const DEPTH: u32 = 32;
+/// Pixel order values of the firmware
+const PIXEL_ORDER_RGB: u32 = 1;
+/// Alignment requested for the buffer
+const BUFFER_ALIGNMENT: u32 = 4096;
+/// Strips the GPU bus alias from buffer addresses
+const BUS_ADDRESS_MASK: u32 = 0x3FFF_FFFF;
+
+// Define structs
+/// Linear 32-bit framebuffer allocated by the firmware.
+///
+/// The memory comes from the GPU share of the RAM, so it is never handed out
+/// by the page allocator. It is identity-mapped like the rest of memory.
+///
+/// When the firmware accepts it, the buffer is twice the screen height and
+/// every row is drawn in both halves: scrolling then only moves the
+/// displayed window, instead of copying the whole picture.
+pub struct Framebuffer {
+    base: *mut u32,
+    width: usize,
+    height: usize,
+    /// Bytes between the starts of two rows
+    pitch: usize,
+    /// Red is in the low byte of a pixel (otherwise blue is)
+    rgb: bool,
+    /// The buffer holds two copies of the screen
+    panning: bool,
+    /// First buffer row displayed (below `height`)
+    offset: usize,
+}
+
+// Define procedures
+/// Asks the firmware for a `width` x `height` framebuffer.
+///
+/// The firmware may pick another resolution, see `width` and `height`.
+pub fn init(width: u32, height: u32) -> Result<Framebuffer, MailboxError> {
+    let mut message = Message::new();
+    let physical = message.add(Tag::SetPhysicalSize, &[width, height], 2)?;
+    let virtual_size = message.add(Tag::SetVirtualSize, &[width, height * 2], 2)?;
+    message.add(Tag::SetVirtualOffset, &[0, 0], 2)?;
+    let depth = message.add(Tag::SetDepth, &[DEPTH], 1)?;
+    let order = message.add(Tag::SetPixelOrder, &[PIXEL_ORDER_RGB], 1)?;
+    let buffer = message.add(Tag::AllocateBuffer, &[BUFFER_ALIGNMENT], 2)?;
+    let pitch = message.add(Tag::Pitch, &[], 1)?;
+    message.send()?;
+    let [width, height] = message.response_words(physical)?;
+    let [_, virtual_height] = message.response_words(virtual_size)?;
+    let [base, size] = message.response_words(buffer)?;
+    let [pitch] = message.response_words(pitch)?;
+    let panning = virtual_height >= height * 2 && size >= pitch * height * 2;
+    if message.response_words(depth)? != [DEPTH] || base == 0 || size < pitch * height {
+        return Err(MailboxError::TagFailed(Tag::AllocateBuffer as u32));
+    }
+    Ok(Framebuffer {
+        base: (base & BUS_ADDRESS_MASK) as usize as *mut u32,
+        width: width as usize,
+        height: height as usize,
+        pitch: pitch as usize,
+        rgb: message.response_words(order)? == [PIXEL_ORDER_RGB],
+        panning,
+        offset: 0,
+    })
+}
+
+// Implement structs
+impl Framebuffer {
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Pixel value of a color
+    pub fn color(&self, red: u8, green: u8, blue: u8) -> u32 {
+        let (low, high) = if self.rgb { (red, blue) } else { (blue, red) };
+        u32::from_le_bytes([low, green, high, 0])
+    }
+
+    /// Address of pixel (`x`, `y`)
+    fn pixel(&self, x: usize, y: usize) -> *mut u32 {
+        self.base.cast::<u8>().wrapping_add(y * self.pitch).cast::<u32>().wrapping_add(x)
+    }
+
+    /// Pixels of buffer row `row`
+    fn row(&mut self, row: usize) -> &mut [u32] {
+        let rows = if self.panning { self.height * 2 } else { self.height };
+        assert!(row < rows, "Row {} out of the framebuffer", row);
+        // SAFETY: The firmware gave the buffer to the kernel, rows are `pitch` bytes apart
+        unsafe { slice::from_raw_parts_mut(self.pixel(0, row), self.width) }
+    }
+
+    /// Runs `f` on the pixels of screen row `y`, in every copy of the screen.
+    fn update_row(&mut self, y: usize, mut f: impl FnMut(&mut [u32])) {
+        let row = self.offset + y;
+        f(self.row(row));
+        if self.panning {
+            let mirror = if row < self.height { row + self.height } else { row - self.height };
+            f(self.row(mirror));
+        }
+    }
+
+    pub fn put_pixel(&mut self, x: usize, y: usize, color: u32) {
+        self.update_row(y, |row| row[x] = color);
+    }
+
+    /// Fills the rectangle at (`x`, `y`), clipped to the screen.
+    pub fn fill_rect(&mut self, x: usize, y: usize, width: usize, height: usize, color: u32) {
+        let (right, bottom) = ((x + width).min(self.width), (y + height).min(self.height));
+        if x >= right {
+            return;
+        }
+        for y in y..bottom {
+            self.update_row(y, |row| row[x..right].fill(color));
+        }
+    }
+
+    /// Draws a font glyph at (`x`, `y`), each bit scaled up to `scale` x `scale` pixels (clipped to the screen).
+    pub fn draw_glyph(&mut self, x: usize, y: usize, glyph: &[u8; font::GLYPH_SIZE], scale: usize, foreground: u32, background: u32) {
+        let right = (x + font::GLYPH_SIZE * scale).min(self.width);
+        let bottom = (y + font::GLYPH_SIZE * scale).min(self.height);
+        if x >= right {
+            return;
+        }
+        for line in 0..bottom.saturating_sub(y) {
+            let bits = glyph[line / scale];
+            self.update_row(y + line, |row| {
+                for (column, pixel) in row[x..right].iter_mut().enumerate() {
+                    *pixel = if bits & (1 << (column / scale)) != 0 { foreground } else { background };
+                }
+            });
+        }
+    }
+
+    /// Moves the picture up by `lines` pixel rows, filling the bottom with `color`.
+    ///
+    /// Without `pan`, the picture is copied even when the displayed window could
+    /// move instead, so that the mailbox (whose owner may be stopped) is not used.
+    pub fn scroll_up(&mut self, lines: usize, color: u32, pan: bool) {
+        let lines = lines.min(self.height);
+        if self.panning && pan {
+            // Both halves hold the screen, so the window can wrap to the top
+            self.offset = (self.offset + lines) % self.height;
+            // The offset stays within the virtual size the firmware accepted
+            let _ = mailbox::set_virtual_offset(0, self.offset as u32);
+        } else {
+            for y in 0..self.height - lines {
+                // Moved top-down, so every source row is read before it is overwritten
+                let source = self.pixel(0, self.offset + y + lines);
+                // SAFETY: The source row is within the buffer and differs from the written ones
+                self.update_row(y, |row| unsafe { ptr::copy_nonoverlapping(source, row.as_mut_ptr(), row.len()) });
+            }
+        }
+        self.fill_rect(0, self.height - lines, self.width, lines, color);
+    }
+}
+
+// Implement thread safety
+// SAFETY: The buffer is owned by the framebuffer
+unsafe impl Send for Framebuffer {}
@@ -0,0 +1,112 @@
+// Import dependencies
+use crate::arch::cpu::IrqGuard;
+use crate::sync::spin::Spinlock;
+use super::{font::{self, GLYPH_SIZE}, Framebuffer};
+
+// Define constants
+/// Pixels per font bit
+const SCALE: usize = 2;
+const CELL_SIZE: usize = GLYPH_SIZE * SCALE;
+const TAB_WIDTH: usize = 8;
+
+// Define structs
+/// Scrolling text console drawn on a framebuffer
+struct TextConsole {
+    framebuffer: Framebuffer,
+    columns: usize,
+    rows: usize,
+    column: usize,
+    row: usize,
+    foreground: u32,
+    background: u32,
+}
+
+// Define globals
+static CONSOLE: Spinlock<Option<TextConsole>> = Spinlock::new(None);
+
+// Define procedures
+/// Clears `framebuffer` and starts drawing the text written from now on to it.
+pub fn init(framebuffer: Framebuffer) {
+    let mut console = TextConsole {
+        columns: framebuffer.width() / CELL_SIZE,
+        rows: framebuffer.height() / CELL_SIZE,
+        column: 0,
+        row: 0,
+        foreground: framebuffer.color(0xC0, 0xC0, 0xC0),
+        background: framebuffer.color(0, 0, 0),
+        framebuffer,
+    };
+    console.clear();
+    let _irq = IrqGuard::new();
+    *CONSOLE.lock() = Some(console);
+}
+
+/// Whether a framebuffer was given to the console.
+pub fn is_ready() -> bool {
+    let _irq = IrqGuard::new();
+    CONSOLE.lock().is_some()
+}
+
+/// Draws `s`, scrolling when the screen is full.
+pub fn write_str(s: &str) {
+    let _irq = IrqGuard::new();
+    if let Some(console) = CONSOLE.lock().as_mut() {
+        console.write_str(s, true);
+    }
+}
+
+/// Draws `s` unless the console is busy, for panics (where its owner may be stopped).
+///
+/// Scrolling copies the picture, the mailbox may be held by a stopped core too.
+pub fn try_write_str(s: &str) {
+    let _irq = IrqGuard::new();
+    if let Some(console) = CONSOLE.try_lock().as_deref_mut().and_then(Option::as_mut) {
+        console.write_str(s, false);
+    }
+}
+
+// Implement structs
+impl TextConsole {
+    fn clear(&mut self) {
+        let (width, height) = (self.framebuffer.width(), self.framebuffer.height());
+        self.framebuffer.fill_rect(0, 0, width, height, self.background);
+        self.column = 0;
+        self.row = 0;
+    }
+
+    /// Draws `s`, scrolling by panning the framebuffer if `pan`.
+    fn write_str(&mut self, s: &str, pan: bool) {
+        for c in s.chars() {
+            match c {
+                '\n' => self.new_line(pan),
+                '\r' => self.column = 0,
+                '\t' => {
+                    for _ in 0..TAB_WIDTH - self.column % TAB_WIDTH {
+                        self.put(' ', pan);
+                    }
+                }
+                // Backspace
+                '\x08' => self.column = self.column.saturating_sub(1),
+                c => self.put(c, pan),
+            }
+        }
+    }
+
+    fn put(&mut self, c: char, pan: bool) {
+        if self.column == self.columns {
+            self.new_line(pan);
+        }
+        let (x, y) = (self.column * CELL_SIZE, self.row * CELL_SIZE);
+        self.framebuffer.draw_glyph(x, y, font::glyph(c), SCALE, self.foreground, self.background);
+        self.column += 1;
+    }
+
+    fn new_line(&mut self, pan: bool) {
+        self.column = 0;
+        if self.row + 1 < self.rows {
+            self.row += 1;
+        } else {
+            self.framebuffer.scroll_up(CELL_SIZE, self.background, pan);
+        }
+    }
+}
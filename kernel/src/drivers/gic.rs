@@ -0,0 +1,118 @@
+// Import dependencies
+use crate::arch::interrupts::irq;
+use super::mmio::{registers, ReadOnly, ReadWrite, WriteOnly};
+use super::LOCAL_PERIPHERAL_BASE;
+
+// Define constants
+/// GIC-400 distributor (BCM2711 low-peripheral mapping)
+const DISTRIBUTOR_BASE: usize = LOCAL_PERIPHERAL_BASE + 0x4_1000;
+/// GIC-400 CPU interface
+const CPU_INTERFACE_BASE: usize = LOCAL_PERIPHERAL_BASE + 0x4_2000;
+/// Software-generated interrupts 0..15 (banked per core)
+pub const IRQ_SGI_0: usize = 0;
+pub const SGI_COUNT: usize = 16;
+/// Non-secure physical timer (private peripheral interrupt)
+pub const IRQ_CNTPNS: usize = 30;
+/// Shared peripheral interrupts start after the banked SGIs and PPIs
+const FIRST_SPI: usize = 32;
+/// Supported interrupt lines, in blocks of 32 (`GICD_TYPER.ITLinesNumber`)
+const TYPE_LINES_MASK: u32 = 0x1F;
+/// Target byte of each interrupt (4 per register): core 0
+const TARGETS_CORE_0: u32 = 0x0101_0101;
+/// Interrupt IDs from this one on are special (1023 is spurious)
+const SPECIAL_IRQ: u32 = 1020;
+const IAR_ID_MASK: u32 = 0x3FF;
+/// Lowest priority mask: every priority is forwarded
+const PRIORITY_MASK_ALL: u32 = 0xFF;
+
+// Define structs
+#[repr(C)]
+struct DistributorRegisters {
+    control: ReadWrite<u32>,
+    type_info: ReadOnly<u32>,
+    _reserved0: [u32; 30],
+    group: [ReadWrite<u32>; 32],
+    set_enable: [ReadWrite<u32>; 32],
+    clear_enable: [ReadWrite<u32>; 32],
+    _reserved1: [u32; 0x180],
+    targets: [ReadWrite<u32>; 256],
+    _reserved2: [u32; 0xC0],
+    software_interrupt: WriteOnly<u32>,
+}
+
+#[repr(C)]
+struct CpuInterfaceRegisters {
+    control: ReadWrite<u32>,
+    priority_mask: ReadWrite<u32>,
+    binary_point: ReadWrite<u32>,
+    acknowledge: ReadOnly<u32>,
+    end_of_interrupt: WriteOnly<u32>,
+}
+
+// Define procedures
+/// Routes the shared interrupts to core 0 (where the drivers handle them),
+/// enables the distributor and makes the GIC the root IRQ handler.
+pub fn init() {
+    let distributor = distributor();
+    distributor.control.write(0);
+    let lines = ((distributor.type_info.read() & TYPE_LINES_MASK) as usize + 1) * 32;
+    // Group 0 is signalled as IRQ (writes are ignored when the firmware left us non-secure)
+    for group in &distributor.group[FIRST_SPI / 32..lines / 32] {
+        group.write(0);
+    }
+    for targets in &distributor.targets[FIRST_SPI / 4..lines / 4] {
+        targets.write(TARGETS_CORE_0);
+    }
+    distributor.control.write(1);
+    irq::set_root_handler(handle_pending);
+}
+
+/// Enables the CPU interface of the calling core.
+pub fn init_core() {
+    // The SGIs and PPIs are banked, so each core sets their group
+    distributor().group[0].write(0);
+    let cpu_interface = cpu_interface();
+    cpu_interface.priority_mask.write(PRIORITY_MASK_ALL);
+    cpu_interface.binary_point.write(0);
+    cpu_interface.control.write(1);
+}
+
+/// Enables `irq` (SGIs and PPIs are enabled for the calling core only).
+pub fn enable(irq: usize) {
+    distributor().set_enable[irq / 32].write(1 << (irq % 32));
+}
+
+pub fn disable(irq: usize) {
+    distributor().clear_enable[irq / 32].write(1 << (irq % 32));
+}
+
+/// Raises software-generated interrupt `sgi` on `core`.
+pub fn send_sgi(core: usize, sgi: usize) {
+    assert!(sgi < SGI_COUNT, "Invalid SGI {}", sgi);
+    distributor().software_interrupt.write((1 << (16 + core)) | sgi as u32);
+}
+
+// Define helpers
+fn handle_pending() {
+    let cpu_interface = cpu_interface();
+    loop {
+        let acknowledged = cpu_interface.acknowledge.read();
+        let id = acknowledged & IAR_ID_MASK;
+        if id >= SPECIAL_IRQ {
+            break;
+        }
+        irq::dispatch(id as usize);
+        // The source core bits of SGIs must be written back too
+        cpu_interface.end_of_interrupt.write(acknowledged);
+    }
+}
+
+fn distributor() -> &'static DistributorRegisters {
+    // SAFETY: The GIC is always mapped
+    unsafe { registers(DISTRIBUTOR_BASE) }
+}
+
+fn cpu_interface() -> &'static CpuInterfaceRegisters {
+    // SAFETY: The GIC is always mapped (the CPU interface is banked per core)
+    unsafe { registers(CPU_INTERFACE_BASE) }
+}
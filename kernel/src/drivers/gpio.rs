@@ -0,0 +1,274 @@
+// Import dependencies
+use core::{hint, mem, sync::atomic::{AtomicUsize, Ordering}, time::Duration};
+use bitflags::bitflags;
+use crate::arch::cpu::IrqGuard;
+use crate::sync::spin::Spinlock;
+use crate::time::Timer;
+use super::gpu_intc::{self, IRQ_GPIO_0};
+use super::mmio::{registers, ReadOnly, ReadWrite, WriteOnly};
+use super::PERIPHERAL_BASE;
+
+// Define constants
+/// BCM2837 GPIO controller
+const GPIO_BASE: usize = PERIPHERAL_BASE + 0x20_0000;
+pub const PIN_COUNT: usize = 54;
+/// Cycles the pull-up/down control signal must be held
+const PULL_SETUP_CYCLES: usize = 150;
+/// Event interrupt line of all banks (the first three are per bank)
+const IRQ_GPIO_ANY: usize = IRQ_GPIO_0 + 3;
+/// Events are delivered from the interrupt, unless the GPU interrupts are
+/// not wired up (they are then polled every `POLL_INTERVAL`)
+pub const IRQ_DRIVEN: bool = gpu_intc::IRQ_DRIVEN;
+const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+// Define structs
+/// Function of a pin (its alternate functions are listed in the BCM2835 peripherals manual)
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Function {
+    Input = 0b000,
+    Output = 0b001,
+    Alt0 = 0b100,
+    Alt1 = 0b101,
+    Alt2 = 0b110,
+    Alt3 = 0b111,
+    Alt4 = 0b011,
+    Alt5 = 0b010,
+}
+
+/// Pull resistor of a pin
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pull {
+    None = 0b00,
+    Down = 0b01,
+    Up = 0b10,
+}
+
+bitflags! {
+    /// Conditions latched into the event status of a pin
+    pub struct Events: u32 {
+        /// Rising edge, sampled against the system clock (filters glitches)
+        const RISING_EDGE = 1 << 0;
+        const FALLING_EDGE = 1 << 1;
+        /// High level, reported for as long as it lasts
+        const HIGH = 1 << 2;
+        const LOW = 1 << 3;
+        /// Rising edge, not sampled (catches short pulses)
+        const ASYNC_RISING_EDGE = 1 << 4;
+        const ASYNC_FALLING_EDGE = 1 << 5;
+    }
+}
+
+/// Called with the pin number when an event is detected on it
+pub type EventHandler = fn(usize);
+
+#[repr(C)]
+struct Registers {
+    function_select: [ReadWrite<u32>; 6],
+    _reserved0: u32,
+    set: [WriteOnly<u32>; 2],
+    _reserved1: u32,
+    clear: [WriteOnly<u32>; 2],
+    _reserved2: u32,
+    level: [ReadOnly<u32>; 2],
+    _reserved3: u32,
+    event_status: [ReadWrite<u32>; 2],
+    _reserved4: u32,
+    rising_edge_detect: [ReadWrite<u32>; 2],
+    _reserved5: u32,
+    falling_edge_detect: [ReadWrite<u32>; 2],
+    _reserved6: u32,
+    high_detect: [ReadWrite<u32>; 2],
+    _reserved7: u32,
+    low_detect: [ReadWrite<u32>; 2],
+    _reserved8: u32,
+    async_rising_edge_detect: [ReadWrite<u32>; 2],
+    _reserved9: u32,
+    async_falling_edge_detect: [ReadWrite<u32>; 2],
+    _reserved10: u32,
+    pull_control: ReadWrite<u32>,
+    pull_clock: [ReadWrite<u32>; 2],
+}
+
+// Define globals
+/// Serializes the read-modify-write and sequenced register accesses
+static LOCK: Spinlock<()> = Spinlock::new(());
+/// Event handlers stored as function addresses (0 when none), read without locks from IRQ context
+static HANDLERS: [AtomicUsize; PIN_COUNT] = [ATOMIC_NONE; PIN_COUNT];
+/// Runs `poll_events` when not `IRQ_DRIVEN`
+static POLLER: Timer = Timer::new(poll, 0);
+#[allow(clippy::declare_interior_mutable_const)]
+const ATOMIC_NONE: AtomicUsize = AtomicUsize::new(0);
+
+// Define procedures
+/// Selects the function of `pin`.
+pub fn set_function(pin: usize, function: Function) {
+    assert!(pin < PIN_COUNT, "Invalid GPIO pin {}", pin);
+    let shift = (pin % 10) * 3;
+    let _irq = IrqGuard::new();
+    let _lock = LOCK.lock();
+    regs().function_select[pin / 10].modify(|select| (select & !(0b111 << shift)) | ((function as u32) << shift));
+}
+
+/// Selects the pull resistor of `pin`.
+pub fn set_pull(pin: usize, pull: Pull) {
+    assert!(pin < PIN_COUNT, "Invalid GPIO pin {}", pin);
+    let regs = regs();
+    let _irq = IrqGuard::new();
+    let _lock = LOCK.lock();
+    // Clock the control signal into the pin
+    regs.pull_control.write(pull as u32);
+    wait_cycles(PULL_SETUP_CYCLES);
+    regs.pull_clock[pin / 32].write(1 << (pin % 32));
+    wait_cycles(PULL_SETUP_CYCLES);
+    regs.pull_control.write(0);
+    regs.pull_clock[pin / 32].write(0);
+}
+
+/// Drives output `pin` high or low.
+pub fn write(pin: usize, high: bool) {
+    if high {
+        set(pin);
+    } else {
+        clear(pin);
+    }
+}
+
+/// Drives output `pin` high.
+pub fn set(pin: usize) {
+    let (bank, bit) = bank_bit(pin);
+    regs().set[bank].write(1 << bit);
+}
+
+/// Drives output `pin` low.
+pub fn clear(pin: usize) {
+    let (bank, bit) = bank_bit(pin);
+    regs().clear[bank].write(1 << bit);
+}
+
+/// Level of `pin` (whatever its function).
+pub fn read(pin: usize) -> bool {
+    let (bank, bit) = bank_bit(pin);
+    regs().level[bank].read() & (1 << bit) != 0
+}
+
+/// Calls `handler` on `events` of `pin`, from IRQ context.
+///
+/// Level events keep firing until the handler changes the level or disables
+/// them. Fails, returning the installed handler, if the pin already has one.
+pub fn enable_events(pin: usize, events: Events, handler: EventHandler) -> Result<(), EventHandler> {
+    let (bank, bit) = bank_bit(pin);
+    HANDLERS[pin]
+        .compare_exchange(0, handler as usize, Ordering::AcqRel, Ordering::Acquire)
+        // SAFETY: Only function addresses are stored
+        .map_err(|installed| unsafe { to_handler(installed) })?;
+    gpu_intc::request(IRQ_GPIO_ANY, handle_irq);
+    if !IRQ_DRIVEN && !POLLER.is_pending() {
+        POLLER.start_periodic(POLL_INTERVAL);
+    }
+    let _irq = IrqGuard::new();
+    let _lock = LOCK.lock();
+    // Drops events latched before
+    regs().event_status[bank].write(1 << bit);
+    set_detection(pin, events);
+    Ok(())
+}
+
+/// Stops the event detection of `pin`, returning its handler.
+pub fn disable_events(pin: usize) -> Option<EventHandler> {
+    let (bank, bit) = bank_bit(pin);
+    {
+        let _irq = IrqGuard::new();
+        let _lock = LOCK.lock();
+        set_detection(pin, Events::empty());
+        regs().event_status[bank].write(1 << bit);
+    }
+    match HANDLERS[pin].swap(0, Ordering::AcqRel) {
+        0 => None,
+        // SAFETY: Only function addresses are stored
+        handler => Some(unsafe { to_handler(handler) }),
+    }
+}
+
+/// Runs the handlers of the latched events, returning whether there were any.
+///
+/// Called by the interrupt handler, or periodically when not `IRQ_DRIVEN`.
+pub fn poll_events() -> bool {
+    let regs = regs();
+    let mut handled = false;
+    for (bank, status) in regs.event_status.iter().enumerate() {
+        let mut pending = status.read();
+        // Acknowledges before running the handlers, so events they cause are not lost
+        status.write(pending);
+        while pending != 0 {
+            let pin = bank * 32 + pending.trailing_zeros() as usize;
+            match HANDLERS[pin].load(Ordering::Acquire) {
+                // Nobody wants it, so it would keep firing
+                0 => {
+                    let _irq = IrqGuard::new();
+                    let _lock = LOCK.lock();
+                    set_detection(pin, Events::empty());
+                }
+                handler => {
+                    // SAFETY: Only function addresses are stored
+                    let handler = unsafe { to_handler(handler) };
+                    handler(pin);
+                }
+            }
+            handled = true;
+            pending &= pending - 1;
+        }
+    }
+    handled
+}
+
+// Define helpers
+/// Enables exactly `events` on `pin` (with the lock held).
+fn set_detection(pin: usize, events: Events) {
+    let (bank, bit) = bank_bit(pin);
+    let regs = regs();
+    let detectors = [
+        (&regs.rising_edge_detect, Events::RISING_EDGE),
+        (&regs.falling_edge_detect, Events::FALLING_EDGE),
+        (&regs.high_detect, Events::HIGH),
+        (&regs.low_detect, Events::LOW),
+        (&regs.async_rising_edge_detect, Events::ASYNC_RISING_EDGE),
+        (&regs.async_falling_edge_detect, Events::ASYNC_FALLING_EDGE),
+    ];
+    for (detect, event) in detectors {
+        if events.contains(event) {
+            detect[bank].modify(|pins| pins | (1 << bit));
+        } else {
+            detect[bank].modify(|pins| pins & !(1 << bit));
+        }
+    }
+}
+
+fn handle_irq() {
+    poll_events();
+}
+
+fn poll(_: usize) {
+    poll_events();
+}
+
+fn bank_bit(pin: usize) -> (usize, usize) {
+    assert!(pin < PIN_COUNT, "Invalid GPIO pin {}", pin);
+    (pin / 32, pin % 32)
+}
+
+unsafe fn to_handler(address: usize) -> EventHandler {
+    mem::transmute::<usize, EventHandler>(address)
+}
+
+fn wait_cycles(cycles: usize) {
+    for _ in 0..cycles {
+        hint::spin_loop();
+    }
+}
+
+fn regs() -> &'static Registers {
+    // SAFETY: The GPIO controller is always mapped
+    unsafe { registers(GPIO_BASE) }
+}
@@ -0,0 +1,114 @@
+// Import dependencies
+use crate::arch::interrupts::irq;
+#[cfg(not(feature = "gic"))]
+use super::local_intc::IRQ_GPU;
+#[cfg(not(feature = "gic"))]
+use super::mmio::{registers, ReadOnly, ReadWrite, WriteOnly};
+#[cfg(not(feature = "gic"))]
+use super::PERIPHERAL_BASE;
+
+// Define constants
+/// BCM2835 interrupt controller of the GPU peripherals (not wired up with the GIC,
+/// where only the line numbers and `request` are compiled)
+#[cfg(not(feature = "gic"))]
+const GPU_INTC_BASE: usize = PERIPHERAL_BASE + 0xB200;
+/// Drivers use the GPU interrupts, unless they are not wired up (with the GIC)
+pub const IRQ_DRIVEN: bool = cfg!(not(feature = "gic"));
+/// IRQ number of GPU interrupt 0 (the lower numbers are the ARM-local sources)
+pub const GPU_IRQ_BASE: usize = 32;
+pub const GPU_IRQ_COUNT: usize = 64;
+/// System timer compare channels 1 and 3 (0 and 2 are used by the GPU)
+pub const IRQ_SYSTEM_TIMER_1: usize = GPU_IRQ_BASE + 1;
+pub const IRQ_SYSTEM_TIMER_3: usize = GPU_IRQ_BASE + 3;
+/// DMA channel `n` interrupts on `IRQ_DMA_0 + n` (the last channels share a line)
+pub const IRQ_DMA_0: usize = GPU_IRQ_BASE + 16;
+/// Auxiliary peripherals (mini UART and SPI 1/2)
+pub const IRQ_AUX: usize = GPU_IRQ_BASE + 29;
+/// GPIO banks 0..3 (the last one fires for any pin)
+pub const IRQ_GPIO_0: usize = GPU_IRQ_BASE + 49;
+pub const IRQ_UART: usize = GPU_IRQ_BASE + 57;
+/// Arasan SDHCI (EMMC) controller
+pub const IRQ_EMMC: usize = GPU_IRQ_BASE + 62;
+
+// Define structs
+#[cfg(not(feature = "gic"))]
+#[repr(C)]
+struct Registers {
+    basic_pending: ReadOnly<u32>,
+    pending: [ReadOnly<u32>; 2],
+    fiq_control: ReadWrite<u32>,
+    enable: [WriteOnly<u32>; 2],
+    enable_basic: WriteOnly<u32>,
+    disable: [WriteOnly<u32>; 2],
+    disable_basic: WriteOnly<u32>,
+}
+
+// Define procedures
+/// Dispatches the GPU interrupts from the local GPU line.
+///
+/// GPU interrupts are routed to core 0.
+#[cfg(not(feature = "gic"))]
+pub fn init() {
+    if let Err(installed) = irq::register(IRQ_GPU, handle_pending) {
+        assert!(installed == handle_pending as irq::IrqHandler, "GPU IRQ already in use");
+    }
+}
+
+/// Installs `handler` for GPU interrupt `irq` and enables it (nothing when not `IRQ_DRIVEN`).
+///
+/// Requesting a line again with the same handler has no effect.
+///
+/// # Panics
+///
+/// Panics if the line has another handler.
+#[cfg(not(feature = "gic"))]
+pub fn request(irq: usize, handler: irq::IrqHandler) {
+    if let Err(installed) = irq::register(irq, handler) {
+        assert!(installed == handler, "IRQ {} already in use", irq);
+    }
+    enable(irq);
+}
+
+#[cfg(feature = "gic")]
+pub fn request(_irq: usize, _handler: irq::IrqHandler) {}
+
+/// Enables GPU interrupt `irq` (numbered from `GPU_IRQ_BASE`).
+#[cfg(not(feature = "gic"))]
+pub fn enable(irq: usize) {
+    let (bank, bit) = bank_bit(irq);
+    regs().enable[bank].write(1 << bit);
+}
+
+#[cfg(not(feature = "gic"))]
+pub fn disable(irq: usize) {
+    let (bank, bit) = bank_bit(irq);
+    regs().disable[bank].write(1 << bit);
+}
+
+// Define helpers
+#[cfg(not(feature = "gic"))]
+fn handle_pending() {
+    for (bank, pending) in regs().pending.iter().enumerate() {
+        let mut pending = pending.read();
+        while pending != 0 {
+            let irq = GPU_IRQ_BASE + bank * 32 + pending.trailing_zeros() as usize;
+            // Nobody would acknowledge it
+            if !irq::dispatch(irq) {
+                disable(irq);
+            }
+            pending &= pending - 1;
+        }
+    }
+}
+
+#[cfg(not(feature = "gic"))]
+fn bank_bit(irq: usize) -> (usize, usize) {
+    assert!((GPU_IRQ_BASE..GPU_IRQ_BASE + GPU_IRQ_COUNT).contains(&irq), "Not a GPU interrupt: {}", irq);
+    ((irq - GPU_IRQ_BASE) / 32, (irq - GPU_IRQ_BASE) % 32)
+}
+
+#[cfg(not(feature = "gic"))]
+fn regs() -> &'static Registers {
+    // SAFETY: GPU peripherals are always mapped
+    unsafe { registers(GPU_INTC_BASE) }
+}
@@ -0,0 +1,106 @@
+// Import dependencies
+use crate::arch::{cpu, interrupts::irq};
+use super::mmio::{registers, ReadOnly, ReadWrite, WriteOnly};
+use super::LOCAL_PERIPHERAL_BASE;
+
+// Define constants
+/// Number of per-core interrupt sources
+pub const LOCAL_IRQ_COUNT: usize = 12;
+/// Physical secure timer
+pub const IRQ_CNTPS: usize = 0;
+/// Physical non-secure timer
+pub const IRQ_CNTPNS: usize = 1;
+/// Hypervisor timer
+pub const IRQ_CNTHP: usize = 2;
+/// Virtual timer
+pub const IRQ_CNTV: usize = 3;
+/// Mailboxes 0..3
+pub const IRQ_MAILBOX_0: usize = 4;
+/// Interrupt from the GPU (BCM2835) interrupt controller
+pub const IRQ_GPU: usize = 8;
+pub const IRQ_PMU: usize = 9;
+pub const IRQ_LOCAL_TIMER: usize = 11;
+
+// Define structs
+#[repr(C)]
+struct Registers {
+    control: ReadWrite<u32>,
+    _reserved0: u32,
+    core_timer_prescaler: ReadWrite<u32>,
+    gpu_interrupt_routing: ReadWrite<u32>,
+    pmu_interrupt_set: WriteOnly<u32>,
+    pmu_interrupt_clear: WriteOnly<u32>,
+    _reserved1: u32,
+    core_timer_low: ReadWrite<u32>,
+    core_timer_high: ReadWrite<u32>,
+    local_interrupt_routing: ReadWrite<u32>,
+    _reserved2: u32,
+    axi_outstanding_counters: ReadWrite<u32>,
+    axi_outstanding_irq: ReadWrite<u32>,
+    local_timer_control: ReadWrite<u32>,
+    local_timer_flags: WriteOnly<u32>,
+    _reserved3: u32,
+    core_timer_interrupt_control: [ReadWrite<u32>; cpu::CORE_COUNT],
+    core_mailbox_interrupt_control: [ReadWrite<u32>; cpu::CORE_COUNT],
+    core_irq_source: [ReadOnly<u32>; cpu::CORE_COUNT],
+    core_fiq_source: [ReadOnly<u32>; cpu::CORE_COUNT],
+    core_mailbox_write_set: [[WriteOnly<u32>; 4]; cpu::CORE_COUNT],
+    core_mailbox_read_clear: [[ReadWrite<u32>; 4]; cpu::CORE_COUNT],
+}
+
+// Define procedures
+/// Makes the local controller the root IRQ handler.
+pub fn init() {
+    irq::set_root_handler(handle_pending);
+}
+
+/// Routes a core timer interrupt (`IRQ_CNTPS..=IRQ_CNTV`) of the calling core to its IRQ line.
+pub fn enable_timer_irq(timer_irq: usize) {
+    assert!(timer_irq <= IRQ_CNTV, "Not a core timer interrupt: {}", timer_irq);
+    regs().core_timer_interrupt_control[cpu::this_core()].modify(|control| control | (1 << timer_irq));
+}
+
+pub fn disable_timer_irq(timer_irq: usize) {
+    assert!(timer_irq <= IRQ_CNTV, "Not a core timer interrupt: {}", timer_irq);
+    regs().core_timer_interrupt_control[cpu::this_core()].modify(|control| control & !(1 << timer_irq));
+}
+
+/// Routes the IRQ of `mailbox` (0..3) of the calling core to its IRQ line.
+pub fn enable_mailbox_irq(mailbox: usize) {
+    assert!(mailbox < 4, "Invalid mailbox {}", mailbox);
+    regs().core_mailbox_interrupt_control[cpu::this_core()].modify(|control| control | (1 << mailbox));
+}
+
+/// Sets `bits` in `mailbox` of `core` (raising its mailbox IRQ).
+pub fn mailbox_send(core: usize, mailbox: usize, bits: u32) {
+    regs().core_mailbox_write_set[core][mailbox].write(bits);
+}
+
+/// Reads and clears `mailbox` of the calling core.
+pub fn mailbox_take(mailbox: usize) -> u32 {
+    let mailbox = &regs().core_mailbox_read_clear[cpu::this_core()][mailbox];
+    let bits = mailbox.read();
+    // Writing ones clears them
+    mailbox.write(bits);
+    bits
+}
+
+/// Pending interrupt sources of the calling core (bit per local IRQ number)
+pub fn pending() -> u32 {
+    regs().core_irq_source[cpu::this_core()].read()
+}
+
+// Define helpers
+fn handle_pending() {
+    let mut pending = pending() & ((1 << LOCAL_IRQ_COUNT) - 1);
+    while pending != 0 {
+        let source = pending.trailing_zeros() as usize;
+        irq::dispatch(source);
+        pending &= pending - 1;
+    }
+}
+
+fn regs() -> &'static Registers {
+    // SAFETY: ARM-local peripherals are always mapped
+    unsafe { registers(LOCAL_PERIPHERAL_BASE) }
+}
@@ -0,0 +1,339 @@
+// Import dependencies
+use core::{fmt, hint, mem};
+use crate::arch::cpu::{self, IrqGuard};
+use crate::sync::spin::Spinlock;
+use super::mmio::{registers, ReadOnly, WriteOnly};
+use super::PERIPHERAL_BASE;
+
+// Define constants
+/// VideoCore mailbox 0 (GPU to ARM) and 1 (ARM to GPU)
+const MAILBOX_BASE: usize = PERIPHERAL_BASE + 0xB880;
+/// Channel of the property tags (ARM to VideoCore)
+const CHANNEL_PROPERTY: u32 = 8;
+const STATUS_FULL: u32 = 1 << 31;
+const STATUS_EMPTY: u32 = 1 << 30;
+/// GPU bus alias of the ARM memory, bypassing the GPU L2 cache
+const BUS_UNCACHED: u32 = 0xC000_0000;
+/// Capacity of a property message, in words
+pub const MESSAGE_WORDS: usize = 64;
+/// Words of the message header (size and code)
+const HEADER_WORDS: usize = 2;
+const END_TAG: u32 = 0;
+/// Tag header words (tag, value buffer size, request/response code)
+const TAG_HEADER_WORDS: usize = 3;
+const CODE_REQUEST: u32 = 0;
+const CODE_SUCCESS: u32 = 0x8000_0000;
+/// Set in the tag code by the firmware, with the response length in the low bits
+const TAG_RESPONSE: u32 = 1 << 31;
+/// Power state bits
+const POWER_ON: u32 = 1 << 0;
+const POWER_WAIT: u32 = 1 << 1;
+const POWER_NO_DEVICE: u32 = 1 << 1;
+
+// Define structs
+/// Property tags understood by the firmware
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tag {
+    FirmwareRevision = 0x0000_0001,
+    BoardModel = 0x0001_0001,
+    BoardRevision = 0x0001_0002,
+    MacAddress = 0x0001_0003,
+    BoardSerial = 0x0001_0004,
+    ArmMemory = 0x0001_0005,
+    VcMemory = 0x0001_0006,
+    PowerState = 0x0002_0001,
+    SetPowerState = 0x0002_8001,
+    ClockRate = 0x0003_0002,
+    MaxClockRate = 0x0003_0004,
+    SetClockRate = 0x0003_8002,
+    Temperature = 0x0003_0006,
+    AllocateBuffer = 0x0004_0001,
+    Pitch = 0x0004_0008,
+    SetPhysicalSize = 0x0004_8003,
+    SetVirtualSize = 0x0004_8004,
+    SetDepth = 0x0004_8005,
+    SetPixelOrder = 0x0004_8006,
+    SetVirtualOffset = 0x0004_8009,
+    DmaChannels = 0x0006_0001,
+}
+
+/// Clocks managed by the firmware
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Clock {
+    Emmc = 1,
+    Uart = 2,
+    Arm = 3,
+    /// VPU clock, which the mini UART and SPI derive from
+    Core = 4,
+    V3d = 5,
+    H264 = 6,
+    Isp = 7,
+    Sdram = 8,
+    Pixel = 9,
+    Pwm = 10,
+}
+
+/// Peripherals whose power is managed by the firmware
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Device {
+    SdCard = 0,
+    Uart0 = 1,
+    Uart1 = 2,
+    UsbHcd = 3,
+    I2c0 = 4,
+    I2c1 = 5,
+    I2c2 = 6,
+    Spi = 7,
+    Ccp2tx = 8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MailboxError {
+    /// The tags do not fit in `MESSAGE_WORDS`
+    MessageFull,
+    /// The firmware rejected the message
+    Failed,
+    /// The firmware did not answer the tag, or answered more than its buffer holds
+    TagFailed(u32),
+    /// The device is not present on the board
+    NoDevice,
+}
+
+/// Property message, built tag by tag and sent with `send`.
+pub struct Message {
+    buffer: MessageBuffer,
+    len: usize,
+}
+
+/// Words shared with the firmware (the low 4 address bits carry the channel)
+#[repr(C, align(16))]
+struct MessageBuffer([u32; MESSAGE_WORDS]);
+
+/// Position of a tag in a [`Message`], to read its response
+#[derive(Debug, Clone, Copy)]
+pub struct TagSlot {
+    offset: usize,
+    words: usize,
+}
+
+#[repr(C)]
+struct Registers {
+    read: ReadOnly<u32>,
+    _reserved0: [u32; 3],
+    peek: ReadOnly<u32>,
+    sender: ReadOnly<u32>,
+    status: ReadOnly<u32>,
+    config: ReadOnly<u32>,
+    write: WriteOnly<u32>,
+    _reserved1: [u32; 5],
+    write_status: ReadOnly<u32>,
+}
+
+// Define globals
+/// Serializes the request and response of a message
+static LOCK: Spinlock<()> = Spinlock::new(());
+
+// Define procedures
+pub fn firmware_revision() -> Result<u32, MailboxError> {
+    query::<1>(Tag::FirmwareRevision, &[]).map(|[revision]| revision)
+}
+
+pub fn board_model() -> Result<u32, MailboxError> {
+    query::<1>(Tag::BoardModel, &[]).map(|[model]| model)
+}
+
+/// Revision code of the board (encodes the model, memory size and manufacturer)
+pub fn board_revision() -> Result<u32, MailboxError> {
+    query::<1>(Tag::BoardRevision, &[]).map(|[revision]| revision)
+}
+
+pub fn board_serial() -> Result<u64, MailboxError> {
+    query::<2>(Tag::BoardSerial, &[]).map(|[low, high]| (high as u64) << 32 | low as u64)
+}
+
+pub fn mac_address() -> Result<[u8; 6], MailboxError> {
+    let [low, high] = query::<2>(Tag::MacAddress, &[])?;
+    let (low, high) = (low.to_le_bytes(), high.to_le_bytes());
+    Ok([low[0], low[1], low[2], low[3], high[0], high[1]])
+}
+
+/// Base address and size of the memory given to the ARM cores
+pub fn arm_memory() -> Result<(usize, usize), MailboxError> {
+    query::<2>(Tag::ArmMemory, &[]).map(|[base, size]| (base as usize, size as usize))
+}
+
+/// Base address and size of the memory kept by the GPU
+pub fn vc_memory() -> Result<(usize, usize), MailboxError> {
+    query::<2>(Tag::VcMemory, &[]).map(|[base, size]| (base as usize, size as usize))
+}
+
+/// Current rate of `clock`, in Hz
+pub fn clock_rate(clock: Clock) -> Result<u32, MailboxError> {
+    query::<2>(Tag::ClockRate, &[clock as u32]).map(|[_, rate]| rate)
+}
+
+pub fn max_clock_rate(clock: Clock) -> Result<u32, MailboxError> {
+    query::<2>(Tag::MaxClockRate, &[clock as u32]).map(|[_, rate]| rate)
+}
+
+/// Sets the rate of `clock`, returning the rate the firmware chose.
+pub fn set_clock_rate(clock: Clock, rate: u32) -> Result<u32, MailboxError> {
+    // Do not let the firmware raise the other clocks along (turbo mode)
+    query::<2>(Tag::SetClockRate, &[clock as u32, rate, 1]).map(|[_, rate]| rate)
+}
+
+/// SoC temperature, in thousandths of a degree Celsius
+pub fn temperature() -> Result<u32, MailboxError> {
+    query::<2>(Tag::Temperature, &[0]).map(|[_, temperature]| temperature)
+}
+
+/// Whether `device` is powered on.
+pub fn power_state(device: Device) -> Result<bool, MailboxError> {
+    parse_power_state(query::<2>(Tag::PowerState, &[device as u32])?)
+}
+
+/// Powers `device` on or off, waiting for it to be stable, and returns its new state.
+pub fn set_power_state(device: Device, on: bool) -> Result<bool, MailboxError> {
+    let state = if on { POWER_ON | POWER_WAIT } else { POWER_WAIT };
+    parse_power_state(query::<2>(Tag::SetPowerState, &[device as u32, state])?)
+}
+
+/// DMA channels left to the ARM, one bit per channel
+pub fn dma_channels() -> Result<u32, MailboxError> {
+    query::<1>(Tag::DmaChannels, &[]).map(|[mask]| mask)
+}
+
+/// Moves the displayed part of the framebuffer to (`x`, `y`) in its virtual size,
+/// returning the offset the firmware applied.
+pub fn set_virtual_offset(x: u32, y: u32) -> Result<(u32, u32), MailboxError> {
+    query::<2>(Tag::SetVirtualOffset, &[x, y]).map(|[x, y]| (x, y))
+}
+
+// Implement structs
+impl Message {
+    pub const fn new() -> Self {
+        Self {
+            buffer: MessageBuffer([0; MESSAGE_WORDS]),
+            len: HEADER_WORDS,
+        }
+    }
+
+    /// Appends `tag` with its `request` values and room for `response_words` words of response.
+    pub fn add(&mut self, tag: Tag, request: &[u32], response_words: usize) -> Result<TagSlot, MailboxError> {
+        let words = request.len().max(response_words);
+        // Keep room for the end tag
+        if self.len + TAG_HEADER_WORDS + words + 1 > MESSAGE_WORDS {
+            return Err(MailboxError::MessageFull);
+        }
+        let header = [tag as u32, (words * mem::size_of::<u32>()) as u32, CODE_REQUEST];
+        self.buffer.0[self.len..self.len + TAG_HEADER_WORDS].copy_from_slice(&header);
+        let values = self.len + TAG_HEADER_WORDS;
+        self.buffer.0[values..values + words].fill(0);
+        self.buffer.0[values..values + request.len()].copy_from_slice(request);
+        let slot = TagSlot { offset: self.len, words };
+        self.len = values + words;
+        Ok(slot)
+    }
+
+    /// Sends the message on the property channel and waits for the firmware to answer it.
+    pub fn send(&mut self) -> Result<(), MailboxError> {
+        let words = &mut self.buffer.0;
+        words[0] = ((self.len + 1) * mem::size_of::<u32>()) as u32;
+        words[1] = CODE_REQUEST;
+        words[self.len] = END_TAG;
+        call(CHANNEL_PROPERTY, &mut words[..self.len + 1]);
+        match self.buffer.0[1] {
+            CODE_SUCCESS => Ok(()),
+            _ => Err(MailboxError::Failed),
+        }
+    }
+
+    /// Response values of the tag in `slot` (once sent).
+    pub fn response(&self, slot: TagSlot) -> Result<&[u32], MailboxError> {
+        let [tag, _, code] = [0, 1, 2].map(|word| self.buffer.0[slot.offset + word]);
+        let len = (code & !TAG_RESPONSE) as usize;
+        if code & TAG_RESPONSE == 0 || len > slot.words * mem::size_of::<u32>() {
+            return Err(MailboxError::TagFailed(tag));
+        }
+        let values = slot.offset + TAG_HEADER_WORDS;
+        let words = (len + mem::size_of::<u32>() - 1) / mem::size_of::<u32>();
+        Ok(&self.buffer.0[values..values + words])
+    }
+
+    /// Response of the tag in `slot` as `N` words (missing ones are 0).
+    pub fn response_words<const N: usize>(&self, slot: TagSlot) -> Result<[u32; N], MailboxError> {
+        let response = self.response(slot)?;
+        let mut words = [0; N];
+        let len = response.len().min(N);
+        words[..len].copy_from_slice(&response[..len]);
+        Ok(words)
+    }
+}
+
+impl Default for Message {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for MailboxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MailboxError::MessageFull => write!(f, "property message full"),
+            MailboxError::Failed => write!(f, "property message rejected by the firmware"),
+            MailboxError::TagFailed(tag) => write!(f, "property tag {:#010x} not answered", tag),
+            MailboxError::NoDevice => write!(f, "no such device"),
+        }
+    }
+}
+
+// Define helpers
+/// Sends a single tag and returns its first `N` response words.
+fn query<const N: usize>(tag: Tag, request: &[u32]) -> Result<[u32; N], MailboxError> {
+    let mut message = Message::new();
+    let slot = message.add(tag, request, N)?;
+    message.send()?;
+    message.response_words(slot)
+}
+
+fn parse_power_state([_, state]: [u32; 2]) -> Result<bool, MailboxError> {
+    match state & POWER_NO_DEVICE {
+        0 => Ok(state & POWER_ON != 0),
+        _ => Err(MailboxError::NoDevice),
+    }
+}
+
+/// Hands `buffer` to the firmware on `channel` and waits for it to be answered in place.
+fn call(channel: u32, buffer: &mut [u32]) {
+    let regs = regs();
+    // Exposed, as the firmware writes to it behind the compiler's back
+    let address = buffer.as_mut_ptr() as usize;
+    let size = mem::size_of_val(buffer);
+    let bus_address = address as u32 | BUS_UNCACHED;
+    // The firmware reads the buffer from memory, and writes the response there
+    cpu::clean_dcache_range(address, size);
+    let _irq = IrqGuard::new();
+    let _lock = LOCK.lock();
+    while regs.write_status.read() & STATUS_FULL != 0 {
+        hint::spin_loop();
+    }
+    regs.write.write(bus_address | channel);
+    loop {
+        while regs.status.read() & STATUS_EMPTY != 0 {
+            hint::spin_loop();
+        }
+        // Skip the messages of other channels
+        if regs.read.read() == bus_address | channel {
+            break;
+        }
+    }
+    cpu::invalidate_dcache_range(address, size);
+}
+
+fn regs() -> &'static Registers {
+    // SAFETY: GPU peripherals are always mapped
+    unsafe { registers(MAILBOX_BASE) }
+}
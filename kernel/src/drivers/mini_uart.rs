@@ -0,0 +1,182 @@
+// Import dependencies
+use core::{hint, sync::atomic::{AtomicBool, Ordering}};
+use crate::arch::cpu::IrqGuard;
+use crate::sync::spin::Spinlock;
+use crate::task::{self, WaitQueue};
+use super::gpio::{self, Function, Pull};
+use super::gpu_intc::{self, IRQ_AUX, IRQ_DRIVEN};
+use super::mailbox::{self, Clock};
+use super::mmio::{registers, ReadOnly, ReadWrite};
+use super::serial::ByteQueue;
+use super::PERIPHERAL_BASE;
+
+// Define constants
+/// BCM2835 auxiliary peripherals (mini UART, SPI 1 and 2)
+const AUX_BASE: usize = PERIPHERAL_BASE + 0x21_5000;
+/// VPU core clock the mini UART baud rate derives from, if the firmware cannot tell
+pub const DEFAULT_CORE_CLOCK: u32 = 250_000_000;
+pub const DEFAULT_BAUD_RATE: u32 = 115_200;
+/// Mini UART TX and RX (ALT5 of GPIO 14/15)
+const TX_PIN: usize = 14;
+const RX_PIN: usize = 15;
+/// `irq` and `enables` bit of the mini UART
+const AUX_MINI_UART: u32 = 1 << 0;
+/// Interrupt enable: RX (the datasheet swaps the RX and TX bits)
+const IER_RX: u32 = 1 << 0;
+/// Interrupt enable: bits 3:2 must be set too or no interrupt is raised (datasheet errata)
+const IER_REQUIRED: u32 = 0b11 << 2;
+/// Interrupt identify (write): clear both FIFOs
+const IIR_CLEAR_FIFOS: u32 = 0b11 << 1;
+/// Line control: 8-bit words
+const LCR_8_BITS: u32 = 0b11;
+/// Line status: RX data ready, TX FIFO can take a byte, transmitter idle
+const LSR_DATA_READY: u32 = 1 << 0;
+const LSR_TX_EMPTY: u32 = 1 << 5;
+const LSR_TX_IDLE: u32 = 1 << 6;
+/// Extra control: receiver and transmitter enabled
+const CNTL_RX: u32 = 1 << 0;
+const CNTL_TX: u32 = 1 << 1;
+
+// Define structs
+#[repr(C)]
+struct Registers {
+    /// Pending interrupts of the auxiliary peripherals
+    irq: ReadOnly<u32>,
+    enables: ReadWrite<u32>,
+    _reserved0: [u32; 14],
+    io: ReadWrite<u32>,
+    interrupt_enable: ReadWrite<u32>,
+    interrupt_identify: ReadWrite<u32>,
+    line_control: ReadWrite<u32>,
+    modem_control: ReadWrite<u32>,
+    line_status: ReadOnly<u32>,
+    modem_status: ReadOnly<u32>,
+    scratch: ReadWrite<u32>,
+    extra_control: ReadWrite<u32>,
+    extra_status: ReadOnly<u32>,
+    baud_rate: ReadWrite<u32>,
+}
+
+// Define globals
+static RX_BUFFER: Spinlock<ByteQueue> = Spinlock::new(ByteQueue::new());
+/// Keeps the bytes of concurrent writes together
+static TX_LOCK: Spinlock<()> = Spinlock::new(());
+/// Threads waiting in `read_byte`
+static READERS: WaitQueue = WaitQueue::new();
+static READY: AtomicBool = AtomicBool::new(false);
+
+// Define procedures
+/// Enables the mini UART on GPIO 14/15 and sets it up for 8N1 at `baud_rate`.
+///
+/// The rate follows the core clock, which must be fixed (`core_freq` or
+/// `enable_uart` in the firmware configuration) for a stable baud rate.
+pub fn init(baud_rate: u32) {
+    let regs = regs();
+    regs.enables.modify(|enables| enables | AUX_MINI_UART);
+    // Reconfiguring requires the transmitter and receiver disabled
+    regs.extra_control.write(0);
+    regs.interrupt_enable.write(0);
+    regs.line_control.write(LCR_8_BITS);
+    regs.modem_control.write(0);
+    regs.interrupt_identify.write(IIR_CLEAR_FIFOS);
+    // Baud rate is `clock / (8 * (divisor + 1))` (rounded)
+    let clock = mailbox::clock_rate(Clock::Core).unwrap_or(DEFAULT_CORE_CLOCK);
+    regs.baud_rate.write((clock + baud_rate * 4) / (baud_rate * 8) - 1);
+    for pin in [TX_PIN, RX_PIN] {
+        gpio::set_pull(pin, Pull::None);
+        gpio::set_function(pin, Function::Alt5);
+    }
+    if IRQ_DRIVEN {
+        gpu_intc::request(IRQ_AUX, handle_irq);
+        regs.interrupt_enable.write(IER_RX | IER_REQUIRED);
+    }
+    regs.extra_control.write(CNTL_RX | CNTL_TX);
+    READY.store(true, Ordering::Release);
+}
+
+/// Whether `init` was called.
+pub fn is_ready() -> bool {
+    READY.load(Ordering::Acquire)
+}
+
+/// Writes `bytes`, waiting for room in the 8-byte TX FIFO.
+pub fn write_bytes(bytes: &[u8]) {
+    let _irq = IrqGuard::new();
+    let _lock = TX_LOCK.lock();
+    write_bytes_polled(bytes);
+}
+
+/// Writes `bytes` without taking the TX lock, for panics.
+pub fn write_bytes_polled(bytes: &[u8]) {
+    let regs = regs();
+    for &byte in bytes {
+        while regs.line_status.read() & LSR_TX_EMPTY == 0 {
+            hint::spin_loop();
+        }
+        regs.io.write(byte as u32);
+    }
+}
+
+/// Returns a received byte, if any.
+pub fn try_read_byte() -> Option<u8> {
+    let _irq = IrqGuard::new();
+    let mut rx = RX_BUFFER.lock();
+    // Also picks up the bytes when not interrupt-driven
+    receive(regs(), &mut rx);
+    rx.pop()
+}
+
+/// Blocks the calling thread until a byte is received.
+pub fn read_byte() -> u8 {
+    loop {
+        if let Some(byte) = try_read_byte() {
+            return byte;
+        }
+        if IRQ_DRIVEN {
+            READERS.wait_until(has_input);
+        } else {
+            task::yield_now();
+        }
+    }
+}
+
+/// Waits until every written byte left the UART.
+pub fn flush() {
+    while regs().line_status.read() & LSR_TX_IDLE == 0 {
+        hint::spin_loop();
+    }
+}
+
+// Define helpers
+/// Moves the bytes of the RX FIFO into the buffer (dropping them when it is full),
+/// returning whether there were any.
+fn receive(regs: &Registers, rx: &mut ByteQueue) -> bool {
+    let mut received = false;
+    while regs.line_status.read() & LSR_DATA_READY != 0 {
+        rx.push(regs.io.read() as u8);
+        received = true;
+    }
+    received
+}
+
+fn has_input() -> bool {
+    let _irq = IrqGuard::new();
+    !RX_BUFFER.lock().is_empty()
+}
+
+/// Handles the AUX line, shared with the SPI masters
+fn handle_irq() {
+    let regs = regs();
+    if regs.irq.read() & AUX_MINI_UART == 0 {
+        return;
+    }
+    // Draining the RX FIFO acknowledges the interrupt
+    if receive(regs, &mut RX_BUFFER.lock()) {
+        READERS.notify_all();
+    }
+}
+
+fn regs() -> &'static Registers {
+    // SAFETY: GPU peripherals are always mapped
+    unsafe { registers(AUX_BASE) }
+}
@@ -0,0 +1,66 @@
+// Import dependencies
+use core::{cell::UnsafeCell, ptr};
+
+/// Memory-mapped register, read and written with volatile accesses
+#[repr(transparent)]
+pub struct ReadWrite<T: Copy> {
+    value: UnsafeCell<T>,
+}
+
+#[repr(transparent)]
+pub struct ReadOnly<T: Copy> {
+    value: UnsafeCell<T>,
+}
+
+#[repr(transparent)]
+pub struct WriteOnly<T: Copy> {
+    value: UnsafeCell<T>,
+}
+
+// Implement structs
+impl<T: Copy> ReadWrite<T> {
+    #[inline(always)]
+    pub fn read(&self) -> T {
+        unsafe { ptr::read_volatile(self.value.get()) }
+    }
+
+    #[inline(always)]
+    pub fn write(&self, value: T) {
+        unsafe { ptr::write_volatile(self.value.get(), value) }
+    }
+
+    #[inline(always)]
+    pub fn modify(&self, f: impl FnOnce(T) -> T) {
+        self.write(f(self.read()))
+    }
+}
+
+impl<T: Copy> ReadOnly<T> {
+    #[inline(always)]
+    pub fn read(&self) -> T {
+        unsafe { ptr::read_volatile(self.value.get()) }
+    }
+}
+
+impl<T: Copy> WriteOnly<T> {
+    #[inline(always)]
+    pub fn write(&self, value: T) {
+        unsafe { ptr::write_volatile(self.value.get(), value) }
+    }
+}
+
+// Implement thread safety
+// SAFETY: Registers are accessed with single volatile operations
+unsafe impl<T: Copy> Sync for ReadWrite<T> {}
+unsafe impl<T: Copy> Sync for ReadOnly<T> {}
+unsafe impl<T: Copy> Sync for WriteOnly<T> {}
+
+// Define helpers
+/// Returns the register block mapped at `address`.
+///
+/// # Safety
+///
+/// A register block of type `T` must be mapped at `address` for the kernel lifetime.
+pub const unsafe fn registers<T>(address: usize) -> &'static T {
+    &*(address as *const T)
+}
@@ -0,0 +1,31 @@
+// Define modules
+pub mod block;
+pub mod dma;
+pub mod emmc;
+pub mod framebuffer;
+#[cfg(feature = "gic")]
+pub mod gic;
+pub mod gpio;
+pub mod gpu_intc;
+pub mod local_intc;
+pub mod mailbox;
+pub mod mini_uart;
+pub mod mmio;
+pub mod pl011;
+pub mod power;
+pub mod rng;
+mod serial;
+pub mod system_timer;
+
+// Define constants
+/// ARM address of the SoC peripherals: BCM2837 (raspi3), or BCM2711 in its
+/// low-peripheral mode (Raspberry Pi 4, the board with a GIC)
+#[cfg(not(feature = "gic"))]
+pub const PERIPHERAL_BASE: usize = 0x3F00_0000;
+#[cfg(feature = "gic")]
+pub const PERIPHERAL_BASE: usize = 0xFE00_0000;
+/// ARM-local peripherals (per-core timers, mailboxes and IRQ routing, and the GIC-400 of the BCM2711)
+#[cfg(not(feature = "gic"))]
+pub const LOCAL_PERIPHERAL_BASE: usize = 0x4000_0000;
+#[cfg(feature = "gic")]
+pub const LOCAL_PERIPHERAL_BASE: usize = 0xFF80_0000;
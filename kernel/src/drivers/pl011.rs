@@ -0,0 +1,236 @@
+// Import dependencies
+use core::{hint, sync::atomic::{AtomicBool, Ordering}};
+use crate::arch::cpu::IrqGuard;
+use crate::sync::spin::Spinlock;
+use crate::task::{self, WaitQueue};
+use super::gpio::{self, Function, Pull};
+use super::gpu_intc::{self, IRQ_DRIVEN, IRQ_UART};
+use super::mailbox::{self, Clock};
+use super::mmio::{registers, ReadOnly, ReadWrite, WriteOnly};
+use super::serial::ByteQueue;
+use super::PERIPHERAL_BASE;
+
+// Define constants
+/// PL011 UART0 (on GPIO 14/15 through ALT0)
+const UART0_BASE: usize = PERIPHERAL_BASE + 0x20_1000;
+/// Reference clock of UART0 if the firmware cannot tell
+pub const DEFAULT_UART_CLOCK: u32 = 48_000_000;
+pub const DEFAULT_BAUD_RATE: u32 = 115_200;
+const TX_PIN: usize = 14;
+const RX_PIN: usize = 15;
+/// Flag register bits
+const FR_BUSY: u32 = 1 << 3;
+const FR_RXFE: u32 = 1 << 4;
+const FR_TXFF: u32 = 1 << 5;
+/// Line control: FIFOs enabled, 8-bit words (no parity, one stop bit)
+const LCRH_FEN: u32 = 1 << 4;
+const LCRH_WLEN_8: u32 = 0b11 << 5;
+/// Control: UART, transmitter and receiver enabled
+const CR_UARTEN: u32 = 1 << 0;
+const CR_TXE: u32 = 1 << 8;
+const CR_RXE: u32 = 1 << 9;
+/// FIFO levels: TX interrupt at 1/8 full, RX interrupt at 1/2 full
+const IFLS_TX_1_8: u32 = 0b000;
+const IFLS_RX_1_2: u32 = 0b010 << 3;
+/// Interrupt bits (mask, status and clear registers)
+const INT_RX: u32 = 1 << 4;
+const INT_TX: u32 = 1 << 5;
+const INT_RX_TIMEOUT: u32 = 1 << 6;
+const INT_ALL: u32 = 0x7FF;
+
+// Define structs
+#[repr(C)]
+struct Registers {
+    data: ReadWrite<u32>,
+    receive_status: ReadWrite<u32>,
+    _reserved0: [u32; 4],
+    flags: ReadOnly<u32>,
+    _reserved1: u32,
+    irda_low_power: ReadWrite<u32>,
+    integer_baud_rate: ReadWrite<u32>,
+    fractional_baud_rate: ReadWrite<u32>,
+    line_control: ReadWrite<u32>,
+    control: ReadWrite<u32>,
+    fifo_level: ReadWrite<u32>,
+    interrupt_mask: ReadWrite<u32>,
+    raw_interrupt_status: ReadOnly<u32>,
+    masked_interrupt_status: ReadOnly<u32>,
+    interrupt_clear: WriteOnly<u32>,
+}
+
+/// Software buffers in front of the hardware FIFOs
+struct Buffers {
+    tx: ByteQueue,
+    rx: ByteQueue,
+}
+
+// Define globals
+static BUFFERS: Spinlock<Buffers> = Spinlock::new(Buffers {
+    tx: ByteQueue::new(),
+    rx: ByteQueue::new(),
+});
+/// Threads waiting in `read_byte`
+static READERS: WaitQueue = WaitQueue::new();
+static READY: AtomicBool = AtomicBool::new(false);
+
+// Define procedures
+/// Routes UART0 to GPIO 14/15 and sets it up for 8N1 at `baud_rate`.
+pub fn init(baud_rate: u32) {
+    let regs = regs();
+    // Reconfiguring requires the UART disabled and idle
+    regs.control.write(0);
+    while regs.flags.read() & FR_BUSY != 0 {
+        hint::spin_loop();
+    }
+    // Disabling the FIFOs flushes them
+    regs.line_control.write(0);
+    for pin in [TX_PIN, RX_PIN] {
+        gpio::set_pull(pin, Pull::None);
+        gpio::set_function(pin, Function::Alt0);
+    }
+    // Divisor of the 16x oversampled clock, with 6 fractional bits (rounded)
+    let clock = mailbox::clock_rate(Clock::Uart).unwrap_or(DEFAULT_UART_CLOCK);
+    let divisor = (clock * 4 + baud_rate / 2) / baud_rate;
+    regs.integer_baud_rate.write(divisor >> 6);
+    regs.fractional_baud_rate.write(divisor & 0x3F);
+    regs.line_control.write(LCRH_FEN | LCRH_WLEN_8);
+    regs.fifo_level.write(IFLS_TX_1_8 | IFLS_RX_1_2);
+    regs.interrupt_clear.write(INT_ALL);
+    if IRQ_DRIVEN {
+        regs.interrupt_mask.write(INT_RX | INT_RX_TIMEOUT);
+        gpu_intc::request(IRQ_UART, handle_irq);
+    }
+    regs.control.write(CR_UARTEN | CR_TXE | CR_RXE);
+    READY.store(true, Ordering::Release);
+}
+
+/// Whether `init` was called.
+pub fn is_ready() -> bool {
+    READY.load(Ordering::Acquire)
+}
+
+/// Queues `bytes` for transmission, waiting only while the buffer is full.
+pub fn write_bytes(bytes: &[u8]) {
+    let regs = regs();
+    let _irq = IrqGuard::new();
+    let mut buffers = BUFFERS.lock();
+    for &byte in bytes {
+        loop {
+            // Bytes must leave in order: bypass the buffer only when it is empty
+            if buffers.tx.is_empty() && regs.flags.read() & FR_TXFF == 0 {
+                regs.data.write(byte as u32);
+                break;
+            }
+            if IRQ_DRIVEN && buffers.tx.push(byte) {
+                regs.interrupt_mask.modify(|mask| mask | INT_TX);
+                break;
+            }
+            // Full: feed the FIFO meanwhile, the IRQ may be held up on another core
+            transmit(regs, &mut buffers.tx);
+            hint::spin_loop();
+        }
+    }
+}
+
+/// Writes `bytes` by polling the FIFO, bypassing the buffer and its lock.
+///
+/// For panics, where the lock may be held by a stopped core.
+pub fn write_bytes_polled(bytes: &[u8]) {
+    let regs = regs();
+    for &byte in bytes {
+        while regs.flags.read() & FR_TXFF != 0 {
+            hint::spin_loop();
+        }
+        regs.data.write(byte as u32);
+    }
+}
+
+/// Returns a received byte, if any.
+pub fn try_read_byte() -> Option<u8> {
+    let _irq = IrqGuard::new();
+    let mut buffers = BUFFERS.lock();
+    // Also picks up bytes left under the FIFO level when not interrupt-driven
+    receive(regs(), &mut buffers.rx);
+    buffers.rx.pop()
+}
+
+/// Blocks the calling thread until a byte is received.
+pub fn read_byte() -> u8 {
+    loop {
+        if let Some(byte) = try_read_byte() {
+            return byte;
+        }
+        if IRQ_DRIVEN {
+            READERS.wait_until(has_input);
+        } else {
+            task::yield_now();
+        }
+    }
+}
+
+/// Waits until every queued byte left the UART.
+pub fn flush() {
+    let regs = regs();
+    loop {
+        {
+            let _irq = IrqGuard::new();
+            let mut buffers = BUFFERS.lock();
+            transmit(regs, &mut buffers.tx);
+            if buffers.tx.is_empty() && regs.flags.read() & FR_BUSY == 0 {
+                return;
+            }
+        }
+        hint::spin_loop();
+    }
+}
+
+// Define helpers
+/// Moves buffered bytes into the TX FIFO, masking the TX interrupt once the buffer is empty.
+fn transmit(regs: &Registers, tx: &mut ByteQueue) {
+    while regs.flags.read() & FR_TXFF == 0 {
+        match tx.pop() {
+            Some(byte) => regs.data.write(byte as u32),
+            None => break,
+        }
+    }
+    if tx.is_empty() {
+        regs.interrupt_mask.modify(|mask| mask & !INT_TX);
+    }
+}
+
+/// Moves the bytes of the RX FIFO into the buffer (dropping them when it is full),
+/// returning whether there were any.
+fn receive(regs: &Registers, rx: &mut ByteQueue) -> bool {
+    let mut received = false;
+    while regs.flags.read() & FR_RXFE == 0 {
+        // The upper bits hold the framing, parity, break and overrun errors
+        rx.push(regs.data.read() as u8);
+        received = true;
+    }
+    received
+}
+
+fn has_input() -> bool {
+    let _irq = IrqGuard::new();
+    !BUFFERS.lock().rx.is_empty()
+}
+
+fn handle_irq() {
+    let regs = regs();
+    let status = regs.masked_interrupt_status.read();
+    let received = {
+        let mut buffers = BUFFERS.lock();
+        transmit(regs, &mut buffers.tx);
+        receive(regs, &mut buffers.rx)
+    };
+    // The TX FIFO is now full or its interrupt masked, so no edge is lost
+    regs.interrupt_clear.write(status);
+    if received {
+        READERS.notify_all();
+    }
+}
+
+fn regs() -> &'static Registers {
+    // SAFETY: GPU peripherals are always mapped
+    unsafe { registers(UART0_BASE) }
+}
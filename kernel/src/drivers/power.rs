@@ -0,0 +1,168 @@
+// Import dependencies
+use core::{fmt, sync::atomic::{AtomicBool, AtomicU32, Ordering}, time::Duration};
+use crate::arch::cpu;
+use crate::smp;
+use crate::task;
+use crate::time;
+use super::mmio::{registers, ReadWrite};
+use super::PERIPHERAL_BASE;
+
+// Define constants
+/// BCM2835 power management block (reset control and watchdog)
+const PM_BASE: usize = PERIPHERAL_BASE + 0x10_0000;
+/// Writes are ignored without the password in the top byte
+const PASSWORD: u32 = 0x5A00_0000;
+/// Reset control: action taken when the watchdog expires
+const RSTC_WRCFG_MASK: u32 = 0x30;
+const RSTC_WRCFG_FULL_RESET: u32 = 0x20;
+const RSTC_RESET: u32 = 0x102;
+/// Reset status: kind of the last reset
+const RSTS_HADWRF: u32 = 1 << 5;
+const RSTS_HADWRH: u32 = 1 << 6;
+const RSTS_HADPOR: u32 = 1 << 12;
+/// Reset status: boot partition (its bits are spread over 0, 2, .., 10), 63 makes the firmware halt
+const RSTS_PARTITION_MASK: u32 = 0x555;
+const RSTS_PARTITION_HALT: u32 = 0x555;
+/// The watchdog counts down in 1/65536 s, on 20 bits
+const WDOG_TICKS_PER_SEC: u64 = 1 << 16;
+const WDOG_TICKS_MASK: u32 = 0xF_FFFF;
+/// Watchdog ticks before `reboot` and `halt` take effect
+const RESET_TICKS: u32 = 10;
+/// Longest watchdog timeout (16 s)
+pub const MAX_WATCHDOG_TIMEOUT: Duration = Duration::from_micros(WDOG_TICKS_MASK as u64 * 1_000_000 / WDOG_TICKS_PER_SEC);
+
+// Define structs
+/// Cause of the last reset, as recorded by the PM block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResetReason {
+    PowerOn,
+    /// The watchdog expired (also how `reboot` resets the board)
+    Watchdog,
+    Unknown,
+}
+
+#[repr(C)]
+struct Registers {
+    _reserved0: [u32; 7],
+    reset_control: ReadWrite<u32>,
+    reset_status: ReadWrite<u32>,
+    watchdog: ReadWrite<u32>,
+}
+
+// Define globals
+/// Reset status read by `init`, before anything changes it
+static RESET_STATUS: AtomicU32 = AtomicU32::new(0);
+/// Timeout the watchdog is re-armed with, in watchdog ticks (0 when stopped)
+static WATCHDOG_TICKS: AtomicU32 = AtomicU32::new(0);
+static WATCHDOG_THREAD: AtomicBool = AtomicBool::new(false);
+
+// Define procedures
+/// Records the reason of the last reset.
+pub fn init() {
+    let regs = regs();
+    let status = regs.reset_status.read();
+    RESET_STATUS.store(status, Ordering::Relaxed);
+    // Clears the HAD* flags (which only a power-on resets), so the next boot sees its own reset
+    regs.reset_status.write(PASSWORD | (status & RSTS_PARTITION_MASK));
+}
+
+pub fn reset_reason() -> ResetReason {
+    let status = RESET_STATUS.load(Ordering::Relaxed);
+    // A watchdog reset may still come with HADPOR (from the power-on before it)
+    if status & (RSTS_HADWRF | RSTS_HADWRH) != 0 {
+        ResetReason::Watchdog
+    } else if status & RSTS_HADPOR != 0 {
+        ResetReason::PowerOn
+    } else {
+        ResetReason::Unknown
+    }
+}
+
+/// Resets the board, booting the kernel again.
+pub fn reboot() -> ! {
+    reset(0)
+}
+
+/// Resets the board into the firmware halt state (power off, until the power is cycled).
+pub fn halt() -> ! {
+    reset(RSTS_PARTITION_HALT)
+}
+
+/// Arms the watchdog to reset the board unless it is petted within `timeout`
+/// (capped to `MAX_WATCHDOG_TIMEOUT`).
+pub fn start_watchdog(timeout: Duration) {
+    let ticks = (timeout.as_micros() * WDOG_TICKS_PER_SEC as u128 / 1_000_000).clamp(1, WDOG_TICKS_MASK as u128) as u32;
+    WATCHDOG_TICKS.store(ticks, Ordering::Relaxed);
+    arm_watchdog(ticks);
+}
+
+/// Re-arms the watchdog with its timeout (nothing if it is stopped).
+pub fn pet_watchdog() {
+    match WATCHDOG_TICKS.load(Ordering::Relaxed) {
+        0 => {}
+        ticks => regs().watchdog.write(PASSWORD | ticks),
+    }
+}
+
+pub fn stop_watchdog() {
+    WATCHDOG_TICKS.store(0, Ordering::Relaxed);
+    regs().reset_control.write(PASSWORD | RSTC_RESET);
+}
+
+/// Time before the watchdog resets the board
+pub fn watchdog_time_left() -> Duration {
+    let ticks = (regs().watchdog.read() & WDOG_TICKS_MASK) as u64;
+    Duration::from_micros(ticks * 1_000_000 / WDOG_TICKS_PER_SEC)
+}
+
+/// Arms the watchdog and starts a thread petting it every half `timeout`, so
+/// the board reboots when threads stop being scheduled (or the kernel panics).
+pub fn start_watchdog_thread(timeout: Duration) {
+    start_watchdog(timeout);
+    if WATCHDOG_THREAD.swap(true, Ordering::AcqRel) {
+        return;
+    }
+    let period = timeout.min(MAX_WATCHDOG_TIMEOUT) / 2;
+    // Runs forever, nobody joins it
+    drop(task::spawn(move || loop {
+        pet_watchdog();
+        time::sleep(period);
+    }));
+}
+
+// Implement structs
+impl fmt::Display for ResetReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResetReason::PowerOn => write!(f, "power-on reset"),
+            ResetReason::Watchdog => write!(f, "watchdog reset"),
+            ResetReason::Unknown => write!(f, "unknown reset"),
+        }
+    }
+}
+
+// Define helpers
+/// Resets the board through the watchdog, telling the firmware to boot `partition`.
+fn reset(partition: u32) -> ! {
+    // SAFETY: The kernel does not continue after a reset
+    unsafe { cpu::mask_irq() };
+    smp::stop_other_cores();
+    let regs = regs();
+    let status = regs.reset_status.read();
+    regs.reset_status.write(PASSWORD | (status & !RSTS_PARTITION_MASK) | partition);
+    arm_watchdog(RESET_TICKS);
+    // SAFETY: Nothing runs on the core anymore
+    unsafe { cpu::park_core() }
+}
+
+fn arm_watchdog(ticks: u32) {
+    let regs = regs();
+    regs.watchdog.write(PASSWORD | (ticks & WDOG_TICKS_MASK));
+    let control = regs.reset_control.read();
+    regs.reset_control.write(PASSWORD | (control & !RSTC_WRCFG_MASK) | RSTC_WRCFG_FULL_RESET);
+}
+
+fn regs() -> &'static Registers {
+    // SAFETY: GPU peripherals are always mapped
+    unsafe { registers(PM_BASE) }
+}
@@ -0,0 +1,82 @@
+// Import dependencies
+use core::{hint, sync::atomic::{AtomicBool, Ordering}};
+use super::mmio::{registers, ReadOnly, ReadWrite};
+use super::PERIPHERAL_BASE;
+
+// Define constants
+/// BCM2835 hardware random number generator
+const RNG_BASE: usize = PERIPHERAL_BASE + 0x10_4000;
+/// Control: generator enabled
+const CTRL_ENABLE: u32 = 1 << 0;
+/// Numbers discarded after enabling, while the generator warms up
+const WARMUP_COUNT: u32 = 0x4_0000;
+/// Interrupt mask: no interrupt when the FIFO fills (it is polled)
+const INT_MASK_OFF: u32 = 1 << 0;
+/// Polls of the FIFO level before giving up on a word
+const READ_SPINS: usize = 1 << 16;
+
+// Define structs
+#[repr(C)]
+struct Registers {
+    control: ReadWrite<u32>,
+    /// Warm-up count in the low bits, words available in the top byte
+    status: ReadWrite<u32>,
+    data: ReadOnly<u32>,
+    fifo_threshold: ReadWrite<u32>,
+    interrupt_mask: ReadWrite<u32>,
+}
+
+// Define globals
+static READY: AtomicBool = AtomicBool::new(false);
+
+// Define procedures
+/// Starts the generator (its first words take a while to come out).
+pub fn init() {
+    let regs = regs();
+    regs.interrupt_mask.modify(|mask| mask | INT_MASK_OFF);
+    regs.status.write(WARMUP_COUNT);
+    regs.control.modify(|control| control | CTRL_ENABLE);
+    READY.store(true, Ordering::Release);
+}
+
+/// Whether `init` was called.
+pub fn is_ready() -> bool {
+    READY.load(Ordering::Acquire)
+}
+
+/// Returns a random word if one is available.
+pub fn try_read_u32() -> Option<u32> {
+    let regs = regs();
+    if !is_ready() || regs.status.read() >> 24 == 0 {
+        return None;
+    }
+    Some(regs.data.read())
+}
+
+/// Returns a random word, waiting (a bounded time) for the generator.
+pub fn read_u32() -> Option<u32> {
+    for _ in 0..READ_SPINS {
+        if let Some(word) = try_read_u32() {
+            return Some(word);
+        }
+        hint::spin_loop();
+    }
+    None
+}
+
+/// Fills `words` with hardware random words, returning how many it could read.
+pub fn fill(words: &mut [u32]) -> usize {
+    for (filled, word) in words.iter_mut().enumerate() {
+        match read_u32() {
+            Some(random) => *word = random,
+            None => return filled,
+        }
+    }
+    words.len()
+}
+
+// Define helpers
+fn regs() -> &'static Registers {
+    // SAFETY: GPU peripherals are always mapped
+    unsafe { registers(RNG_BASE) }
+}
@@ -0,0 +1,46 @@
+// Define constants
+/// Capacity of the software buffers of the UART drivers
+pub const BUFFER_SIZE: usize = 1024;
+
+// Define structs
+/// FIFO of bytes in front of a UART hardware FIFO
+pub struct ByteQueue {
+    bytes: [u8; BUFFER_SIZE],
+    head: usize,
+    len: usize,
+}
+
+// Implement structs
+impl ByteQueue {
+    pub const fn new() -> Self {
+        Self {
+            bytes: [0; BUFFER_SIZE],
+            head: 0,
+            len: 0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Appends `byte`, returning `false` if the queue is full.
+    pub fn push(&mut self, byte: u8) -> bool {
+        if self.len == BUFFER_SIZE {
+            return false;
+        }
+        self.bytes[(self.head + self.len) % BUFFER_SIZE] = byte;
+        self.len += 1;
+        true
+    }
+
+    pub fn pop(&mut self) -> Option<u8> {
+        if self.len == 0 {
+            return None;
+        }
+        let byte = self.bytes[self.head];
+        self.head = (self.head + 1) % BUFFER_SIZE;
+        self.len -= 1;
+        Some(byte)
+    }
+}
@@ -0,0 +1,177 @@
+// Import dependencies
+use core::{mem, sync::atomic::{AtomicUsize, Ordering}, time::Duration};
+use crate::arch::cpu::IrqGuard;
+use crate::sync::spin::Spinlock;
+use crate::time::Timer;
+use super::gpu_intc::{self, IRQ_SYSTEM_TIMER_1, IRQ_SYSTEM_TIMER_3};
+use super::mmio::{registers, ReadOnly, ReadWrite};
+use super::PERIPHERAL_BASE;
+
+// Define constants
+/// BCM2835 system timer
+const SYSTEM_TIMER_BASE: usize = PERIPHERAL_BASE + 0x3000;
+/// The counter runs at 1 MHz whatever the CPU and core clocks
+pub const FREQUENCY_HZ: u64 = 1_000_000;
+pub const CHANNEL_COUNT: usize = 4;
+/// Events are delivered from the interrupts, unless the GPU interrupts are
+/// not wired up (matches are then polled every `POLL_INTERVAL`, so events are late by up to that)
+pub const IRQ_DRIVEN: bool = gpu_intc::IRQ_DRIVEN;
+const POLL_INTERVAL: Duration = Duration::from_millis(1);
+/// Shortest delay that cannot be missed while the compare register is written
+const MIN_DELAY_TICKS: u32 = 2;
+
+// Define structs
+/// Compare channel of the timer
+///
+/// Channels 0 and 2 are used by the GPU firmware.
+#[repr(usize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    One = 1,
+    Three = 3,
+}
+
+/// Called from IRQ context when the compare value of its channel is reached
+pub type EventHandler = fn();
+
+#[repr(C)]
+struct Registers {
+    /// Match bit per channel (write 1 to clear)
+    control_status: ReadWrite<u32>,
+    counter_low: ReadOnly<u32>,
+    counter_high: ReadOnly<u32>,
+    compare: [ReadWrite<u32>; CHANNEL_COUNT],
+}
+
+// Define globals
+/// Serializes arming with the match handling, which runs on core 0 (taken with IRQs masked)
+static LOCK: Spinlock<()> = Spinlock::new(());
+/// Armed handlers stored as function addresses (0 when none)
+static HANDLERS: [AtomicUsize; CHANNEL_COUNT] = [ATOMIC_NONE; CHANNEL_COUNT];
+/// Runs `poll_events` when not `IRQ_DRIVEN`
+static POLLER: Timer = Timer::new(poll, 0);
+#[allow(clippy::declare_interior_mutable_const)]
+const ATOMIC_NONE: AtomicUsize = AtomicUsize::new(0);
+
+// Define procedures
+/// Routes the compare channels to their handlers.
+pub fn init() {
+    for channel in [Channel::One, Channel::Three] {
+        disarm(channel);
+    }
+    gpu_intc::request(IRQ_SYSTEM_TIMER_1, handle_irq_1);
+    gpu_intc::request(IRQ_SYSTEM_TIMER_3, handle_irq_3);
+    if !IRQ_DRIVEN {
+        POLLER.start_periodic(POLL_INTERVAL);
+    }
+}
+
+/// Value of the free-running 64-bit counter, in microseconds
+pub fn counter() -> u64 {
+    let regs = regs();
+    // Re-reads if the low word wrapped between the two reads
+    loop {
+        let high = regs.counter_high.read();
+        let low = regs.counter_low.read();
+        if regs.counter_high.read() == high {
+            return (high as u64) << 32 | low as u64;
+        }
+    }
+}
+
+/// Time elapsed since the timer started (at power on)
+pub fn uptime() -> Duration {
+    ticks_to_duration(counter())
+}
+
+pub fn ticks_to_duration(ticks: u64) -> Duration {
+    // Ticks are microseconds (`FREQUENCY_HZ`)
+    Duration::from_micros(ticks)
+}
+
+pub fn duration_to_ticks(duration: Duration) -> u64 {
+    (duration.as_nanos() * FREQUENCY_HZ as u128 / 1_000_000_000) as u64
+}
+
+/// Calls `handler` once, after `delay`, replacing what `channel` was armed with.
+///
+/// Compare values are 32-bit, so the delay is capped to about 35 minutes.
+pub fn arm(channel: Channel, delay: Duration, handler: EventHandler) {
+    let delay = duration_to_ticks(delay).clamp(MIN_DELAY_TICKS as u64, i32::MAX as u64) as u32;
+    let regs = regs();
+    let bit = 1 << channel as usize;
+    let _irq = IrqGuard::new();
+    let _lock = LOCK.lock();
+    // Drops a match of the previous compare value
+    regs.control_status.write(bit);
+    loop {
+        let compare = regs.counter_low.read().wrapping_add(delay);
+        regs.compare[channel as usize].write(compare);
+        // The counter must not have passed the compare value unnoticed (when the core stalled)
+        if regs.control_status.read() & bit != 0 || (compare.wrapping_sub(regs.counter_low.read()) as i32) > 0 {
+            break;
+        }
+    }
+    HANDLERS[channel as usize].store(handler as usize, Ordering::Release);
+}
+
+/// Cancels the pending event of `channel`, returning whether there was one.
+pub fn disarm(channel: Channel) -> bool {
+    let _irq = IrqGuard::new();
+    let _lock = LOCK.lock();
+    regs().control_status.write(1 << channel as usize);
+    HANDLERS[channel as usize].swap(0, Ordering::AcqRel) != 0
+}
+
+/// Runs the handlers of the matched channels, returning whether there were any.
+///
+/// Called by the interrupt handlers, or periodically when not `IRQ_DRIVEN`.
+pub fn poll_events() -> bool {
+    let mut handled = false;
+    for channel in [Channel::One, Channel::Three] {
+        handled |= handle_channel(channel);
+    }
+    handled
+}
+
+// Define helpers
+fn handle_channel(channel: Channel) -> bool {
+    let regs = regs();
+    let bit = 1 << channel as usize;
+    let handler = {
+        let _irq = IrqGuard::new();
+        let _lock = LOCK.lock();
+        if regs.control_status.read() & bit == 0 {
+            return false;
+        }
+        // Acknowledges before running the handler, which may re-arm the channel
+        regs.control_status.write(bit);
+        HANDLERS[channel as usize].swap(0, Ordering::AcqRel)
+    };
+    match handler {
+        0 => {}
+        handler => {
+            // SAFETY: Only function addresses are stored
+            let handler = unsafe { mem::transmute::<usize, EventHandler>(handler) };
+            handler();
+        }
+    }
+    true
+}
+
+fn handle_irq_1() {
+    handle_channel(Channel::One);
+}
+
+fn handle_irq_3() {
+    handle_channel(Channel::Three);
+}
+
+fn poll(_: usize) {
+    poll_events();
+}
+
+fn regs() -> &'static Registers {
+    // SAFETY: GPU peripherals are always mapped
+    unsafe { registers(SYSTEM_TIMER_BASE) }
+}
@@ -9,17 +9,65 @@
 #![feature(fn_align)]
 #![feature(const_mut_refs)]
 #![feature(slice_from_ptr_range)]
-// Load panic package
-extern crate armv8a_panic_semihosting;
+#![feature(const_maybe_uninit_zeroed)]
 // Define modules
 mod arch;
+mod console;
+mod drivers;
+mod mm;
+mod panic;
+mod random;
+mod smp;
 mod sync;
+mod task;
+mod time;
 // mod boot;
 // mod cpu;
 // mod exception;
 
 // Define kernel init
 unsafe fn main() -> ! {
-    loop {}
+    // Thread stacks come from the page allocator
+    mm::init();
+    // Read the reset reason before anything resets again
+    drivers::power::init();
+    #[cfg(not(feature = "gic"))]
+    {
+        drivers::local_intc::init();
+        drivers::gpu_intc::init();
+    }
+    #[cfg(feature = "gic")]
+    {
+        drivers::gic::init();
+        drivers::gic::init_core();
+    }
+    console::init();
+    // Claim the DMA channels left to the ARM
+    drivers::dma::init();
+    // Cores signal each other through IPIs
+    smp::init_core();
+    // The boot flow becomes the idle thread of the core
+    task::init_core();
+    // Preempt threads on every tick
+    time::init_core();
+    // Second time base, independent of the generic timer
+    drivers::system_timer::init();
+    // Seed the kernel random generator from the hardware one
+    random::init();
+    // Bring up the other cores
+    arch::cpu::release_secondary_cores();
+    arch::cpu::unmask_irq();
+    task::idle()
+}
+
+// Define secondary cores init
+unsafe fn secondary_main() -> ! {
+    #[cfg(feature = "gic")]
+    drivers::gic::init_core();
+    smp::init_core();
+    task::init_core();
+    time::init_core();
+    arch::cpu::unmask_irq();
+    task::idle()
 }
 
@@ -0,0 +1,28 @@
+// Import dependencies
+use core::ptr;
+use crate::drivers::mailbox;
+// Define modules
+pub mod page;
+// Export definitions
+pub use page::{alloc_pages, free_pages, order_for, PAGE_SIZE};
+// Link with global labels
+extern "C" {
+    #[link_name = "__kernel_end__"]
+    static kernel_end: u8;
+}
+
+// Define constants
+/// End of the RAM usable by the ARM cores if the firmware cannot tell
+/// (the default split of a 1 GiB board, the GPU owning the memory above)
+pub const DEFAULT_MEMORY_END: usize = 0x3C00_0000;
+
+// Define procedures
+/// Hands the memory after the kernel image to the page allocator.
+///
+/// # Safety
+///
+/// Must be called once, on the boot core, before any allocation.
+pub unsafe fn init() {
+    let memory_end = mailbox::arm_memory().map_or(DEFAULT_MEMORY_END, |(base, size)| base + size);
+    page::init(ptr::addr_of!(kernel_end).addr(), memory_end);
+}
@@ -0,0 +1,197 @@
+// Import dependencies
+use core::{ptr::{self, NonNull}, slice};
+use crate::arch::cpu::IrqGuard;
+use crate::sync::spin::Spinlock;
+
+// Define constants
+pub const PAGE_SIZE: usize = 4096;
+/// Largest block order (blocks of `2^MAX_ORDER` pages)
+pub const MAX_ORDER: usize = 10;
+/// Page map flag: head page of a free block (the low bits hold its order)
+const FREE: u8 = 0x80;
+/// Page map value of pages that are allocated or inside a block
+const USED: u8 = 0;
+
+// Define structs
+/// Link stored at the start of each free block
+struct FreeBlock {
+    prev: *mut FreeBlock,
+    next: *mut FreeBlock,
+}
+
+/// Binary buddy allocator over a contiguous range of pages.
+///
+/// Each page has one byte in the page map, placed at the start of the range.
+struct Zone {
+    /// Address of the first managed page
+    base: usize,
+    pages: usize,
+    map: &'static mut [u8],
+    free_lists: [*mut FreeBlock; MAX_ORDER + 1],
+    free_pages: usize,
+}
+
+// Define globals
+static ZONE: Spinlock<Zone> = Spinlock::new(Zone::new());
+
+// Define procedures
+/// Manages the pages in `start..end`.
+///
+/// # Safety
+///
+/// The range must be unused RAM, and this must be called once before any allocation.
+pub(super) unsafe fn init(start: usize, end: usize) {
+    let start = align_up(start, PAGE_SIZE);
+    let end = end & !(PAGE_SIZE - 1);
+    assert!(start < end, "No memory left for the page allocator");
+    // The page map takes the first pages of the range
+    let total = (end - start) / PAGE_SIZE;
+    let map_pages = align_up(total, PAGE_SIZE) / PAGE_SIZE;
+    let pages = total - map_pages;
+    let map = slice::from_raw_parts_mut(start as *mut u8, pages);
+    map.fill(USED);
+    let _irq = IrqGuard::new();
+    let mut zone = ZONE.lock();
+    zone.base = start + map_pages * PAGE_SIZE;
+    zone.pages = pages;
+    zone.map = map;
+    // Carve the range into the largest aligned blocks
+    let mut page = 0;
+    while page < pages {
+        let order = (0..=MAX_ORDER).rev().find(|&order| page % (1 << order) == 0 && page + (1 << order) <= pages).unwrap_or(0);
+        zone.push(page, order);
+        zone.free_pages += 1 << order;
+        page += 1 << order;
+    }
+}
+
+/// Allocates `2^order` contiguous pages, aligned to their size (relative to the managed range).
+pub fn alloc_pages(order: usize) -> Option<NonNull<u8>> {
+    assert!(order <= MAX_ORDER, "Invalid page order {}", order);
+    let _irq = IrqGuard::new();
+    let mut zone = ZONE.lock();
+    let (page, found) = (order..=MAX_ORDER).find_map(|found| zone.pop(found).map(|page| (page, found)))?;
+    // Split the block, freeing the upper halves
+    for split in (order..found).rev() {
+        zone.push(page + (1 << split), split);
+    }
+    zone.free_pages -= 1 << order;
+    NonNull::new((zone.base + page * PAGE_SIZE) as *mut u8)
+}
+
+/// Returns pages obtained from `alloc_pages` with the same `order`.
+///
+/// # Safety
+///
+/// The pages must not be used anymore.
+pub unsafe fn free_pages(pages: NonNull<u8>, order: usize) {
+    let _irq = IrqGuard::new();
+    let mut zone = ZONE.lock();
+    let mut page = zone.page_index(pages.as_ptr());
+    assert!(page % (1 << order) == 0, "Misaligned page block");
+    zone.free_pages += 1 << order;
+    // Merge with the free buddies
+    let mut order = order;
+    while order < MAX_ORDER {
+        let buddy = page ^ (1 << order);
+        if buddy + (1 << order) > zone.pages || zone.map[buddy] != FREE | order as u8 {
+            break;
+        }
+        zone.remove(buddy);
+        page = page.min(buddy);
+        order += 1;
+    }
+    zone.push(page, order);
+}
+
+/// Smallest order of a block holding `size` bytes
+pub const fn order_for(size: usize) -> usize {
+    let pages = (size + PAGE_SIZE - 1) / PAGE_SIZE;
+    if pages <= 1 { 0 } else { (usize::BITS - (pages - 1).leading_zeros()) as usize }
+}
+
+/// Number of free pages
+pub fn free_page_count() -> usize {
+    let _irq = IrqGuard::new();
+    ZONE.lock().free_pages
+}
+
+/// Number of pages managed by the allocator
+pub fn total_page_count() -> usize {
+    let _irq = IrqGuard::new();
+    ZONE.lock().pages
+}
+
+// Implement structs
+impl Zone {
+    const fn new() -> Self {
+        Self {
+            base: 0,
+            pages: 0,
+            map: &mut [],
+            free_lists: [ptr::null_mut(); MAX_ORDER + 1],
+            free_pages: 0,
+        }
+    }
+
+    fn block(&self, page: usize) -> *mut FreeBlock {
+        (self.base + page * PAGE_SIZE) as *mut FreeBlock
+    }
+
+    fn page_index(&self, address: *mut u8) -> usize {
+        let offset = address.addr().wrapping_sub(self.base);
+        assert!(offset < self.pages * PAGE_SIZE, "Address {:p} not managed by the page allocator", address);
+        offset / PAGE_SIZE
+    }
+
+    fn push(&mut self, page: usize, order: usize) {
+        let block = self.block(page);
+        let head = self.free_lists[order];
+        // SAFETY: Free blocks are owned by the allocator
+        unsafe {
+            block.write(FreeBlock { prev: ptr::null_mut(), next: head });
+            if let Some(head) = head.as_mut() {
+                head.prev = block;
+            }
+        }
+        self.free_lists[order] = block;
+        self.map[page] = FREE | order as u8;
+    }
+
+    fn pop(&mut self, order: usize) -> Option<usize> {
+        let block = self.free_lists[order];
+        if block.is_null() {
+            return None;
+        }
+        let page = self.page_index(block.cast());
+        self.remove(page);
+        Some(page)
+    }
+
+    /// Unlinks the free block starting at `page`.
+    fn remove(&mut self, page: usize) {
+        let order = (self.map[page] & !FREE) as usize;
+        let block = self.block(page);
+        // SAFETY: Free blocks are owned by the allocator
+        unsafe {
+            let FreeBlock { prev, next } = block.read();
+            if let Some(next) = next.as_mut() {
+                next.prev = prev;
+            }
+            match prev.as_mut() {
+                Some(prev) => prev.next = next,
+                None => self.free_lists[order] = next,
+            }
+        }
+        self.map[page] = USED;
+    }
+}
+
+// Implement thread safety
+// SAFETY: Free blocks are only accessed with the zone locked
+unsafe impl Send for Zone {}
+
+// Define helpers
+const fn align_up(value: usize, alignment: usize) -> usize {
+    (value + alignment - 1) & !(alignment - 1)
+}
@@ -0,0 +1,32 @@
+// Import dependencies
+use core::{panic::PanicInfo, sync::atomic::{AtomicBool, Ordering}};
+use armv8a_semihosting::debug::{self, EXIT_FAILURE};
+use crate::arch::cpu;
+use crate::console;
+use crate::smp;
+
+// Define globals
+/// Set by the first core to panic
+static PANICKING: AtomicBool = AtomicBool::new(false);
+
+// Define panic handler
+/// Halts the other cores, reports the panic and exits QEMU.
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    // SAFETY: The kernel does not continue after a panic
+    unsafe { cpu::mask_irq() };
+    // A concurrent panic is reported by the first core, which stops this one
+    if PANICKING.swap(true, Ordering::AcqRel) {
+        // SAFETY: Nothing runs on the core anymore
+        unsafe { cpu::park_core() }
+    }
+    let stopped = smp::stop_other_cores();
+    console::panic_print_fmt(format_args!("{}\n", info));
+    if !stopped {
+        console::panic_print_fmt(format_args!("Warning: some cores did not stop\n"));
+    }
+    // Exit the QEMU process
+    debug::exit(EXIT_FAILURE);
+    // SAFETY: Nothing runs on the core anymore
+    unsafe { cpu::park_core() }
+}
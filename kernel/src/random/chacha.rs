@@ -0,0 +1,46 @@
+// Define constants
+pub const KEY_WORDS: usize = 8;
+pub const BLOCK_WORDS: usize = 16;
+/// Bytes of keystream per block
+pub const BLOCK_SIZE: usize = BLOCK_WORDS * 4;
+const DOUBLE_ROUNDS: usize = 10;
+/// "expand 32-byte k"
+const SIGMA: [u32; 4] = [0x6170_7865, 0x3320_646E, 0x7962_2D32, 0x6B20_6574];
+
+// Define procedures
+/// ChaCha20 block `counter` of the keystream of `key` and `nonce` (64-bit counter and nonce).
+pub fn block(key: &[u32; KEY_WORDS], counter: u64, nonce: u64) -> [u32; BLOCK_WORDS] {
+    let mut input = [0; BLOCK_WORDS];
+    input[..4].copy_from_slice(&SIGMA);
+    input[4..12].copy_from_slice(key);
+    input[12..].copy_from_slice(&[counter as u32, (counter >> 32) as u32, nonce as u32, (nonce >> 32) as u32]);
+    let mut state = input;
+    for _ in 0..DOUBLE_ROUNDS {
+        // Columns
+        quarter_round(&mut state, 0, 4, 8, 12);
+        quarter_round(&mut state, 1, 5, 9, 13);
+        quarter_round(&mut state, 2, 6, 10, 14);
+        quarter_round(&mut state, 3, 7, 11, 15);
+        // Diagonals
+        quarter_round(&mut state, 0, 5, 10, 15);
+        quarter_round(&mut state, 1, 6, 11, 12);
+        quarter_round(&mut state, 2, 7, 8, 13);
+        quarter_round(&mut state, 3, 4, 9, 14);
+    }
+    for (word, input) in state.iter_mut().zip(input) {
+        *word = word.wrapping_add(input);
+    }
+    state
+}
+
+// Define helpers
+fn quarter_round(state: &mut [u32; BLOCK_WORDS], a: usize, b: usize, c: usize, d: usize) {
+    state[a] = state[a].wrapping_add(state[b]);
+    state[d] = (state[d] ^ state[a]).rotate_left(16);
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_left(12);
+    state[a] = state[a].wrapping_add(state[b]);
+    state[d] = (state[d] ^ state[a]).rotate_left(8);
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_left(7);
+}
@@ -0,0 +1,216 @@
+// Import dependencies
+use core::{sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering}, time::Duration};
+use crate::arch::cpu::{self, IrqGuard, CORE_COUNT};
+use crate::drivers::rng;
+use crate::sync::spin::Spinlock;
+use crate::task::WaitQueue;
+use crate::time::Instant;
+use self::chacha::{BLOCK_SIZE, KEY_WORDS};
+// Define modules
+mod chacha;
+
+// Define constants
+/// Entropy the pool must gather before the generator is seeded, in bits
+const SEED_BITS: u32 = 256;
+/// Time after which the generator takes in the pool again
+const RESEED_INTERVAL: Duration = Duration::from_secs(60);
+/// Hardware words read when seeding and reseeding, each credited with half its bits
+const HARDWARE_SEED_WORDS: usize = 16;
+const HARDWARE_RESEED_WORDS: usize = 4;
+const HARDWARE_WORD_BITS: u32 = 16;
+/// Interrupts folded into a per-core pool before it is mixed in, crediting one bit
+const FAST_POOL_EVENTS: u32 = 64;
+/// Most bytes generated at once with IRQs masked
+const CHUNK_SIZE: usize = 4 * BLOCK_SIZE;
+/// Keystream nonces of the pool operations
+const NONCE_MIX: u64 = 0;
+const NONCE_EXTRACT: u64 = 1;
+
+// Define structs
+/// Entropy accumulated from the sources, as the key of a ChaCha20 permutation
+struct Pool {
+    state: [u32; KEY_WORDS],
+    /// Estimated entropy of the state, in bits
+    credited: u32,
+    /// Mixes so far, making every permutation distinct
+    mixes: u64,
+}
+
+/// ChaCha20 generator with fast key erasure: each request is followed by
+/// a new key, so earlier output cannot be recovered from the current state
+struct Generator {
+    key: [u32; KEY_WORDS],
+    /// Unset until the first seeding
+    reseeded: Option<Instant>,
+}
+
+/// Interrupt timings gathered by a core without locks (only touched with IRQs masked)
+struct FastPool {
+    state: AtomicU64,
+    events: AtomicU32,
+}
+
+// Define globals
+static POOL: Spinlock<Pool> = Spinlock::new(Pool { state: [0; KEY_WORDS], credited: 0, mixes: 0 });
+static GENERATOR: Spinlock<Generator> = Spinlock::new(Generator { key: [0; KEY_WORDS], reseeded: None });
+static FAST_POOLS: [FastPool; CORE_COUNT] = [FAST_POOL_EMPTY; CORE_COUNT];
+#[allow(clippy::declare_interior_mutable_const)]
+const FAST_POOL_EMPTY: FastPool = FastPool { state: AtomicU64::new(0), events: AtomicU32::new(0) };
+/// The pool gathered `SEED_BITS` once
+static SEEDED: AtomicBool = AtomicBool::new(false);
+/// Threads in `random_bytes` before the seeding
+static SEED_WAITERS: WaitQueue = WaitQueue::new();
+
+// Define procedures
+/// Starts the hardware generator and seeds the pool with it.
+///
+/// Without it, the pool is seeded by interrupt timings only, which takes a while.
+pub fn init() {
+    rng::init();
+    let mut words = [0; HARDWARE_SEED_WORDS];
+    let read = rng::fill(&mut words);
+    add_entropy(&words[..read], read as u32 * HARDWARE_WORD_BITS);
+}
+
+/// Mixes `words` into the pool, crediting it with `bits` of entropy.
+///
+/// Device data known to attackers (serial numbers, MAC addresses) is fine
+/// with no credit: it only makes the state differ between boards.
+pub fn add_entropy(words: &[u32], bits: u32) {
+    let _irq = IrqGuard::new();
+    POOL.lock().mix(words, bits);
+}
+
+/// Gathers the timing of an interrupt that started at counter value `start`.
+///
+/// Called on every IRQ exception, with IRQs masked.
+pub fn add_interrupt_randomness(start: u64) {
+    let pool = &FAST_POOLS[cpu::this_core()];
+    let sample = start ^ cpu::counter().rotate_left(32);
+    let state = (pool.state.load(Ordering::Relaxed).rotate_left(7) ^ sample).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    pool.state.store(state, Ordering::Relaxed);
+    let events = pool.events.load(Ordering::Relaxed) + 1;
+    // Keeps gathering while another core uses the pool
+    if events >= FAST_POOL_EVENTS {
+        if let Some(mut main_pool) = POOL.try_lock() {
+            main_pool.mix(&[state as u32, (state >> 32) as u32], 1);
+            pool.events.store(0, Ordering::Relaxed);
+            return;
+        }
+    }
+    pool.events.store(events, Ordering::Relaxed);
+}
+
+/// Whether the pool gathered enough entropy for `random_bytes` not to block.
+pub fn is_seeded() -> bool {
+    SEEDED.load(Ordering::Acquire)
+}
+
+/// Fills `buffer` with cryptographically secure random bytes.
+///
+/// Blocks the calling thread until the pool is seeded.
+pub fn random_bytes(buffer: &mut [u8]) {
+    SEED_WAITERS.wait_until(is_seeded);
+    generate(buffer);
+}
+
+/// Like `random_bytes`, failing instead of blocking before the seeding.
+pub fn try_random_bytes(buffer: &mut [u8]) -> bool {
+    if !is_seeded() {
+        return false;
+    }
+    generate(buffer);
+    true
+}
+
+pub fn random_u64() -> u64 {
+    let mut bytes = [0; 8];
+    random_bytes(&mut bytes);
+    u64::from_le_bytes(bytes)
+}
+
+// Implement structs
+impl Pool {
+    fn mix(&mut self, words: &[u32], bits: u32) {
+        for chunk in words.chunks(KEY_WORDS) {
+            for (word, input) in self.state.iter_mut().zip(chunk) {
+                *word ^= input;
+            }
+            self.permute();
+        }
+        self.credited = self.credited.saturating_add(bits);
+        if self.credited >= SEED_BITS && !SEEDED.swap(true, Ordering::AcqRel) {
+            SEED_WAITERS.notify_all();
+        }
+    }
+
+    /// Replaces the state by a one-way function of it.
+    fn permute(&mut self) {
+        let block = chacha::block(&self.state, self.mixes, NONCE_MIX);
+        self.state.copy_from_slice(&block[..KEY_WORDS]);
+        self.mixes += 1;
+    }
+
+    /// Takes a seed out of the pool, leaving it in a state the seed does not reveal.
+    fn extract(&mut self) -> [u32; KEY_WORDS] {
+        let block = chacha::block(&self.state, self.mixes, NONCE_EXTRACT);
+        let mut seed = [0; KEY_WORDS];
+        seed.copy_from_slice(&block[..KEY_WORDS]);
+        self.state.copy_from_slice(&block[KEY_WORDS..]);
+        self.mixes += 1;
+        self.credited = 0;
+        seed
+    }
+}
+
+impl Generator {
+    fn reseed_due(&self) -> bool {
+        self.reseeded.map_or(true, |reseeded| reseeded.elapsed() >= RESEED_INTERVAL)
+    }
+
+    fn reseed(&mut self, seed: [u32; KEY_WORDS]) {
+        for (word, seed) in self.key.iter_mut().zip(seed) {
+            *word ^= seed;
+        }
+        self.rekey();
+        self.reseeded = Some(Instant::now());
+    }
+
+    /// Fills `buffer` (at most `CHUNK_SIZE` bytes) and moves to a new key.
+    fn fill(&mut self, buffer: &mut [u8]) {
+        // Block 0 is the next key
+        for (counter, chunk) in (1..).zip(buffer.chunks_mut(BLOCK_SIZE)) {
+            let block = chacha::block(&self.key, counter, 0);
+            for (byte, random) in chunk.iter_mut().zip(block.iter().flat_map(|word| word.to_le_bytes())) {
+                *byte = random;
+            }
+        }
+        self.rekey();
+    }
+
+    fn rekey(&mut self) {
+        let block = chacha::block(&self.key, 0, 0);
+        self.key.copy_from_slice(&block[..KEY_WORDS]);
+    }
+}
+
+// Define helpers
+fn generate(buffer: &mut [u8]) {
+    for chunk in buffer.chunks_mut(CHUNK_SIZE) {
+        let _irq = IrqGuard::new();
+        let mut generator = GENERATOR.lock();
+        if generator.reseed_due() {
+            reseed(&mut generator);
+        }
+        generator.fill(chunk);
+    }
+}
+
+/// Feeds fresh hardware words and the pool to the generator.
+fn reseed(generator: &mut Generator) {
+    let mut words = [0; HARDWARE_RESEED_WORDS];
+    let read = rng::fill(&mut words);
+    let mut pool = POOL.lock();
+    pool.mix(&words[..read], read as u32 * HARDWARE_WORD_BITS);
+    generator.reseed(pool.extract());
+}
@@ -0,0 +1,178 @@
+// Import dependencies
+use core::{hint, mem, sync::atomic::{AtomicBool, AtomicUsize, Ordering}};
+use crate::arch::cpu::{self, IrqGuard, CORE_COUNT};
+use crate::task::preempt::preempt_disable;
+use super::{send_ipi, Ipi};
+
+// Define constants
+/// Slot function value while the sender fills it in
+const CLAIMED: usize = 1;
+
+// Define structs
+pub type CallFn = fn(usize);
+
+/// Call posted by one core to another
+struct CallSlot {
+    /// Function address (0 when the slot is free, `CLAIMED` while it is filled in)
+    function: AtomicUsize,
+    data: AtomicUsize,
+    /// The sender waits: the slot is only freed once the call returned
+    wait: AtomicBool,
+}
+
+// Define globals
+/// Slots indexed by target then sender core
+static CALLS: [[CallSlot; CORE_COUNT]; CORE_COUNT] = [CALL_SLOTS_EMPTY; CORE_COUNT];
+#[allow(clippy::declare_interior_mutable_const)]
+const CALL_SLOTS_EMPTY: [CallSlot; CORE_COUNT] = [CallSlot::EMPTY; CORE_COUNT];
+
+// Define procedures
+/// Runs `f` on `core`, returning once it returned.
+///
+/// Remote calls run in the IPI handler of the core, with IRQs masked, so `f` must not block.
+///
+/// # Panics
+///
+/// Panics if IRQs are masked (cores calling each other would deadlock).
+pub fn call_on<F: FnOnce() + Send>(core: usize, f: F) {
+    assert!(!cpu::irqs_masked(), "Waiting for a cross-core call with IRQs masked");
+    // The slot waited for is the one of this core
+    let _preempt = preempt_disable();
+    if core == cpu::this_core() {
+        let _irq = IrqGuard::new();
+        return f();
+    }
+    let mut f = Some(f);
+    post(core, run_once::<F>, &mut f as *mut Option<F> as usize, true);
+    wait_for(core);
+}
+
+/// Runs `f` on every core (the calling one included), returning once they all returned.
+///
+/// Same constraints as [`call_on`].
+pub fn call_on_all<F: Fn() + Sync>(f: F) {
+    assert!(!cpu::irqs_masked(), "Waiting for a cross-core call with IRQs masked");
+    let _preempt = preempt_disable();
+    let this = cpu::this_core();
+    for core in (0..CORE_COUNT).filter(|&core| core != this) {
+        post(core, run_shared::<F>, &f as *const F as usize, true);
+    }
+    {
+        let _irq = IrqGuard::new();
+        f();
+    }
+    (0..CORE_COUNT).filter(|&core| core != this).for_each(wait_for);
+}
+
+/// Posts `function(data)` to run on `core`, without waiting for it.
+///
+/// Callable from IRQ handlers. On the calling core, it runs before returning.
+pub fn call_on_async(core: usize, function: CallFn, data: usize) {
+    if core == cpu::this_core() {
+        let _irq = IrqGuard::new();
+        function(data);
+    } else {
+        post(core, function, data, false);
+    }
+}
+
+/// Posts `function(data)` to run on every core, without waiting for the other ones.
+pub fn call_on_all_async(function: CallFn, data: usize) {
+    let _preempt = preempt_disable();
+    let this = cpu::this_core();
+    for core in (0..CORE_COUNT).filter(|&core| core != this) {
+        post(core, function, data, false);
+    }
+    let _irq = IrqGuard::new();
+    function(data);
+}
+
+/// Invalidates the TLBs of every core, returning once they are all done.
+pub fn tlb_shootdown() {
+    // SAFETY: Only drops cached translations
+    call_on_all(|| unsafe { cpu::flush_tlb_all() })
+}
+
+/// Invalidates the translations of the page holding `address` on every core.
+pub fn tlb_shootdown_page(address: usize) {
+    // SAFETY: Only drops cached translations
+    call_on_all(move || unsafe { cpu::flush_tlb_page(address) })
+}
+
+/// Runs the calls posted to the calling core (with IRQs masked).
+pub(super) fn run_pending() {
+    for slot in &CALLS[cpu::this_core()] {
+        let function = slot.function.load(Ordering::Acquire);
+        // The sender raises the IPI again once the slot is filled in
+        if function <= CLAIMED {
+            continue;
+        }
+        let data = slot.data.load(Ordering::Relaxed);
+        let wait = slot.wait.load(Ordering::Relaxed);
+        // SAFETY: Only function addresses are stored past `CLAIMED`
+        let function = unsafe { to_call(function) };
+        // Free the slot first when nobody waits, so the sender can post again from the call
+        if !wait {
+            slot.function.store(0, Ordering::Release);
+        }
+        function(data);
+        if wait {
+            slot.function.store(0, Ordering::Release);
+        }
+    }
+}
+
+// Implement structs
+impl CallSlot {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const EMPTY: CallSlot = CallSlot {
+        function: AtomicUsize::new(0),
+        data: AtomicUsize::new(0),
+        wait: AtomicBool::new(false),
+    };
+}
+
+// Define helpers
+/// Fills in the slot of the calling core towards `core` and raises the IPI.
+fn post(core: usize, function: CallFn, data: usize, wait: bool) {
+    let slot = &CALLS[core][cpu::this_core()];
+    while slot.function.compare_exchange_weak(0, CLAIMED, Ordering::Acquire, Ordering::Relaxed).is_err() {
+        // The target may itself be spinning on this core with IRQs masked
+        if cpu::irqs_masked() {
+            run_pending();
+        }
+        hint::spin_loop();
+    }
+    slot.data.store(data, Ordering::Relaxed);
+    slot.wait.store(wait, Ordering::Relaxed);
+    slot.function.store(function as usize, Ordering::Release);
+    send_ipi(core, Ipi::Call);
+}
+
+/// Waits until the slot of the calling core towards `core` is free (its call returned).
+fn wait_for(core: usize) {
+    let slot = &CALLS[core][cpu::this_core()];
+    while slot.function.load(Ordering::Acquire) != 0 {
+        hint::spin_loop();
+    }
+}
+
+/// Call trampoline of `call_on`, `data` pointing to the closure
+fn run_once<F: FnOnce()>(data: usize) {
+    // SAFETY: `call_on` keeps the closure alive until the call returned
+    let f = unsafe { &mut *(data as *mut Option<F>) };
+    if let Some(f) = f.take() {
+        f()
+    }
+}
+
+/// Call trampoline of `call_on_all`, `data` pointing to the closure
+fn run_shared<F: Fn()>(data: usize) {
+    // SAFETY: `call_on_all` keeps the closure alive until every call returned
+    let f = unsafe { &*(data as *const F) };
+    f()
+}
+
+unsafe fn to_call(address: usize) -> CallFn {
+    mem::transmute::<usize, CallFn>(address)
+}
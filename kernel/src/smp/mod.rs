@@ -0,0 +1,135 @@
+// Import dependencies
+use core::{hint, sync::atomic::{AtomicUsize, Ordering}};
+use enum_iterator::{all, Sequence};
+use crate::arch::{cpu::{self, CORE_COUNT}, interrupts::irq};
+#[cfg(feature = "gic")]
+use crate::drivers::gic;
+#[cfg(not(feature = "gic"))]
+use crate::drivers::local_intc::{self, IRQ_MAILBOX_0};
+use crate::task::preempt;
+// Define modules
+pub mod call;
+// Export definitions
+pub use call::{call_on, call_on_all, call_on_all_async, call_on_async, tlb_shootdown, tlb_shootdown_page};
+
+// Define constants
+/// ARM-local mailbox carrying the IPIs (one bit per kind)
+#[cfg(not(feature = "gic"))]
+const IPI_MAILBOX: usize = 0;
+/// Spins to wait for the other cores to acknowledge a stop
+const STOP_SPINS: usize = 1 << 20;
+
+// Define structs
+/// Inter-processor interrupt kinds
+#[repr(usize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Sequence)]
+pub enum Ipi {
+    /// Reschedule on return from the interrupt (a thread of higher rank became ready)
+    Reschedule = 0,
+    /// Run the cross-core calls posted to the core
+    Call = 1,
+    /// Mask IRQs and halt
+    Stop = 2,
+}
+
+// Define globals
+/// Cores halted by `Ipi::Stop`
+static STOPPED: AtomicUsize = AtomicUsize::new(0);
+
+// Define procedures
+/// Lets the calling core receive IPIs.
+pub fn init_core() {
+    init_transport();
+}
+
+/// Interrupts `core` with `ipi` (IPIs of the same kind sent meanwhile are merged).
+pub fn send_ipi(core: usize, ipi: Ipi) {
+    // The data the IPI refers to must be visible when it is taken
+    cpu::store_barrier();
+    raise(core, ipi);
+}
+
+/// Interrupts every core but the calling one with `ipi`.
+pub fn send_ipi_others(ipi: Ipi) {
+    let this = cpu::this_core();
+    cpu::store_barrier();
+    (0..CORE_COUNT).filter(|&core| core != this).for_each(|core| raise(core, ipi));
+}
+
+/// Halts every other core, callable from any context (used on panic).
+///
+/// The wait for them is bounded, as cores with IRQs masked never stop.
+/// Returns whether they all stopped.
+pub fn stop_other_cores() -> bool {
+    send_ipi_others(Ipi::Stop);
+    (0..STOP_SPINS)
+        .find(|_| {
+            hint::spin_loop();
+            STOPPED.load(Ordering::Acquire) == CORE_COUNT - 1
+        })
+        .is_some()
+}
+
+// Define helpers
+/// Handles `ipi` (called from its IRQ handler, with IRQs masked)
+fn handle(ipi: Ipi) {
+    match ipi {
+        // Acted upon on the exception return
+        Ipi::Reschedule => preempt::set_need_resched(),
+        Ipi::Call => call::run_pending(),
+        Ipi::Stop => {
+            STOPPED.fetch_add(1, Ordering::AcqRel);
+            // SAFETY: Nothing runs on the core anymore
+            unsafe { cpu::park_core() }
+        }
+    }
+}
+
+#[cfg(not(feature = "gic"))]
+fn init_transport() {
+    // Every core shares the same handler
+    if let Err(installed) = irq::register(IRQ_MAILBOX_0 + IPI_MAILBOX, handle_mailbox) {
+        assert!(installed == handle_mailbox as irq::IrqHandler, "IPI mailbox IRQ already in use");
+    }
+    local_intc::enable_mailbox_irq(IPI_MAILBOX);
+}
+
+#[cfg(not(feature = "gic"))]
+fn raise(core: usize, ipi: Ipi) {
+    local_intc::mailbox_send(core, IPI_MAILBOX, 1 << ipi as u32);
+}
+
+#[cfg(not(feature = "gic"))]
+fn handle_mailbox() {
+    let pending = local_intc::mailbox_take(IPI_MAILBOX);
+    for ipi in all::<Ipi>().filter(|&ipi| pending & (1 << ipi as u32) != 0) {
+        handle(ipi);
+    }
+}
+
+/// Each kind has its own SGI
+#[cfg(feature = "gic")]
+fn init_transport() {
+    for ipi in all::<Ipi>() {
+        let sgi = gic::IRQ_SGI_0 + ipi as usize;
+        let handler = sgi_handler(ipi);
+        if let Err(installed) = irq::register(sgi, handler) {
+            assert!(installed == handler, "IPI SGI {} already in use", sgi);
+        }
+        gic::enable(sgi);
+    }
+}
+
+#[cfg(feature = "gic")]
+fn raise(core: usize, ipi: Ipi) {
+    gic::send_sgi(core, gic::IRQ_SGI_0 + ipi as usize);
+}
+
+#[cfg(feature = "gic")]
+fn sgi_handler(ipi: Ipi) -> irq::IrqHandler {
+    match ipi {
+        Ipi::Reschedule => || handle(Ipi::Reschedule),
+        Ipi::Call => || handle(Ipi::Call),
+        Ipi::Stop => || handle(Ipi::Stop),
+    }
+}
@@ -1 +1,6 @@
+pub mod mpsc;
+pub mod once;
+pub mod rcu;
+pub mod ring;
+pub mod seqlock;
 pub mod spin;
\ No newline at end of file
@@ -0,0 +1,136 @@
+// Import dependencies
+use core::{marker::PhantomData, ptr, sync::atomic::{AtomicBool, AtomicPtr, Ordering}};
+
+/// Link embedded in the nodes of a [`MpscQueue`].
+#[repr(C)]
+pub struct MpscLink {
+    next: AtomicPtr<MpscLink>,
+}
+
+/// Node of an intrusive queue.
+///
+/// # Safety
+///
+/// Implementors must be `#[repr(C)]` with an [`MpscLink`] as first field.
+pub unsafe trait MpscNode {}
+
+/// Intrusive lock-free multi-producer single-consumer queue (Vyukov's algorithm).
+///
+/// Pushing is a single atomic swap, so it is wait-free and can be done
+/// from IRQ handlers on any core. Nodes are `'static`, nothing is allocated.
+pub struct MpscQueue<T: MpscNode> {
+    /// Last pushed link (producers side, null stands for the stub)
+    head: AtomicPtr<MpscLink>,
+    /// Next link to pop (consumer side, null stands for the stub)
+    tail: AtomicPtr<MpscLink>,
+    stub: MpscLink,
+    consumer_taken: AtomicBool,
+    _nodes: PhantomData<*const T>,
+}
+
+pub struct MpscQueueConsumer<'queue, T: MpscNode> {
+    queue: &'queue MpscQueue<T>,
+    _not_sync: PhantomData<*mut T>,
+}
+
+// Implement structs
+impl MpscLink {
+    pub const fn new() -> Self {
+        Self {
+            next: AtomicPtr::new(ptr::null_mut()),
+        }
+    }
+}
+
+impl<T: MpscNode> MpscQueue<T> {
+    pub const fn new() -> Self {
+        Self {
+            head: AtomicPtr::new(ptr::null_mut()),
+            tail: AtomicPtr::new(ptr::null_mut()),
+            stub: MpscLink::new(),
+            consumer_taken: AtomicBool::new(false),
+            _nodes: PhantomData,
+        }
+    }
+
+    /// Appends `node` from any core.
+    ///
+    /// The node must not be queued already (in this or another queue).
+    pub fn push(&self, node: &'static T) {
+        self.push_link(node as *const T as *mut MpscLink)
+    }
+
+    /// Claims the consumer endpoint (fails while it is taken).
+    pub fn take_consumer(&self) -> Option<MpscQueueConsumer<'_, T>> {
+        (!self.consumer_taken.swap(true, Ordering::AcqRel)).then(|| MpscQueueConsumer {
+            queue: self,
+            _not_sync: PhantomData,
+        })
+    }
+
+    fn push_link(&self, link: *mut MpscLink) {
+        // SAFETY: Links are 'static
+        unsafe { (*link).next.store(ptr::null_mut(), Ordering::Relaxed) };
+        let prev = self.resolve(self.head.swap(link, Ordering::AcqRel));
+        // Until this store, the consumer sees the queue as cut after `prev`
+        unsafe { (*prev).next.store(link, Ordering::Release) };
+    }
+
+    fn stub(&self) -> *mut MpscLink {
+        &self.stub as *const MpscLink as *mut MpscLink
+    }
+
+    fn resolve(&self, link: *mut MpscLink) -> *mut MpscLink {
+        if link.is_null() { self.stub() } else { link }
+    }
+}
+
+impl<T: MpscNode> MpscQueueConsumer<'_, T> {
+    /// Removes the oldest node.
+    ///
+    /// May return `None` while a producer is in the middle of a push.
+    pub fn pop(&mut self) -> Option<&'static T> {
+        let queue = self.queue;
+        let stub = queue.stub();
+        let mut tail = queue.resolve(queue.tail.load(Ordering::Relaxed));
+        // SAFETY: Links in the queue are 'static
+        let mut next = unsafe { (*tail).next.load(Ordering::Acquire) };
+        // Skip the stub
+        if tail == stub {
+            if next.is_null() {
+                return None;
+            }
+            queue.tail.store(next, Ordering::Relaxed);
+            tail = next;
+            next = unsafe { (*next).next.load(Ordering::Acquire) };
+        }
+        if !next.is_null() {
+            queue.tail.store(next, Ordering::Relaxed);
+            return Some(unsafe { &*(tail as *const T) });
+        }
+        // `tail` is the last node, unless a push is in progress
+        if tail != queue.resolve(queue.head.load(Ordering::Acquire)) {
+            return None;
+        }
+        // Re-insert the stub so that `tail` can be detached
+        queue.push_link(stub);
+        next = unsafe { (*tail).next.load(Ordering::Acquire) };
+        if !next.is_null() {
+            queue.tail.store(next, Ordering::Relaxed);
+            return Some(unsafe { &*(tail as *const T) });
+        }
+        None
+    }
+}
+
+impl<T: MpscNode> Drop for MpscQueueConsumer<'_, T> {
+    fn drop(&mut self) {
+        self.queue.consumer_taken.store(false, Ordering::Release);
+    }
+}
+
+// Implement thread safety
+// SAFETY: Producers only swap the head, the consumer endpoint is unique
+unsafe impl<T: MpscNode + Sync> Sync for MpscQueue<T> {}
+unsafe impl<T: MpscNode + Sync> Send for MpscQueue<T> {}
+unsafe impl<T: MpscNode + Sync> Send for MpscQueueConsumer<'_, T> {}
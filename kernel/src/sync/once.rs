@@ -0,0 +1,266 @@
+// Import dependencies
+use core::{cell::{Cell, UnsafeCell}, fmt, hint::spin_loop, mem::{self, MaybeUninit}, ops::Deref, sync::atomic::{AtomicU8, Ordering}};
+
+// Define constants
+const INCOMPLETE: u8 = 0;
+const RUNNING: u8 = 1;
+const COMPLETE: u8 = 2;
+const POISONED: u8 = 3;
+
+/// One-time initialization barrier.
+///
+/// Only a state byte is used, so it can live in `.bss` and be used
+/// before any allocator exists. Cores racing on `call_once` spin until
+/// the winner finishes the initialization.
+///
+/// An initializer that panics poisons the `Once`. The kernel is built with
+/// `panic = "abort"` and its panic handler stops every core, so poisoning is
+/// only observed by unwinding builds (such as host-side tests).
+#[repr(C)]
+pub struct Once {
+    state: AtomicU8,
+}
+
+/// Write-once cell, initialized at runtime by any core.
+#[repr(C)]
+pub struct OnceCell<T> {
+    once: Once,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+/// Value initialized on first access.
+pub struct Lazy<T, F = fn() -> T> {
+    cell: OnceCell<T>,
+    init: Cell<Option<F>>,
+}
+
+/// Marks the `Once` as poisoned if the initializer does not return
+struct PoisonOnDrop<'once> {
+    state: &'once AtomicU8,
+}
+
+// Implement structs
+impl Once {
+    pub const fn new() -> Self {
+        Self {
+            state: AtomicU8::new(INCOMPLETE),
+        }
+    }
+
+    /// Runs `init` exactly once, even if called concurrently from several cores.
+    ///
+    /// When this returns, the initialization is visible to the caller.
+    /// Calling it again from inside `init` deadlocks.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a previous initializer panicked (the `Once` is poisoned).
+    pub fn call_once<F: FnOnce()>(&self, init: F) {
+        // Fast path: already initialized (acquire to see the initialized data)
+        if self.state.load(Ordering::Acquire) == COMPLETE {
+            return;
+        }
+        self.call_once_slow(init)
+    }
+
+    #[cold]
+    fn call_once_slow<F: FnOnce()>(&self, init: F) {
+        loop {
+            match self.state.compare_exchange_weak(INCOMPLETE, RUNNING, Ordering::Acquire, Ordering::Acquire) {
+                Ok(_) => {
+                    // We won the race, run the initializer
+                    let guard = PoisonOnDrop { state: &self.state };
+                    init();
+                    mem::forget(guard);
+                    // Publish the initialized data
+                    self.state.store(COMPLETE, Ordering::Release);
+                    return;
+                }
+                Err(RUNNING) => {
+                    self.wait();
+                    return;
+                }
+                Err(COMPLETE) => return,
+                Err(POISONED) => panic!("Once instance has been poisoned"),
+                // Spurious failure of the weak exchange
+                Err(_) => spin_loop(),
+            }
+        }
+    }
+
+    /// Blocks until another core completes the initialization.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the initializer panicked.
+    pub fn wait(&self) {
+        loop {
+            match self.state.load(Ordering::Acquire) {
+                COMPLETE => return,
+                POISONED => panic!("Once instance has been poisoned"),
+                _ => spin_loop(),
+            }
+        }
+    }
+
+    pub fn is_completed(&self) -> bool {
+        self.state.load(Ordering::Acquire) == COMPLETE
+    }
+
+    pub fn is_poisoned(&self) -> bool {
+        self.state.load(Ordering::Acquire) == POISONED
+    }
+
+    /// Asserts that the initialization has completed (debug builds only).
+    #[inline(always)]
+    #[track_caller]
+    pub fn debug_assert_completed(&self) {
+        debug_assert!(self.is_completed(), "Once instance used before initialization");
+    }
+}
+
+impl<'once> Drop for PoisonOnDrop<'once> {
+    fn drop(&mut self) {
+        // Only reached when the initializer unwinds
+        self.state.store(POISONED, Ordering::Release);
+    }
+}
+
+impl<T> OnceCell<T> {
+    pub const fn new() -> Self {
+        Self {
+            once: Once::new(),
+            value: UnsafeCell::new(MaybeUninit::uninit()),
+        }
+    }
+
+    /// Returns the value, if it was already initialized.
+    pub fn get(&self) -> Option<&T> {
+        if self.once.is_completed() {
+            // SAFETY: Completed cells are never written again
+            Some(unsafe { self.get_unchecked() })
+        } else {
+            None
+        }
+    }
+
+    pub fn get_mut(&mut self) -> Option<&mut T> {
+        if self.once.is_completed() {
+            // SAFETY: Initialized and exclusively borrowed
+            Some(unsafe { (*self.value.get()).assume_init_mut() })
+        } else {
+            None
+        }
+    }
+
+    /// Returns the value without checking the initialization state.
+    ///
+    /// Debug builds still assert that the cell was initialized.
+    ///
+    /// # Safety
+    ///
+    /// The cell must have been initialized.
+    #[inline(always)]
+    #[track_caller]
+    pub unsafe fn get_unchecked(&self) -> &T {
+        self.once.debug_assert_completed();
+        (*self.value.get()).assume_init_ref()
+    }
+
+    /// Initializes the cell with `value`, returning it back if the cell was already set.
+    pub fn set(&self, value: T) -> Result<(), T> {
+        let mut value = Some(value);
+        self.get_or_init(|| value.take().unwrap());
+        match value {
+            None => Ok(()),
+            Some(value) => Err(value),
+        }
+    }
+
+    /// Returns the value, initializing it with `init` if needed.
+    pub fn get_or_init<F: FnOnce() -> T>(&self, init: F) -> &T {
+        self.once.call_once(|| {
+            // SAFETY: Only the winning core runs this closure
+            unsafe { (*self.value.get()).write(init()) };
+        });
+        // SAFETY: call_once returns only after completion
+        unsafe { self.get_unchecked() }
+    }
+
+    /// Blocks until another core initializes the cell.
+    pub fn wait(&self) -> &T {
+        self.once.wait();
+        // SAFETY: Waited for completion
+        unsafe { self.get_unchecked() }
+    }
+
+    pub fn is_poisoned(&self) -> bool {
+        self.once.is_poisoned()
+    }
+
+    pub fn into_inner(mut self) -> Option<T> {
+        if self.once.is_completed() {
+            // Prevent double drop by resetting the state before reading the value
+            *self.once.state.get_mut() = INCOMPLETE;
+            // SAFETY: Initialized and owned
+            Some(unsafe { self.value.get_mut().assume_init_read() })
+        } else {
+            None
+        }
+    }
+}
+
+impl<T> Drop for OnceCell<T> {
+    fn drop(&mut self) {
+        if *self.once.state.get_mut() == COMPLETE {
+            // SAFETY: Initialized and never dropped before
+            unsafe { self.value.get_mut().assume_init_drop() }
+        }
+    }
+}
+
+impl<T, F: FnOnce() -> T> Lazy<T, F> {
+    pub const fn new(init: F) -> Self {
+        Self {
+            cell: OnceCell::new(),
+            init: Cell::new(Some(init)),
+        }
+    }
+
+    /// Forces the evaluation of the initializer.
+    pub fn force(this: &Self) -> &T {
+        this.cell.get_or_init(|| match this.init.take() {
+            Some(init) => init(),
+            None => panic!("Lazy instance has been poisoned"),
+        })
+    }
+
+    /// Returns the value, if it was already initialized.
+    pub fn get(this: &Self) -> Option<&T> {
+        this.cell.get()
+    }
+}
+
+impl<T, F: FnOnce() -> T> Deref for Lazy<T, F> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        Lazy::force(self)
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for OnceCell<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.get() {
+            Some(value) => f.debug_tuple("OnceCell").field(value).finish(),
+            None => f.write_str("OnceCell(<uninit>)"),
+        }
+    }
+}
+
+// Implement thread safety
+// SAFETY: The value is written once (by a single core) before being shared
+unsafe impl<T: Send + Sync> Sync for OnceCell<T> {}
+unsafe impl<T: Send> Send for OnceCell<T> {}
+// SAFETY: The initializer is only taken by the core winning the `Once`
+unsafe impl<T: Send + Sync, F: Send> Sync for Lazy<T, F> {}
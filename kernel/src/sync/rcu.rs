@@ -0,0 +1,208 @@
+// Import dependencies
+use core::{cell::Cell, hint::spin_loop, ptr::{self, NonNull}, sync::atomic::{fence, AtomicPtr, AtomicUsize, Ordering}};
+use crate::arch::cpu::{percpu::per_cpu, CORE_COUNT};
+use crate::task::preempt::{preempt_disable, PreemptGuard};
+use super::spin::Spinlock;
+
+// Define structs
+/// Read-side state of a core.
+struct RcuCpu {
+    /// Read-side critical section nesting
+    nesting: AtomicUsize,
+    /// Grace period observed when entering the outermost section (0 when quiescent)
+    gp: AtomicUsize,
+}
+
+/// Read-side critical section.
+///
+/// Pointers loaded from a [`RcuCell`] are valid while the guard is alive.
+/// The thread is not preempted meanwhile, so it cannot leave its core.
+pub struct RcuReadGuard {
+    _preempt: PreemptGuard,
+}
+
+/// Pointer to `'static` data, read without locks and replaced by writers.
+pub struct RcuCell<T: 'static> {
+    ptr: AtomicPtr<T>,
+}
+
+/// Value removed from a [`RcuCell`] that readers may still be using.
+#[must_use = "the value must wait for a grace period before being reused"]
+pub struct Retired<T: 'static> {
+    /// Not a reference: readers may still hold shared ones
+    value: NonNull<T>,
+}
+
+/// Intrusive node used to defer a callback until after a grace period.
+pub struct RcuHead {
+    next: AtomicPtr<RcuHead>,
+    gp: AtomicUsize,
+    func: Cell<Option<fn(&'static RcuHead)>>,
+}
+
+// Define globals
+/// Grace period counter (0 is reserved to mark quiescent cores)
+static GP_COUNTER: AtomicUsize = AtomicUsize::new(1);
+/// Serializes writers waiting for grace periods
+static GP_LOCK: Spinlock<()> = Spinlock::new(());
+/// Callbacks waiting for their grace period
+static PENDING: AtomicPtr<RcuHead> = AtomicPtr::new(ptr::null_mut());
+
+per_cpu! {
+    static RCU_CPU: RcuCpu = RcuCpu {
+        nesting: AtomicUsize::new(0),
+        gp: AtomicUsize::new(0),
+    };
+}
+
+// Define procedures
+/// Enters a read-side critical section (sections may nest).
+pub fn rcu_read_lock() -> RcuReadGuard {
+    let preempt = preempt_disable();
+    let cpu = RCU_CPU.get();
+    if cpu.nesting.load(Ordering::Relaxed) == 0 {
+        cpu.gp.store(GP_COUNTER.load(Ordering::Relaxed), Ordering::Relaxed);
+        // Order the announcement before the reads of the section
+        fence(Ordering::SeqCst);
+    }
+    cpu.nesting.fetch_add(1, Ordering::Relaxed);
+    RcuReadGuard { _preempt: preempt }
+}
+
+/// Waits until every read-side critical section started before the call has ended.
+///
+/// # Panics
+///
+/// Panics when called inside a read-side critical section (it would never return).
+pub fn synchronize_rcu() {
+    assert!(RCU_CPU.get().nesting.load(Ordering::Relaxed) == 0, "synchronize_rcu inside a read-side critical section");
+    let _writer = GP_LOCK.lock();
+    let target = start_grace_period();
+    while !grace_period_completed(target) {
+        spin_loop()
+    }
+}
+
+/// Runs `func(head)` once a grace period has elapsed.
+///
+/// Callbacks run from [`rcu_process_callbacks`], with no read-side section held.
+pub fn call_rcu(head: &'static RcuHead, func: fn(&'static RcuHead)) {
+    head.func.set(Some(func));
+    head.gp.store(start_grace_period(), Ordering::Relaxed);
+    push_pending(head);
+}
+
+/// Runs the deferred callbacks whose grace period has completed.
+///
+/// Returns the number of callbacks executed.
+pub fn rcu_process_callbacks() -> usize {
+    let mut executed = 0;
+    let mut node = PENDING.swap(ptr::null_mut(), Ordering::Acquire);
+    while !node.is_null() {
+        // SAFETY: Heads are 'static and owned by the list while pending
+        let head: &'static RcuHead = unsafe { &*node };
+        node = head.next.load(Ordering::Relaxed);
+        if grace_period_completed(head.gp.load(Ordering::Relaxed)) {
+            if let Some(func) = head.func.take() {
+                func(head);
+                executed += 1;
+            }
+        } else {
+            push_pending(head);
+        }
+    }
+    executed
+}
+
+/// Whether deferred callbacks are waiting for their grace period.
+pub fn rcu_has_callbacks() -> bool {
+    !PENDING.load(Ordering::Relaxed).is_null()
+}
+
+// Implement structs
+impl Drop for RcuReadGuard {
+    fn drop(&mut self) {
+        let cpu = RCU_CPU.get();
+        if cpu.nesting.fetch_sub(1, Ordering::Relaxed) == 1 {
+            // Leaving the outermost section: the core becomes quiescent
+            cpu.gp.store(0, Ordering::Release);
+        }
+    }
+}
+
+impl<T: 'static> RcuCell<T> {
+    pub fn new(value: &'static mut T) -> Self {
+        Self {
+            ptr: AtomicPtr::new(value),
+        }
+    }
+
+    /// Returns the current value, valid for the read-side section.
+    pub fn read<'guard>(&self, _guard: &'guard RcuReadGuard) -> &'guard T {
+        // SAFETY: Replaced values are only reused after a grace period
+        unsafe { &*self.ptr.load(Ordering::Acquire) }
+    }
+
+    /// Publishes `value`, returning the previous one.
+    pub fn replace(&self, value: &'static mut T) -> Retired<T> {
+        let old = self.ptr.swap(value, Ordering::AcqRel);
+        // SAFETY: The old value came from a `&'static mut`
+        Retired { value: unsafe { NonNull::new_unchecked(old) } }
+    }
+}
+
+impl<T: 'static> Retired<T> {
+    /// Waits for the readers of the value to finish, then returns it.
+    pub fn synchronize(self) -> &'static mut T {
+        synchronize_rcu();
+        // SAFETY: The value is no longer published and its readers are gone
+        unsafe { &mut *self.value.as_ptr() }
+    }
+}
+
+impl RcuHead {
+    pub const fn new() -> Self {
+        Self {
+            next: AtomicPtr::new(ptr::null_mut()),
+            gp: AtomicUsize::new(0),
+            func: Cell::new(None),
+        }
+    }
+}
+
+// Implement thread safety
+// SAFETY: Readers only get shared references to 'static data
+unsafe impl<T: Sync> Sync for RcuCell<T> {}
+unsafe impl<T: Send> Send for RcuCell<T> {}
+// SAFETY: The value is exclusively owned once synchronized
+unsafe impl<T: Send> Send for Retired<T> {}
+// SAFETY: The callback is only written before publishing and taken by the processing core
+unsafe impl Sync for RcuHead {}
+
+// Define helpers
+fn start_grace_period() -> usize {
+    // Order the writer's updates before the new grace period
+    fence(Ordering::SeqCst);
+    GP_COUNTER.fetch_add(1, Ordering::SeqCst) + 1
+}
+
+fn grace_period_completed(target: usize) -> bool {
+    fence(Ordering::SeqCst);
+    (0..CORE_COUNT).all(|core| {
+        // SAFETY: The per-CPU state is only made of atomics
+        let gp = unsafe { RCU_CPU.remote(core) }.gp.load(Ordering::Acquire);
+        gp == 0 || gp >= target
+    })
+}
+
+fn push_pending(head: &'static RcuHead) {
+    let node = head as *const RcuHead as *mut RcuHead;
+    let mut top = PENDING.load(Ordering::Relaxed);
+    loop {
+        head.next.store(top, Ordering::Relaxed);
+        match PENDING.compare_exchange_weak(top, node, Ordering::Release, Ordering::Relaxed) {
+            Ok(_) => return,
+            Err(current) => top = current,
+        }
+    }
+}
@@ -0,0 +1,284 @@
+// Import dependencies
+use core::{cell::UnsafeCell, marker::PhantomData, mem::MaybeUninit, ptr, sync::atomic::{AtomicBool, AtomicUsize, Ordering}};
+
+/// Bounded single-producer single-consumer ring buffer.
+///
+/// Storage is inline (`N` must be a power of two), so rings can be
+/// statics usable before the allocator is up. Both sides are wait-free.
+pub struct SpscRing<T, const N: usize> {
+    /// Next slot to read (owned by the consumer)
+    head: AtomicUsize,
+    /// Next slot to write (owned by the producer)
+    tail: AtomicUsize,
+    buffer: UnsafeCell<[MaybeUninit<T>; N]>,
+    producer_taken: AtomicBool,
+    consumer_taken: AtomicBool,
+}
+
+/// Bounded multi-producer single-consumer ring buffer.
+///
+/// Producers may push from any core or IRQ handler; a push never waits
+/// for another producer.
+pub struct MpscRing<T, const N: usize> {
+    head: AtomicUsize,
+    tail: AtomicUsize,
+    slots: [Slot<T>; N],
+    consumer_taken: AtomicBool,
+}
+
+/// Slot of a [`MpscRing`].
+///
+/// The sequence is stored relative to the slot index, so a zeroed slot
+/// is a valid empty slot and the ring can be built in a `const` context.
+struct Slot<T> {
+    seq: AtomicUsize,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+pub struct Producer<'ring, T, const N: usize> {
+    ring: &'ring SpscRing<T, N>,
+    _not_sync: PhantomData<*mut T>,
+}
+
+pub struct Consumer<'ring, T, const N: usize> {
+    ring: &'ring SpscRing<T, N>,
+    _not_sync: PhantomData<*mut T>,
+}
+
+pub struct MpscConsumer<'ring, T, const N: usize> {
+    ring: &'ring MpscRing<T, N>,
+    _not_sync: PhantomData<*mut T>,
+}
+
+// Implement structs
+impl<T, const N: usize> SpscRing<T, N> {
+    pub const fn new() -> Self {
+        assert!(N.is_power_of_two(), "Ring capacity must be a power of two");
+        Self {
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+            // SAFETY: An array of MaybeUninit needs no initialization
+            buffer: UnsafeCell::new(unsafe { MaybeUninit::uninit().assume_init() }),
+            producer_taken: AtomicBool::new(false),
+            consumer_taken: AtomicBool::new(false),
+        }
+    }
+
+    /// Splits the ring into its two endpoints.
+    ///
+    /// Endpoints give their claim back when dropped, so the ring can be split again.
+    pub fn split(&mut self) -> (Producer<'_, T, N>, Consumer<'_, T, N>) {
+        let ring: &Self = self;
+        (ring.take_producer().unwrap(), ring.take_consumer().unwrap())
+    }
+
+    /// Claims the producer endpoint (fails while it is taken).
+    pub fn take_producer(&self) -> Option<Producer<'_, T, N>> {
+        (!self.producer_taken.swap(true, Ordering::AcqRel)).then(|| Producer {
+            ring: self,
+            _not_sync: PhantomData,
+        })
+    }
+
+    /// Claims the consumer endpoint (fails while it is taken).
+    pub fn take_consumer(&self) -> Option<Consumer<'_, T, N>> {
+        (!self.consumer_taken.swap(true, Ordering::AcqRel)).then(|| Consumer {
+            ring: self,
+            _not_sync: PhantomData,
+        })
+    }
+
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    pub fn len(&self) -> usize {
+        self.tail.load(Ordering::Acquire).wrapping_sub(self.head.load(Ordering::Acquire))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn slot(&self, index: usize) -> *mut MaybeUninit<T> {
+        // SAFETY: Masked index is in bounds
+        unsafe { (self.buffer.get() as *mut MaybeUninit<T>).add(index & (N - 1)) }
+    }
+}
+
+impl<T, const N: usize> Producer<'_, T, N> {
+    /// Appends `value`, giving it back if the ring is full.
+    pub fn push(&mut self, value: T) -> Result<(), T> {
+        let tail = self.ring.tail.load(Ordering::Relaxed);
+        if tail.wrapping_sub(self.ring.head.load(Ordering::Acquire)) == N {
+            return Err(value);
+        }
+        // SAFETY: The slot is free and only the producer writes it
+        unsafe { (*self.ring.slot(tail)).write(value) };
+        self.ring.tail.store(tail.wrapping_add(1), Ordering::Release);
+        Ok(())
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.ring.len() == N
+    }
+}
+
+impl<T, const N: usize> Consumer<'_, T, N> {
+    /// Removes the oldest value.
+    pub fn pop(&mut self) -> Option<T> {
+        let head = self.ring.head.load(Ordering::Relaxed);
+        if head == self.ring.tail.load(Ordering::Acquire) {
+            return None;
+        }
+        // SAFETY: The slot was published by the producer
+        let value = unsafe { (*self.ring.slot(head)).assume_init_read() };
+        self.ring.head.store(head.wrapping_add(1), Ordering::Release);
+        Some(value)
+    }
+
+    /// Returns a reference to the oldest value without removing it.
+    pub fn peek(&self) -> Option<&T> {
+        let head = self.ring.head.load(Ordering::Relaxed);
+        if head == self.ring.tail.load(Ordering::Acquire) {
+            return None;
+        }
+        // SAFETY: The slot was published and only the consumer frees it
+        Some(unsafe { (*self.ring.slot(head)).assume_init_ref() })
+    }
+}
+
+impl<T, const N: usize> Drop for Producer<'_, T, N> {
+    fn drop(&mut self) {
+        self.ring.producer_taken.store(false, Ordering::Release);
+    }
+}
+
+impl<T, const N: usize> Drop for Consumer<'_, T, N> {
+    fn drop(&mut self) {
+        self.ring.consumer_taken.store(false, Ordering::Release);
+    }
+}
+
+impl<T, const N: usize> Drop for SpscRing<T, N> {
+    fn drop(&mut self) {
+        let tail = *self.tail.get_mut();
+        let mut head = *self.head.get_mut();
+        while head != tail {
+            // SAFETY: Values between head and tail are initialized
+            unsafe { ptr::drop_in_place((*self.slot(head)).as_mut_ptr()) };
+            head = head.wrapping_add(1);
+        }
+    }
+}
+
+impl<T, const N: usize> MpscRing<T, N> {
+    pub const fn new() -> Self {
+        assert!(N.is_power_of_two(), "Ring capacity must be a power of two");
+        Self {
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+            // SAFETY: Zeroed slots are empty slots (relative sequence 0)
+            slots: unsafe { MaybeUninit::zeroed().assume_init() },
+            consumer_taken: AtomicBool::new(false),
+        }
+    }
+
+    /// Appends `value` from any core, giving it back if the ring is full.
+    pub fn push(&self, value: T) -> Result<(), T> {
+        let mut pos = self.tail.load(Ordering::Relaxed);
+        loop {
+            let slot = self.slot(pos);
+            let seq = slot.sequence(pos & (N - 1));
+            match seq.wrapping_sub(pos) as isize {
+                // Free slot: try to claim it
+                0 => match self.tail.compare_exchange_weak(pos, pos.wrapping_add(1), Ordering::Relaxed, Ordering::Relaxed) {
+                    Ok(_) => {
+                        // SAFETY: The slot was claimed by this producer
+                        unsafe { (*slot.value.get()).write(value) };
+                        slot.publish(pos & (N - 1), pos.wrapping_add(1));
+                        return Ok(());
+                    }
+                    Err(current) => pos = current,
+                },
+                // Slot still holds a value from the previous lap
+                diff if diff < 0 => return Err(value),
+                // Another producer claimed it, reload the tail
+                _ => pos = self.tail.load(Ordering::Relaxed),
+            }
+        }
+    }
+
+    /// Claims the consumer endpoint (fails while it is taken).
+    pub fn take_consumer(&self) -> Option<MpscConsumer<'_, T, N>> {
+        (!self.consumer_taken.swap(true, Ordering::AcqRel)).then(|| MpscConsumer {
+            ring: self,
+            _not_sync: PhantomData,
+        })
+    }
+
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    fn slot(&self, pos: usize) -> &Slot<T> {
+        &self.slots[pos & (N - 1)]
+    }
+}
+
+impl<T, const N: usize> MpscConsumer<'_, T, N> {
+    /// Removes the oldest value.
+    ///
+    /// May return `None` while an earlier producer has not finished its push.
+    pub fn pop(&mut self) -> Option<T> {
+        let pos = self.ring.head.load(Ordering::Relaxed);
+        let slot = self.ring.slot(pos);
+        if slot.sequence(pos & (N - 1)) != pos.wrapping_add(1) {
+            return None;
+        }
+        // SAFETY: The slot was published by its producer
+        let value = unsafe { (*slot.value.get()).assume_init_read() };
+        // Free the slot for the next lap
+        slot.publish(pos & (N - 1), pos.wrapping_add(N));
+        self.ring.head.store(pos.wrapping_add(1), Ordering::Relaxed);
+        Some(value)
+    }
+}
+
+impl<T, const N: usize> Drop for MpscConsumer<'_, T, N> {
+    fn drop(&mut self) {
+        self.ring.consumer_taken.store(false, Ordering::Release);
+    }
+}
+
+impl<T, const N: usize> Drop for MpscRing<T, N> {
+    fn drop(&mut self) {
+        let mut consumer = MpscConsumer {
+            ring: &*self,
+            _not_sync: PhantomData,
+        };
+        while consumer.pop().is_some() {}
+    }
+}
+
+impl<T> Slot<T> {
+    /// Absolute sequence of the slot at `index`
+    fn sequence(&self, index: usize) -> usize {
+        self.seq.load(Ordering::Acquire).wrapping_add(index)
+    }
+
+    fn publish(&self, index: usize, seq: usize) {
+        self.seq.store(seq.wrapping_sub(index), Ordering::Release)
+    }
+}
+
+// Implement thread safety
+// SAFETY: Endpoints are unique, so each index has a single writer
+unsafe impl<T: Send, const N: usize> Sync for SpscRing<T, N> {}
+unsafe impl<T: Send, const N: usize> Send for SpscRing<T, N> {}
+unsafe impl<T: Send, const N: usize> Send for Producer<'_, T, N> {}
+unsafe impl<T: Send, const N: usize> Send for Consumer<'_, T, N> {}
+// SAFETY: Slots are claimed through the tail before being written
+unsafe impl<T: Send, const N: usize> Sync for MpscRing<T, N> {}
+unsafe impl<T: Send, const N: usize> Send for MpscRing<T, N> {}
+unsafe impl<T: Send, const N: usize> Send for MpscConsumer<'_, T, N> {}
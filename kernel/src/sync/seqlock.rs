@@ -0,0 +1,103 @@
+// Import dependencies
+use core::{cell::UnsafeCell, hint::spin_loop, ops::{Deref, DerefMut}, ptr, sync::atomic::{fence, AtomicUsize, Ordering}};
+use crate::arch::cpu::IrqGuard;
+use super::spin::{Spinlock, SpinlockGuard};
+
+/// Sequence lock for small `Copy` data that is read much more often than written.
+///
+/// Readers never write shared memory: they copy the data and retry if a
+/// writer was active meanwhile. Writers are serialized by a spinlock.
+#[repr(C)]
+pub struct SeqLock<T: Copy> {
+    seq: AtomicUsize,
+    data: UnsafeCell<T>,
+    writer: Spinlock<()>,
+}
+
+pub struct SeqLockWriteGuard<'lock, T: Copy> {
+    lock: &'lock SeqLock<T>,
+    seq: usize,
+    // Fields are dropped in order: release the writer lock, then unmask IRQs
+    _writer: SpinlockGuard<'lock, ()>,
+    _irq: IrqGuard,
+}
+
+// Implement structs
+impl<T: Copy> SeqLock<T> {
+    pub const fn new(value: T) -> Self {
+        Self {
+            seq: AtomicUsize::new(0),
+            data: UnsafeCell::new(value),
+            writer: Spinlock::new(()),
+        }
+    }
+
+    /// Returns a consistent copy of the data.
+    pub fn read(&self) -> T {
+        loop {
+            // Odd sequence means a write in progress
+            let seq = self.seq.load(Ordering::Acquire);
+            if seq & 1 != 0 {
+                spin_loop();
+                continue;
+            }
+            // SAFETY: Torn values are discarded by the sequence check
+            let value = unsafe { ptr::read_volatile(self.data.get()) };
+            fence(Ordering::Acquire);
+            if self.seq.load(Ordering::Relaxed) == seq {
+                return value;
+            }
+        }
+    }
+
+    /// Starts a write, blocking other writers.
+    ///
+    /// IRQs are masked while writing, so readers on the same core cannot spin forever.
+    pub fn write(&self) -> SeqLockWriteGuard<'_, T> {
+        let irq = IrqGuard::new();
+        let writer = self.writer.lock();
+        let seq = self.seq.load(Ordering::Relaxed).wrapping_add(1);
+        self.seq.store(seq, Ordering::Relaxed);
+        // Order the odd sequence before the data writes
+        fence(Ordering::Release);
+        SeqLockWriteGuard {
+            lock: self,
+            seq,
+            _writer: writer,
+            _irq: irq,
+        }
+    }
+
+    /// Replaces the data.
+    pub fn set(&self, value: T) {
+        *self.write() = value;
+    }
+}
+
+impl<T: Copy> Drop for SeqLockWriteGuard<'_, T> {
+    fn drop(&mut self) {
+        // Publish the data writes with an even sequence
+        self.lock.seq.store(self.seq.wrapping_add(1), Ordering::Release);
+    }
+}
+
+impl<T: Copy> Deref for SeqLockWriteGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        // SAFETY: Writers are serialized
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T: Copy> DerefMut for SeqLockWriteGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: Writers are serialized
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+// Implement thread safety
+// SAFETY: Readers only copy the data, writers are serialized
+unsafe impl<T: Copy + Send> Sync for SeqLock<T> {}
+unsafe impl<T: Copy + Send> Send for SeqLock<T> {}
@@ -1,5 +1,8 @@
 // Import dependencies
-use core::{cell::UnsafeCell, sync::atomic::{Ordering, AtomicBool}, hint::spin_loop, ops::{Deref, DerefMut}};
+use core::{cell::UnsafeCell, fmt, sync::atomic::{Ordering, AtomicBool}, ops::{Deref, DerefMut}};
+#[cfg(feature = "lock-stats")]
+use core::sync::atomic::AtomicU64;
+use crate::arch::cpu;
 
 /// Spinslocks should be only used in Low-Level environments,
 /// so, it should be FFI-compatible, implementing #[repr(C)]
@@ -7,44 +10,172 @@ use core::{cell::UnsafeCell, sync::atomic::{Ordering, AtomicBool}, hint::spin_lo
 pub struct Spinlock<T> {
     data: UnsafeCell<T>,
     lock: AtomicBool,
+    #[cfg(feature = "lock-stats")]
+    stats: LockStats,
 }
 
 pub struct SpinlockGuard<'lock, T> {
     spin: &'lock Spinlock<T>,
 }
 
+/// Contention counters of a single lock (`lock-stats` feature)
+#[cfg(feature = "lock-stats")]
+#[repr(C)]
+struct LockStats {
+    acquisitions: AtomicU64,
+    contended: AtomicU64,
+    spin_ticks: AtomicU64,
+}
+
+/// Copy of the counters of a lock, printable for profiling
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LockStatsSnapshot {
+    pub acquisitions: u64,
+    pub contended: u64,
+    /// Time spent waiting, in counter ticks
+    pub spin_ticks: u64,
+}
+
 // Implement structs
 impl<T> Spinlock<T> {
     pub const fn new(value: T) -> Self {
         Self {
             data: UnsafeCell::new(value),
             lock: AtomicBool::new(false),
+            #[cfg(feature = "lock-stats")]
+            stats: LockStats::new(),
         }
     }
 
     pub fn lock(&self) -> SpinlockGuard<'_, T> {
-        // Use weak version, because we already in a loop (non-weak runs on a loop)
-        while self.lock.compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed).is_err() {
-            // MESI Protocol: Cores should use shared state (read-only)
-            // while waiting for the lock to release in order to use less resources.
-            while self.lock.load(Ordering::Relaxed) == false {
-                spin_loop()
-            }
+        if !self.try_acquire() {
+            self.lock_contended();
         }
+        #[cfg(feature = "lock-stats")]
+        self.stats.acquisitions.fetch_add(1, Ordering::Relaxed);
         // We have locked the atomic value (memory aquired)
         SpinlockGuard {
             spin: self
         }
     }
+
+    pub fn try_lock(&self) -> Option<SpinlockGuard<'_, T>> {
+        if self.try_acquire() {
+            #[cfg(feature = "lock-stats")]
+            self.stats.acquisitions.fetch_add(1, Ordering::Relaxed);
+            Some(SpinlockGuard { spin: self })
+        } else {
+            None
+        }
+    }
+
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+
+    /// Returns the contention counters (all zero without the `lock-stats` feature).
+    pub fn stats(&self) -> LockStatsSnapshot {
+        #[cfg(feature = "lock-stats")]
+        return self.stats.snapshot();
+        #[cfg(not(feature = "lock-stats"))]
+        return LockStatsSnapshot::default();
+    }
+
+    pub fn reset_stats(&self) {
+        #[cfg(feature = "lock-stats")]
+        self.stats.reset();
+    }
+
+    #[cold]
+    fn lock_contended(&self) {
+        #[cfg(feature = "lock-stats")]
+        let start = cpu::counter();
+        loop {
+            // MESI Protocol: Cores should use shared state (read-only)
+            // while waiting for the lock to release in order to use less resources.
+            // The core sleeps until the owner's releasing store clears the exclusive monitor.
+            unsafe { cpu::wait_while_set(self.lock_ptr()) };
+            if self.try_acquire() {
+                break;
+            }
+        }
+        #[cfg(feature = "lock-stats")]
+        {
+            self.stats.contended.fetch_add(1, Ordering::Relaxed);
+            self.stats.spin_ticks.fetch_add(cpu::counter().wrapping_sub(start), Ordering::Relaxed);
+        }
+    }
+
+    #[inline(always)]
+    fn try_acquire(&self) -> bool {
+        #[cfg(feature = "lse")]
+        if cpu::has_lse() {
+            // SAFETY: LSE support checked above
+            return unsafe { cpu::swap_acquire_u8(self.lock_ptr(), true as u8) } == 0;
+        }
+        self.lock.compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed).is_ok()
+    }
+
+    fn lock_ptr(&self) -> *mut u8 {
+        &self.lock as *const AtomicBool as *mut u8
+    }
 }
 
 impl<'lock, T> Drop for SpinlockGuard<'lock, T> {
     fn drop(&mut self) {
         // Write 0 to the lock status and release the memory atomic value
+        // (the store also wakes up the cores waiting in `wfe`)
         self.spin.lock.store(false, Ordering::Release)
     }
 }
 
+#[cfg(feature = "lock-stats")]
+impl LockStats {
+    const fn new() -> Self {
+        Self {
+            acquisitions: AtomicU64::new(0),
+            contended: AtomicU64::new(0),
+            spin_ticks: AtomicU64::new(0),
+        }
+    }
+
+    fn snapshot(&self) -> LockStatsSnapshot {
+        LockStatsSnapshot {
+            acquisitions: self.acquisitions.load(Ordering::Relaxed),
+            contended: self.contended.load(Ordering::Relaxed),
+            spin_ticks: self.spin_ticks.load(Ordering::Relaxed),
+        }
+    }
+
+    fn reset(&self) {
+        self.acquisitions.store(0, Ordering::Relaxed);
+        self.contended.store(0, Ordering::Relaxed);
+        self.spin_ticks.store(0, Ordering::Relaxed);
+    }
+}
+
+impl LockStatsSnapshot {
+    /// Total spin time, in microseconds
+    pub fn spin_time_us(&self) -> u64 {
+        match cpu::counter_frequency() {
+            0 => 0,
+            frequency => (self.spin_ticks as u128 * 1_000_000 / frequency as u128) as u64,
+        }
+    }
+}
+
+impl fmt::Display for LockStatsSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "acquisitions: {}, contended: {}, spin time: {} us",
+            self.acquisitions,
+            self.contended,
+            self.spin_time_us()
+        )
+    }
+}
+
 
 // Implement transparency for the locked value
 // SAFETY: (lifetime garentees the value to exist)
@@ -65,4 +196,9 @@ impl<T> DerefMut for SpinlockGuard<'_, T> {
         // Return a reference to the underlying data
         unsafe { &mut *self.spin.data.get() }
     }
-}
\ No newline at end of file
+}
+
+// Implement thread safety
+// SAFETY: The lock serializes all accesses to the data
+unsafe impl<T: Send> Sync for Spinlock<T> {}
+unsafe impl<T: Send> Send for Spinlock<T> {}
@@ -0,0 +1,158 @@
+// Import dependencies
+use core::{future::Future, mem, pin::Pin, task::{Context, Poll, Waker}, time::Duration};
+use crate::arch::cpu::IrqGuard;
+use crate::sync::spin::Spinlock;
+use super::{executor::MAX_TASKS, wait::WaitQueue};
+
+/// Event that both threads and async tasks can wait for.
+///
+/// Threads block with `wait_until`, tasks await `until`; `notify_one`
+/// and `notify_all` (callable from IRQ handlers) wake both kinds.
+pub struct Event {
+    threads: WaitQueue,
+    /// Wakers registered by `until`
+    tasks: Spinlock<WakerSet<MAX_TASKS>>,
+}
+
+/// Wakers of the futures waiting for something, each registered once.
+///
+/// When full, the oldest waker is woken to make room: its future polls
+/// again and registers anew, so no wake-up is lost.
+pub(super) struct WakerSet<const N: usize> {
+    wakers: [Option<Waker>; N],
+    /// Next slot to evict when full
+    evict: usize,
+}
+
+/// Future returned by [`Event::until`]
+#[must_use = "futures do nothing unless awaited"]
+pub struct EventWait<'event, C> {
+    event: &'event Event,
+    condition: C,
+}
+
+// Implement structs
+impl Event {
+    pub const fn new() -> Self {
+        Self {
+            threads: WaitQueue::new(),
+            tasks: Spinlock::new(WakerSet::new()),
+        }
+    }
+
+    /// Blocks the calling thread until `condition` returns true.
+    pub fn wait_until(&self, condition: impl FnMut() -> bool) {
+        self.threads.wait_until(condition)
+    }
+
+    /// Like `wait_until`, giving up after `timeout`.
+    ///
+    /// Returns whether the condition became true.
+    pub fn wait_until_timeout(&self, condition: impl FnMut() -> bool, timeout: Duration) -> bool {
+        self.threads.wait_until_timeout(condition, timeout)
+    }
+
+    /// Resolves once `condition` returns true.
+    pub fn until<C: FnMut() -> bool>(&self, condition: C) -> EventWait<'_, C> {
+        EventWait { event: self, condition }
+    }
+
+    /// Wakes up one waiting thread, or every waiting task if there is none.
+    ///
+    /// Tasks re-check their condition when polled, so waking them all is
+    /// only a few extra polls. Returns whether anyone was woken up.
+    pub fn notify_one(&self) -> bool {
+        self.threads.notify_one() || self.wake_tasks() != 0
+    }
+
+    /// Wakes up every waiter, returning how many there were.
+    pub fn notify_all(&self) -> usize {
+        self.threads.notify_all() + self.wake_tasks()
+    }
+
+    pub fn has_waiters(&self) -> bool {
+        let _irq = IrqGuard::new();
+        self.threads.has_waiters() || !self.tasks.lock().is_empty()
+    }
+
+    /// Registers `waker`, to be woken by the next notification.
+    pub(super) fn register(&self, waker: &Waker) {
+        let evicted = {
+            let _irq = IrqGuard::new();
+            self.tasks.lock().insert(waker)
+        };
+        if let Some(evicted) = evicted {
+            evicted.wake();
+        }
+    }
+
+    fn wake_tasks(&self) -> usize {
+        let tasks = {
+            let _irq = IrqGuard::new();
+            self.tasks.lock().take()
+        };
+        tasks.wake_all()
+    }
+}
+
+impl<const N: usize> WakerSet<N> {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const NONE: Option<Waker> = None;
+
+    pub(super) const fn new() -> Self {
+        Self {
+            wakers: [Self::NONE; N],
+            evict: 0,
+        }
+    }
+
+    pub(super) fn is_empty(&self) -> bool {
+        self.wakers.iter().all(Option::is_none)
+    }
+
+    /// Adds `waker` unless it is already registered, returning the waker evicted to make room.
+    pub(super) fn insert(&mut self, waker: &Waker) -> Option<Waker> {
+        if self.wakers.iter().flatten().any(|registered| registered.will_wake(waker)) {
+            return None;
+        }
+        if let Some(free) = self.wakers.iter_mut().find(|slot| slot.is_none()) {
+            *free = Some(waker.clone());
+            return None;
+        }
+        let evicted = self.wakers[self.evict].replace(waker.clone());
+        self.evict = (self.evict + 1) % N;
+        evicted
+    }
+
+    /// Removes every waker, to wake them once the lock is released.
+    pub(super) fn take(&mut self) -> Self {
+        Self {
+            wakers: mem::replace(&mut self.wakers, [Self::NONE; N]),
+            evict: 0,
+        }
+    }
+
+    /// Wakes every waker, returning how many there were.
+    pub(super) fn wake_all(self) -> usize {
+        self.wakers.into_iter().flatten().map(Waker::wake).count()
+    }
+}
+
+impl<C: FnMut() -> bool + Unpin> Future for EventWait<'_, C> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let this = self.get_mut();
+        if (this.condition)() {
+            return Poll::Ready(());
+        }
+        // Registrations left behind (the future resolved or was dropped) only cause a spurious poll
+        this.event.register(cx.waker());
+        // Re-check, a notification may have been sent before registering
+        if (this.condition)() {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
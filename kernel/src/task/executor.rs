@@ -0,0 +1,458 @@
+// Import dependencies
+use core::{cell::UnsafeCell, future::Future, mem, pin::Pin, ptr, sync::atomic::{AtomicU32, AtomicU64, AtomicU8, AtomicUsize, Ordering}, task::{Context, Poll, RawWaker, RawWakerVTable, Waker}, time::Duration};
+use crate::arch::cpu::{self, IrqGuard, CORE_COUNT};
+use crate::sync::{mpsc::{MpscLink, MpscNode, MpscQueue}, once::Once, spin::Spinlock};
+use crate::time::{Instant, Timer};
+use super::{event::{Event, WakerSet}, scheduler::{self, CpuMask}, thread, wait::WaitQueue};
+
+// Define constants
+/// Maximum number of async tasks (on all cores)
+pub const MAX_TASKS: usize = 64;
+/// Largest future a task can hold, in bytes
+pub const TASK_SIZE: usize = 1024;
+const TASK_ALIGNMENT: usize = 16;
+const NO_TASK: usize = usize::MAX;
+const NO_THREAD: usize = usize::MAX;
+/// Task state bits
+const SPAWNED: u32 = 1 << 0;
+/// In the ready queue of its executor (or about to be pushed there)
+const QUEUED: u32 = 1 << 1;
+const RUNNING: u32 = 1 << 2;
+/// The future returned, `output` is valid
+const COMPLETE: u32 = 1 << 3;
+/// The handle was dropped, the slot is released on completion
+const DETACHED: u32 = 1 << 4;
+/// The state bits above count the reuses of the slot, so stale wakers are ignored
+const GENERATION_SHIFT: u32 = 8;
+const FLAGS_MASK: u32 = (1 << GENERATION_SHIFT) - 1;
+/// Distinct wakers of the `sleep` futures of a task
+const SLEEP_WAKERS: usize = 4;
+
+// Check configuration
+const _: () = assert!(MAX_TASKS <= 1 << GENERATION_SHIFT, "Waker tokens hold the task index below the generation");
+
+// Define structs
+type PollFn = unsafe fn(*mut u8, &mut Context<'_>) -> Poll<usize>;
+type DropFn = unsafe fn(*mut u8);
+
+/// Async task slot, holding its future inline
+#[repr(C)]
+struct Task {
+    link: MpscLink,
+    /// State bits, under the generation of the slot
+    state: AtomicU32,
+    /// Executor running the task
+    core: AtomicU8,
+    // Only accessed by the spawner (before queuing) and the executor
+    poll: UnsafeCell<Option<PollFn>>,
+    drop: UnsafeCell<Option<DropFn>>,
+    future: UnsafeCell<Storage>,
+    output: AtomicUsize,
+    /// Threads and tasks waiting for completion
+    joiners: Event,
+    /// Fires at the earliest deadline of the `sleep` futures of the task
+    timer: Timer,
+    /// Deadline the timer is armed for (counter value)
+    timer_deadline: AtomicU64,
+    /// Woken when the timer fires
+    sleepers: Spinlock<WakerSet<SLEEP_WAKERS>>,
+}
+
+#[repr(C, align(16))]
+struct Storage([u8; TASK_SIZE]);
+
+/// Per-core executor, polling its tasks from a dedicated kernel thread
+struct Executor {
+    ready: MpscQueue<Task>,
+    /// Tasks pushed to `ready` and not popped yet
+    queued: AtomicUsize,
+    idle: WaitQueue,
+    started: Once,
+    /// Thread running the executor
+    thread: AtomicUsize,
+    /// Index of the task being polled
+    current: AtomicUsize,
+}
+
+/// Owned permission to join a task (dropping it detaches the task).
+///
+/// Threads join with `join`, tasks await the handle.
+#[must_use = "dropping the handle detaches the task"]
+pub struct TaskHandle {
+    index: usize,
+    generation: u32,
+    joined: bool,
+}
+
+/// Future returned by [`sleep`] and [`sleep_until`]
+#[must_use = "futures do nothing unless awaited"]
+pub struct Sleep {
+    deadline: Instant,
+}
+
+// Define globals
+static TASKS: [Task; MAX_TASKS] = [Task::EMPTY; MAX_TASKS];
+/// Executors, indexed by `cpu::core_id()`
+static EXECUTORS: [Executor; CORE_COUNT] = [Executor::EMPTY; CORE_COUNT];
+static WAKER_VTABLE: RawWakerVTable = RawWakerVTable::new(clone_waker, wake_waker, wake_waker, drop_waker);
+
+// Define procedures
+/// Spawns an async task on the executor of the calling core.
+///
+/// The future is stored in a fixed task slot, so no allocation is needed.
+/// Its output plays the role of a thread exit code.
+///
+/// # Panics
+///
+/// Panics if all task slots are in use or the future is larger than `TASK_SIZE`.
+pub fn spawn<F>(future: F) -> TaskHandle
+where
+    F: Future<Output = usize> + Send + 'static,
+{
+    spawn_on(cpu::this_core(), future)
+}
+
+/// Spawns an async task on the executor of `core`.
+///
+/// Tasks stay on their executor, so wakers and timers always target the same core.
+pub fn spawn_on<F>(core: usize, future: F) -> TaskHandle
+where
+    F: Future<Output = usize> + Send + 'static,
+{
+    assert!(core < CORE_COUNT, "Invalid core {}", core);
+    assert!(mem::size_of::<F>() <= TASK_SIZE && mem::align_of::<F>() <= TASK_ALIGNMENT, "Task future too large");
+    let (index, generation) = allocate().expect("No free task slot");
+    let task = &TASKS[index];
+    // SAFETY: The slot is owned by this call until it is queued
+    unsafe {
+        task.future.get().cast::<F>().write(future);
+        *task.poll.get() = Some(poll_future::<F>);
+        *task.drop.get() = Some(drop_future::<F>);
+    }
+    task.core.store(core as u8, Ordering::Relaxed);
+    task.output.store(0, Ordering::Relaxed);
+    task.timer.set_data(index);
+    task.timer_deadline.store(u64::MAX, Ordering::Relaxed);
+    let executor = &EXECUTORS[core];
+    executor.start(core);
+    executor.push(task);
+    TaskHandle { index, generation, joined: false }
+}
+
+/// Completes after `duration` (when awaited inside a kernel executor task).
+pub fn sleep(duration: Duration) -> Sleep {
+    sleep_until(Instant::now() + duration)
+}
+
+/// Completes once `deadline` has passed (when awaited inside a kernel executor task).
+pub fn sleep_until(deadline: Instant) -> Sleep {
+    Sleep { deadline }
+}
+
+/// Index of the task being polled by the calling thread.
+///
+/// # Panics
+///
+/// Panics if the calling thread is not an executor.
+pub(super) fn current_task_index() -> usize {
+    let thread = thread::current().as_usize();
+    EXECUTORS
+        .iter()
+        .find(|executor| executor.thread.load(Ordering::Relaxed) == thread)
+        .map(|executor| executor.current.load(Ordering::Relaxed))
+        .filter(|&index| index != NO_TASK)
+        .expect("Not polled by a kernel executor")
+}
+
+// Implement structs
+impl Task {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const EMPTY: Task = Task {
+        link: MpscLink::new(),
+        state: AtomicU32::new(0),
+        core: AtomicU8::new(0),
+        poll: UnsafeCell::new(None),
+        drop: UnsafeCell::new(None),
+        future: UnsafeCell::new(Storage([0; TASK_SIZE])),
+        output: AtomicUsize::new(0),
+        joiners: Event::new(),
+        timer: Timer::new(wake_sleepers, 0),
+        timer_deadline: AtomicU64::new(u64::MAX),
+        sleepers: Spinlock::new(WakerSet::new()),
+    };
+
+    fn index(&self) -> usize {
+        // SAFETY: Tasks only live in `TASKS`
+        unsafe { (self as *const Task).offset_from(TASKS.as_ptr()) as usize }
+    }
+
+    fn generation(&self) -> u32 {
+        self.state.load(Ordering::Acquire) >> GENERATION_SHIFT
+    }
+
+    /// Queues the task for a poll, unless it is already queued or complete,
+    /// or the slot was reused since `generation`.
+    fn wake(&'static self, generation: u32) {
+        let queued = self.state.fetch_update(Ordering::AcqRel, Ordering::Acquire, |state| {
+            let current = state >> GENERATION_SHIFT == generation;
+            (current && state & SPAWNED != 0 && state & (QUEUED | COMPLETE) == 0).then_some(state | QUEUED)
+        });
+        // A running task is pushed again by its executor after the poll
+        if matches!(queued, Ok(state) if state & RUNNING == 0) {
+            EXECUTORS[self.core.load(Ordering::Relaxed) as usize].push(self);
+        }
+    }
+
+    fn waker(&'static self) -> Waker {
+        // SAFETY: The vtable functions take a waker token
+        unsafe { Waker::from_raw(RawWaker::new(ptr::invalid(waker_token(self.index(), self.generation())), &WAKER_VTABLE)) }
+    }
+
+    /// Wakes `waker` when the timer fires, at `deadline` or earlier.
+    fn sleep(&'static self, waker: &Waker, deadline: Instant) {
+        let evicted = {
+            let _irq = IrqGuard::new();
+            self.sleepers.lock().insert(waker)
+        };
+        if let Some(evicted) = evicted {
+            evicted.wake();
+        }
+        self.arm_timer(deadline);
+    }
+
+    /// Makes the timer fire at `deadline` or earlier.
+    fn arm_timer(&'static self, deadline: Instant) {
+        // A fired timer is re-armed, an earlier pending one is kept
+        if !self.timer.is_pending() || deadline.ticks() < self.timer_deadline.load(Ordering::Relaxed) {
+            self.timer_deadline.store(deadline.ticks(), Ordering::Relaxed);
+            self.timer.start_at(deadline);
+        }
+    }
+
+    /// Drops the future and publishes its output.
+    fn complete(&'static self, output: usize) {
+        // SAFETY: Set by `spawn_on`, the future is not polled anymore
+        unsafe {
+            let drop_future = (*self.drop.get()).take().expect("Task without a future");
+            drop_future(self.future.get().cast());
+            *self.poll.get() = None;
+        }
+        self.timer.cancel();
+        // Dropped without waking: the futures they belong to are gone
+        drop({
+            let _irq = IrqGuard::new();
+            self.sleepers.lock().take()
+        });
+        self.output.store(output, Ordering::Relaxed);
+        let previous = self.state.fetch_update(Ordering::AcqRel, Ordering::Acquire, |state| Some((state & !FLAGS_MASK) | SPAWNED | COMPLETE)).unwrap();
+        if previous & DETACHED != 0 {
+            self.release();
+        } else {
+            self.joiners.notify_all();
+        }
+    }
+
+    /// Frees the slot, moving it to the next generation.
+    fn release(&self) {
+        let generation = self.generation().wrapping_add(1);
+        self.state.store(generation << GENERATION_SHIFT, Ordering::Release)
+    }
+}
+
+impl Executor {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const EMPTY: Executor = Executor {
+        ready: MpscQueue::new(),
+        queued: AtomicUsize::new(0),
+        idle: WaitQueue::new(),
+        started: Once::new(),
+        thread: AtomicUsize::new(NO_THREAD),
+        current: AtomicUsize::new(NO_TASK),
+    };
+
+    /// Spawns the executor thread of `core` on first use.
+    fn start(&'static self, core: usize) {
+        self.started.call_once(|| {
+            // The executor runs forever, nobody joins it
+            drop(thread::spawn(move || run(core)));
+        })
+    }
+
+    fn push(&self, task: &'static Task) {
+        self.ready.push(task);
+        self.queued.fetch_add(1, Ordering::Release);
+        self.idle.notify_one();
+    }
+
+    fn poll(&self, task: &'static Task) {
+        // Wake-ups from now on are handled after the poll
+        let _ = task.state.fetch_update(Ordering::AcqRel, Ordering::Acquire, |state| Some((state & !QUEUED) | RUNNING));
+        self.current.store(task.index(), Ordering::Relaxed);
+        let waker = task.waker();
+        let mut context = Context::from_waker(&waker);
+        // SAFETY: Set by `spawn_on`, the future stays in place until dropped
+        let poll = unsafe {
+            let poll_future = (*task.poll.get()).expect("Task without a future");
+            poll_future(task.future.get().cast(), &mut context)
+        };
+        self.current.store(NO_TASK, Ordering::Relaxed);
+        match poll {
+            Poll::Ready(output) => task.complete(output),
+            Poll::Pending => {
+                // Woken while running: queue it again
+                if task.state.fetch_and(!RUNNING, Ordering::AcqRel) & QUEUED != 0 {
+                    self.push(task);
+                }
+            }
+        }
+    }
+}
+
+impl TaskHandle {
+    pub fn is_finished(&self) -> bool {
+        self.task().state.load(Ordering::Acquire) & COMPLETE != 0
+    }
+
+    /// Blocks the calling thread until the task completes and returns its output.
+    pub fn join(mut self) -> usize {
+        let task = self.task();
+        task.joiners.wait_until(|| self.is_finished());
+        self.collect()
+    }
+
+    /// Like `join`, giving the handle back if the task is still running after `timeout`.
+    pub fn join_timeout(mut self, timeout: Duration) -> Result<usize, TaskHandle> {
+        let task = self.task();
+        if task.joiners.wait_until_timeout(|| self.is_finished(), timeout) {
+            Ok(self.collect())
+        } else {
+            Err(self)
+        }
+    }
+
+    fn task(&self) -> &'static Task {
+        let task = &TASKS[self.index];
+        debug_assert!(task.generation() == self.generation, "Stale task handle");
+        task
+    }
+
+    /// Takes the output of the completed task and releases its slot.
+    fn collect(&mut self) -> usize {
+        let task = self.task();
+        let output = task.output.load(Ordering::Relaxed);
+        task.release();
+        self.joined = true;
+        output
+    }
+}
+
+impl Future for TaskHandle {
+    type Output = usize;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<usize> {
+        let this = self.get_mut();
+        assert!(!this.joined, "Task handle polled after completion");
+        let task = this.task();
+        if !this.is_finished() {
+            task.joiners.register(cx.waker());
+            // Re-check, the task may have completed before registering
+            if !this.is_finished() {
+                return Poll::Pending;
+            }
+        }
+        Poll::Ready(this.collect())
+    }
+}
+
+impl Drop for TaskHandle {
+    fn drop(&mut self) {
+        if self.joined {
+            return;
+        }
+        // Detach: the slot is released by whoever sees the other side done last
+        let task = self.task();
+        if task.state.fetch_or(DETACHED, Ordering::AcqRel) & COMPLETE != 0 {
+            task.release();
+        }
+    }
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if Instant::now() >= self.deadline {
+            return Poll::Ready(());
+        }
+        // The timer of the task is used, the waker may belong to a combinator inside it
+        TASKS[current_task_index()].sleep(cx.waker(), self.deadline);
+        Poll::Pending
+    }
+}
+
+// Implement thread safety
+// SAFETY: Cells are only accessed by the spawner before queuing, then by the executor
+unsafe impl Sync for Task {}
+// SAFETY: `#[repr(C)]` with the link first
+unsafe impl MpscNode for Task {}
+
+// Define helpers
+/// Body of the executor thread of `core`
+fn run(core: usize) -> usize {
+    let executor = &EXECUTORS[core];
+    scheduler::set_affinity(thread::current(), CpuMask::single(core));
+    executor.thread.store(thread::current().as_usize(), Ordering::Relaxed);
+    let mut ready = executor.ready.take_consumer().expect("Executor already running");
+    loop {
+        executor.idle.wait_until(|| executor.queued.load(Ordering::Acquire) != 0);
+        match ready.pop() {
+            Some(task) => {
+                executor.queued.fetch_sub(1, Ordering::Relaxed);
+                executor.poll(task);
+            }
+            // A push is in progress
+            None => thread::yield_now(),
+        }
+    }
+}
+
+unsafe fn poll_future<F: Future<Output = usize>>(future: *mut u8, context: &mut Context<'_>) -> Poll<usize> {
+    Pin::new_unchecked(&mut *future.cast::<F>()).poll(context)
+}
+
+unsafe fn drop_future<F>(future: *mut u8) {
+    future.cast::<F>().drop_in_place()
+}
+
+/// Timer callback of the task `index` (called from IRQ handlers)
+fn wake_sleepers(index: usize) {
+    let sleepers = TASKS[index].sleepers.lock().take();
+    sleepers.wake_all();
+}
+
+/// Packs the slot and its generation into the data pointer of a waker
+fn waker_token(index: usize, generation: u32) -> usize {
+    (generation as usize) << GENERATION_SHIFT | index
+}
+
+unsafe fn clone_waker(token: *const ()) -> RawWaker {
+    RawWaker::new(token, &WAKER_VTABLE)
+}
+
+unsafe fn wake_waker(token: *const ()) {
+    let token = token.addr();
+    TASKS[token & FLAGS_MASK as usize].wake((token >> GENERATION_SHIFT) as u32)
+}
+
+unsafe fn drop_waker(_token: *const ()) {}
+
+/// Claims a free slot, returning it with its generation.
+fn allocate() -> Option<(usize, u32)> {
+    (0..MAX_TASKS).find_map(|index| {
+        let state = TASKS[index].state.load(Ordering::Relaxed);
+        // Queued until spawned, so wakers cannot push it
+        let free = state & FLAGS_MASK == 0
+            && TASKS[index].state.compare_exchange(state, state | SPAWNED | QUEUED, Ordering::Acquire, Ordering::Relaxed).is_ok();
+        free.then_some((index, state >> GENERATION_SHIFT))
+    })
+}
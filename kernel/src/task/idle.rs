@@ -0,0 +1,69 @@
+// Import dependencies
+use core::time::Duration;
+use crate::arch::{cpu::{self, percpu::per_cpu, IrqGuard}, timer};
+use crate::sync::spin::Spinlock;
+use super::{scheduler, stats};
+
+// Define structs
+/// Low-power state a core can wait in while it has nothing to run.
+pub struct IdleState {
+    pub name: &'static str,
+    /// Shortest idle period for which entering the state pays off
+    pub target_residency: Duration,
+    /// Waits for an interrupt in the state (called with IRQs masked, must return with them masked)
+    pub enter: fn(),
+}
+
+// Define globals
+/// Always available: clock-gated until the next interrupt
+pub static WFI: IdleState = IdleState {
+    name: "wfi",
+    target_residency: Duration::ZERO,
+    enter: enter_wfi,
+};
+/// Idle states from the shallowest to the deepest
+static STATES: Spinlock<&'static [&'static IdleState]> = Spinlock::new(&[&WFI]);
+
+per_cpu! {
+    /// Predicted length of the next idle period (decaying average of the last ones, in counter ticks)
+    static PREDICTED: u64 = 0;
+}
+
+// Define procedures
+/// Replaces the idle states offered by the platform (from the shallowest to the deepest).
+///
+/// # Panics
+///
+/// Panics if `states` is empty.
+pub fn set_idle_states(states: &'static [&'static IdleState]) {
+    assert!(!states.is_empty(), "No idle state");
+    let _irq = IrqGuard::new();
+    *STATES.lock() = states;
+}
+
+/// Waits in a low-power state until an interrupt arrives, unless a thread is ready.
+///
+/// The deepest state whose target residency fits the predicted idle period is used.
+pub(super) fn enter() {
+    let _irq = IrqGuard::new();
+    // Checked with IRQs masked, so a wake-up cannot slip in before the wait
+    if scheduler::has_ready_threads() {
+        return;
+    }
+    let predicted = timer::ticks_to_duration(*PREDICTED.get());
+    let state = {
+        let states = STATES.lock();
+        states.iter().rev().find(|state| state.target_residency <= predicted).copied().unwrap_or(states[0])
+    };
+    stats::account_idle_entry();
+    let start = cpu::counter();
+    (state.enter)();
+    let residency = cpu::counter() - start;
+    PREDICTED.with(|predicted| *predicted = (*predicted * 7 + residency) / 8);
+}
+
+// Define helpers
+fn enter_wfi() {
+    // SAFETY: Pending interrupts end the wait even with IRQs masked
+    unsafe { cpu::wfi() }
+}
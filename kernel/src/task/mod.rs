@@ -0,0 +1,42 @@
+// Import dependencies
+// Define modules
+pub mod event;
+pub mod executor;
+pub mod idle;
+pub mod policy;
+pub mod preempt;
+pub mod scheduler;
+pub mod softirq;
+pub mod stats;
+pub mod thread;
+pub mod wait;
+pub mod workqueue;
+// Export definitions
+pub use thread::{current, exit, spawn, spawn_with_stack, yield_now, JoinHandle, SpawnError, ThreadId};
+pub use event::Event;
+pub use executor::TaskHandle;
+pub use policy::{DeadlineParams, SchedError, SchedPolicy};
+pub use preempt::{preempt_disable, PreemptGuard};
+pub use scheduler::{set_affinity, set_policy, CpuMask};
+pub use softirq::{softirq_disable, SoftirqGuard, Tasklet};
+pub use stats::{cpu_stats, load_average, CpuStats, LoadAverage};
+pub use wait::WaitQueue;
+pub use workqueue::{Work, WorkQueue};
+// Define procedures
+/// Sets up threading on the calling core, the boot flow becoming its idle thread.
+///
+/// # Safety
+///
+/// Must be called once per core, from its boot flow.
+pub unsafe fn init_core() {
+    scheduler::init_core();
+    softirq::init_core();
+}
+
+/// Idle loop: runs ready threads, waiting in a low-power state while there are none.
+pub fn idle() -> ! {
+    loop {
+        yield_now();
+        idle::enter();
+    }
+}
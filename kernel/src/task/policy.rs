@@ -0,0 +1,152 @@
+// Import dependencies
+use core::{fmt, time::Duration};
+use crate::arch::timer;
+
+// Define constants
+/// Highest real-time priority (priorities go from 1 to this value)
+pub const MAX_RT_PRIORITY: u8 = 99;
+/// Share of a core that deadline threads may reserve, in parts per million
+pub const DEADLINE_UTILIZATION_LIMIT: u32 = 950_000;
+const CLASS_SHIFT: u32 = 62;
+const CLASS_DEADLINE: u64 = 0;
+const CLASS_REALTIME: u64 = 1;
+const CLASS_NORMAL: u64 = 2;
+/// Rank of the idle threads (after every other thread)
+pub(super) const IDLE_RANK: u64 = u64::MAX;
+
+// Define structs
+/// Scheduling class and parameters of a thread.
+///
+/// Deadline threads run before real-time ones, which always preempt normal ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchedPolicy {
+    /// Time-shared round-robin
+    Normal,
+    /// Fixed priority, runs until it blocks or yields
+    Fifo(u8),
+    /// Fixed priority, round-robin with the threads of equal priority
+    RoundRobin(u8),
+    /// Earliest deadline first, with `runtime` reserved every `period`
+    Deadline(DeadlineParams),
+}
+
+/// Reservation of a deadline thread: `runtime` of CPU time within `deadline`
+/// after the start of every `period`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeadlineParams {
+    pub runtime: Duration,
+    pub deadline: Duration,
+    pub period: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchedError {
+    /// Priority out of `1..=MAX_RT_PRIORITY`, or not `runtime <= deadline <= period`
+    InvalidParameters,
+    /// No allowed core can fit the reservation
+    Overloaded,
+}
+
+/// Scheduling state of a thread, protected by its scheduler lock
+pub(super) struct SchedEntity {
+    pub policy: SchedPolicy,
+    /// Core holding the deadline reservation
+    pub dl_core: usize,
+    /// Deadline class: CPU time left in the current period (counter ticks)
+    pub dl_budget: u64,
+    /// Deadline class: absolute deadline of the current period (counter value)
+    pub dl_deadline: u64,
+    /// Deadline class: start of the current period (counter value)
+    pub dl_period_start: u64,
+    /// Deadline class: budget exhausted, waiting for the next period
+    pub dl_throttled: bool,
+}
+
+// Implement structs
+impl SchedPolicy {
+    pub fn validate(&self) -> Result<(), SchedError> {
+        let valid = match *self {
+            SchedPolicy::Normal => true,
+            SchedPolicy::Fifo(priority) | SchedPolicy::RoundRobin(priority) => (1..=MAX_RT_PRIORITY).contains(&priority),
+            SchedPolicy::Deadline(params) => {
+                !params.runtime.is_zero() && params.runtime <= params.deadline && params.deadline <= params.period
+            }
+        };
+        valid.then_some(()).ok_or(SchedError::InvalidParameters)
+    }
+
+    /// Whether the thread is preempted when its time slice ends
+    pub fn is_time_sliced(&self) -> bool {
+        matches!(self, SchedPolicy::Normal | SchedPolicy::RoundRobin(_))
+    }
+}
+
+impl DeadlineParams {
+    /// Share of a core reserved, in parts per million (rounded up)
+    pub fn utilization(&self) -> u32 {
+        let period = self.period.as_nanos();
+        ((self.runtime.as_nanos() * 1_000_000 + period - 1) / period) as u32
+    }
+}
+
+impl fmt::Display for SchedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchedError::InvalidParameters => write!(f, "invalid scheduling parameters"),
+            SchedError::Overloaded => write!(f, "deadline reservation would overload the cores"),
+        }
+    }
+}
+
+impl SchedEntity {
+    pub const fn new() -> Self {
+        Self {
+            policy: SchedPolicy::Normal,
+            dl_core: 0,
+            dl_budget: 0,
+            dl_deadline: 0,
+            dl_period_start: 0,
+            dl_throttled: false,
+        }
+    }
+
+    /// Order in the run queues (lowest runs first)
+    pub fn rank(&self) -> u64 {
+        match self.policy {
+            SchedPolicy::Deadline(_) => CLASS_DEADLINE << CLASS_SHIFT | self.dl_deadline.min((1 << CLASS_SHIFT) - 1),
+            SchedPolicy::Fifo(priority) | SchedPolicy::RoundRobin(priority) => {
+                CLASS_REALTIME << CLASS_SHIFT | (MAX_RT_PRIORITY - priority) as u64
+            }
+            SchedPolicy::Normal => CLASS_NORMAL << CLASS_SHIFT,
+        }
+    }
+
+    /// Starts a new period at `now`, with a full budget.
+    pub fn replenish(&mut self, now: u64) {
+        let SchedPolicy::Deadline(params) = self.policy else {
+            return;
+        };
+        self.dl_period_start = now;
+        self.dl_budget = timer::duration_to_ticks(params.runtime);
+        self.dl_deadline = now + timer::duration_to_ticks(params.deadline);
+        self.dl_throttled = false;
+    }
+
+    /// Counter value at which the next period starts
+    pub fn next_period(&self) -> u64 {
+        match self.policy {
+            SchedPolicy::Deadline(params) => self.dl_period_start + timer::duration_to_ticks(params.period),
+            _ => 0,
+        }
+    }
+
+    /// Charges `ticks` of CPU time, throttling a deadline thread whose budget is exhausted.
+    pub fn consume(&mut self, ticks: u64) -> bool {
+        if !matches!(self.policy, SchedPolicy::Deadline(_)) {
+            return false;
+        }
+        self.dl_budget = self.dl_budget.saturating_sub(ticks);
+        self.dl_throttled |= self.dl_budget == 0;
+        self.dl_budget == 0
+    }
+}
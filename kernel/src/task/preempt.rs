@@ -0,0 +1,60 @@
+// Import dependencies
+use core::{marker::PhantomData, mem};
+use crate::arch::cpu::{self, percpu::per_cpu};
+use super::scheduler;
+
+// Define structs
+/// Preemption-disabled section: the thread keeps its core until the guard is dropped.
+///
+/// Sections may nest; a reschedule requested meanwhile happens when the
+/// outermost one ends.
+pub struct PreemptGuard {
+    _not_send: PhantomData<*mut ()>,
+}
+
+// Define globals
+per_cpu! {
+    /// Nesting of preemption-disabled sections
+    static PREEMPT_COUNT: usize = 0;
+    /// The running thread should give up the core as soon as possible
+    static NEED_RESCHED: bool = false;
+}
+
+// Define procedures
+pub fn preempt_disable() -> PreemptGuard {
+    *PREEMPT_COUNT.get() += 1;
+    PreemptGuard { _not_send: PhantomData }
+}
+
+pub fn preempt_count() -> usize {
+    *PREEMPT_COUNT.get()
+}
+
+pub fn need_resched() -> bool {
+    *NEED_RESCHED.get()
+}
+
+/// Requests the calling core to reschedule on the next exception return
+/// (or at the end of the current preemption-disabled section).
+pub fn set_need_resched() {
+    *NEED_RESCHED.get() = true;
+}
+
+/// Clears the reschedule request, returning whether there was one.
+pub(super) fn take_need_resched() -> bool {
+    NEED_RESCHED.with(|flag| mem::replace(flag, false))
+}
+
+// Implement structs
+impl Drop for PreemptGuard {
+    fn drop(&mut self) {
+        let count = PREEMPT_COUNT.with(|count| {
+            *count -= 1;
+            *count
+        });
+        // Catch up with a reschedule requested during the section
+        if count == 0 && need_resched() && !cpu::irqs_masked() {
+            scheduler::schedule();
+        }
+    }
+}
@@ -0,0 +1,656 @@
+// Import dependencies
+use core::{mem, ptr, sync::atomic::{AtomicU32, AtomicU64, AtomicUsize, Ordering}, time::Duration};
+use crate::arch::cpu::{self, context::{switch_to, Context, ContextSwitch}, percpu::per_cpu, IrqGuard, CORE_COUNT};
+use crate::smp::{self, Ipi};
+use crate::sync::spin::Spinlock;
+use crate::time::TICK_HZ;
+use super::{policy::{SchedEntity, SchedError, SchedPolicy, DEADLINE_UTILIZATION_LIMIT, IDLE_RANK}, preempt, stats, thread::{self, ThreadId, ThreadState, MAX_THREADS}};
+
+// Define constants
+/// Time slice of the threads (in ticks) unless configured otherwise
+const DEFAULT_TIME_SLICE: u32 = 2;
+/// Ticks between two periodic load balancing passes of a core
+const BALANCE_INTERVAL: u32 = 10;
+
+// Define structs
+/// Ready threads ordered by rank (FIFO among equal ranks)
+struct RunQueue {
+    slots: [(u64, ThreadId); MAX_THREADS],
+    len: usize,
+}
+
+/// Scheduling state of a core
+struct CoreQueue {
+    ready: Spinlock<RunQueue>,
+    /// Ready threads plus the running one (idle excluded), read without the lock
+    load: AtomicUsize,
+    /// Rank of the running thread
+    running_rank: AtomicU64,
+    /// Deadline threads waiting for their next period (bit per thread id)
+    throttled: AtomicU64,
+    /// Share reserved by deadline threads, in parts per million
+    dl_utilization: AtomicU32,
+}
+
+/// Set of cores a thread may run on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CpuMask(u8);
+
+// Define globals
+/// Run queues, indexed by `cpu::core_id()`
+static CORES: [CoreQueue; CORE_COUNT] = [CoreQueue::EMPTY; CORE_COUNT];
+/// Serializes the admission control of deadline threads
+static ADMISSION: Spinlock<()> = Spinlock::new(());
+/// Ticks a thread runs before being preempted
+static TIME_SLICE: AtomicU32 = AtomicU32::new(DEFAULT_TIME_SLICE);
+
+per_cpu! {
+    /// Thread running on the core
+    static CURRENT: ThreadId = ThreadId::idle(0);
+    /// Thread switched away from, to be finished by the next one
+    static PREVIOUS: Option<ThreadId> = None;
+    /// Ticks left in the time slice of the running thread
+    static SLICE_LEFT: u32 = DEFAULT_TIME_SLICE;
+    /// Counter value when the running thread was last charged
+    static CHARGED_AT: u64 = 0;
+    /// Ticks left until the next periodic load balancing
+    static BALANCE_LEFT: u32 = BALANCE_INTERVAL;
+}
+
+// Define procedures
+/// Adopts the running boot flow as the idle thread of the calling core.
+///
+/// # Safety
+///
+/// Must be called once per core, before any other scheduler function.
+pub unsafe fn init_core() {
+    let core = cpu::this_core();
+    let idle = ThreadId::idle(core);
+    let thread = idle.thread();
+    thread.set_affinity(CpuMask::single(core));
+    thread.set_last_core(core);
+    thread.set_state(ThreadState::Running);
+    thread.set_on_cpu(true);
+    *CURRENT.get() = idle;
+    *CHARGED_AT.get() = cpu::counter();
+}
+
+pub fn current() -> ThreadId {
+    *CURRENT.get()
+}
+
+/// Switches to the next ready thread.
+///
+/// A running thread is put back in a run queue, after the threads of equal
+/// rank; a blocked or exited one is not. When the queue of the core is empty, a thread is
+/// pulled from the busiest core, falling back to the idle thread.
+///
+/// # Panics
+///
+/// Panics if preemption is disabled (the section would continue in another thread).
+pub fn schedule() {
+    assert!(preempt::preempt_count() == 0, "Scheduling with preemption disabled");
+    let _irq = IrqGuard::new();
+    preempt::take_need_resched();
+    let current = current();
+    let next = pick_next(current, false);
+    if next != current {
+        switch(current, next);
+    }
+}
+
+/// Gives up the core to the threads of equal or higher rank.
+///
+/// A deadline thread also gives up the rest of its budget, resuming at its
+/// next period (this is how periodic jobs wait for their next activation).
+pub fn yield_current() {
+    {
+        let _irq = IrqGuard::new();
+        let mut sched = current().thread().sched_lock();
+        if matches!(sched.policy, SchedPolicy::Deadline(_)) {
+            sched.dl_throttled = true;
+        }
+    }
+    schedule();
+}
+
+/// Charges the running thread for a timer tick, requesting a reschedule
+/// when its time slice or deadline budget is used up, or when a thread of
+/// higher rank is waiting.
+///
+/// Called from the timer interrupt.
+pub fn tick() {
+    let current = current();
+    let exhausted = charge(current);
+    let core = cpu::this_core();
+    replenish(core);
+    let balance = BALANCE_LEFT.with(|left| {
+        *left -= 1;
+        if *left == 0 {
+            *left = BALANCE_INTERVAL;
+        }
+        *left == BALANCE_INTERVAL
+    });
+    if balance {
+        rebalance(core);
+    }
+    stats::tick(CORES[core].load());
+    let expired = if current == idle_thread() {
+        // Leave the idle thread as soon as there is work, even on another core
+        CORES.iter().any(|queue| queue.ready_count() != 0)
+    } else {
+        let sliced = current.thread().sched_lock().policy.is_time_sliced();
+        let slice_over = SLICE_LEFT.with(|left| {
+            *left = left.saturating_sub(1);
+            *left == 0
+        });
+        let outranked = CORES[core].ready.lock().head_rank() < CORES[core].running_rank.load(Ordering::Relaxed);
+        exhausted || (sliced && slice_over) || outranked
+    };
+    if expired {
+        preempt::set_need_resched();
+    }
+}
+
+/// Decides how to leave an exception taken while running `context`.
+///
+/// If a reschedule is pending and the interrupted code allows it, the
+/// current thread is preempted and the exception returns into the next one.
+pub fn exception_return(context: &mut Context) -> ContextSwitch {
+    // Sections with IRQs masked or preemption disabled are never preempted
+    if context.irqs_masked() || preempt::preempt_count() != 0 || !preempt::take_need_resched() {
+        return ContextSwitch::resume(context);
+    }
+    let current = current();
+    let next = pick_next(current, true);
+    if next == current {
+        return ContextSwitch::resume(context);
+    }
+    let resume = next.thread().take_context();
+    enter(current, next);
+    ContextSwitch {
+        resume,
+        publish: current.thread().context_slot(),
+    }
+}
+
+/// Whether a thread is waiting that the calling core could run (on its queue or by stealing).
+pub(super) fn has_ready_threads() -> bool {
+    let core = cpu::this_core();
+    CORES[core].ready_count() != 0 || CORES.iter().any(|queue| queue.has_runnable(core))
+}
+
+/// Sets the time slice of the threads, rounded to whole ticks (at least one).
+pub fn set_time_slice(slice: Duration) {
+    let ticks = (slice.as_micros() * TICK_HZ as u128 / 1_000_000).clamp(1, u32::MAX as u128);
+    TIME_SLICE.store(ticks as u32, Ordering::Relaxed);
+}
+
+pub fn time_slice() -> Duration {
+    Duration::from_micros(TIME_SLICE.load(Ordering::Relaxed) as u64 * 1_000_000 / TICK_HZ)
+}
+
+/// Makes a blocked thread ready to run.
+///
+/// The thread is queued on the core it last ran on, unless another allowed
+/// core is clearly less loaded. Returns `false` if the thread was not blocked.
+pub fn wake(id: ThreadId) -> bool {
+    let _irq = IrqGuard::new();
+    let thread = id.thread();
+    let mut sched = thread.sched_lock();
+    if thread.state() != ThreadState::Blocked {
+        return false;
+    }
+    if thread.on_cpu() {
+        // Not switched out yet: cancel the wait instead
+        thread.set_state(ThreadState::Running);
+        return true;
+    }
+    if matches!(sched.policy, SchedPolicy::Deadline(_)) {
+        let now = cpu::counter();
+        if now >= sched.dl_deadline {
+            // Waking up after its deadline: start a new period
+            sched.replenish(now);
+        } else if sched.dl_throttled {
+            thread.set_state(ThreadState::Ready);
+            CORES[sched.dl_core].throttled.fetch_or(1 << id.as_usize(), Ordering::Relaxed);
+            return true;
+        }
+    }
+    enqueue(id, select_core(id), &sched, false);
+    true
+}
+
+/// Changes the scheduling class of `id`.
+///
+/// A deadline reservation is admitted on the least reserved allowed core
+/// that can fit it, and the thread is then bound to that core.
+///
+/// # Panics
+///
+/// Panics if `id` is an idle thread.
+pub fn set_policy(id: ThreadId, policy: SchedPolicy) -> Result<(), SchedError> {
+    policy.validate()?;
+    assert!(!id.is_idle(), "Idle threads keep the normal class");
+    {
+        let _irq = IrqGuard::new();
+        let thread = id.thread();
+        let mut sched = thread.sched_lock();
+        {
+            let _admission = ADMISSION.lock();
+            let reserve = |core: usize, policy: SchedPolicy| {
+                if let SchedPolicy::Deadline(params) = policy {
+                    CORES[core].dl_utilization.fetch_add(params.utilization(), Ordering::Relaxed);
+                }
+            };
+            release_reservation(&sched);
+            if let SchedPolicy::Deadline(params) = policy {
+                let fits = |&core: &usize| {
+                    CORES[core].dl_utilization.load(Ordering::Relaxed) + params.utilization() <= DEADLINE_UTILIZATION_LIMIT
+                };
+                let Some(core) = thread.affinity().cores().filter(fits).min_by_key(|&core| CORES[core].dl_utilization.load(Ordering::Relaxed)) else {
+                    reserve(sched.dl_core, sched.policy);
+                    return Err(SchedError::Overloaded);
+                };
+                reserve(core, policy);
+                sched.dl_core = core;
+                thread.set_affinity(CpuMask::single(core));
+            }
+        }
+        let was_throttled = mem::replace(&mut sched.dl_throttled, false);
+        sched.policy = policy;
+        sched.replenish(cpu::counter());
+        match thread.state() {
+            // Re-queue with the new rank
+            ThreadState::Ready => {
+                let queued = was_throttled || (0..CORE_COUNT).any(|core| CORES[core].remove(id));
+                if was_throttled {
+                    CORES.iter().for_each(|queue| { queue.throttled.fetch_and(!(1 << id.as_usize()), Ordering::Relaxed); });
+                }
+                if queued {
+                    enqueue(id, select_core(id), &sched, false);
+                }
+            }
+            ThreadState::Running => CORES[thread.last_core()].running_rank.store(sched.rank(), Ordering::Relaxed),
+            _ => {}
+        }
+    }
+    if id == current() {
+        schedule();
+    }
+    Ok(())
+}
+
+/// Restricts the cores `id` may run on.
+///
+/// A queued thread is moved right away; a running one leaves a forbidden
+/// core when it is next scheduled out (immediately for the calling thread).
+///
+/// # Panics
+///
+/// Panics if the mask is empty, or `id` is an idle or deadline thread (both
+/// are bound to their core).
+pub fn set_affinity(id: ThreadId, mask: CpuMask) {
+    assert!(!mask.is_empty(), "Empty affinity mask");
+    assert!(!id.is_idle(), "Idle threads are bound to their core");
+    {
+        let _irq = IrqGuard::new();
+        let thread = id.thread();
+        let sched = thread.sched_lock();
+        assert!(!matches!(sched.policy, SchedPolicy::Deadline(_)), "Deadline threads are bound to their core");
+        thread.set_affinity(mask);
+        if thread.state() == ThreadState::Ready {
+            let queued = (0..CORE_COUNT).filter(|&core| !mask.contains(core)).any(|core| CORES[core].remove(id));
+            if queued {
+                enqueue(id, select_core(id), &sched, false);
+            }
+        }
+    }
+    if id == current() && !mask.contains(cpu::this_core()) {
+        schedule();
+    }
+}
+
+/// Marks the calling thread as blocked; it stops running at the next `schedule`.
+pub(super) fn prepare_to_wait() {
+    let _irq = IrqGuard::new();
+    let thread = current().thread();
+    let _sched = thread.sched_lock();
+    thread.set_state(ThreadState::Blocked);
+}
+
+/// Cancels `prepare_to_wait`, even if the thread was already woken up.
+pub(super) fn finish_wait() {
+    let _irq = IrqGuard::new();
+    let thread = current().thread();
+    let _sched = thread.sched_lock();
+    // A thread still on its core is never queued by `wake`
+    debug_assert!(thread.state() != ThreadState::Ready);
+    thread.set_state(ThreadState::Running);
+}
+
+/// Marks the calling thread as exited (it is never scheduled again).
+pub(super) fn exit_current() {
+    let _irq = IrqGuard::new();
+    let thread = current().thread();
+    let mut sched = thread.sched_lock();
+    {
+        let _admission = ADMISSION.lock();
+        release_reservation(&sched);
+    }
+    sched.policy = SchedPolicy::Normal;
+    thread.set_state(ThreadState::Exited);
+}
+
+/// Completes a context switch, running on the stack of the new thread.
+pub(super) fn finish_switch() {
+    if let Some(previous) = PREVIOUS.get().take() {
+        thread::reap(previous);
+    }
+}
+
+// Implement structs
+impl CpuMask {
+    pub const ALL: CpuMask = CpuMask((1 << CORE_COUNT) - 1);
+
+    pub const fn from_bits(bits: u8) -> Self {
+        Self(bits & Self::ALL.0)
+    }
+
+    pub const fn single(core: usize) -> Self {
+        Self(1 << core)
+    }
+
+    pub const fn bits(self) -> u8 {
+        self.0
+    }
+
+    pub const fn contains(self, core: usize) -> bool {
+        core < CORE_COUNT && self.0 & (1 << core) != 0
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn cores(self) -> impl Iterator<Item = usize> {
+        (0..CORE_COUNT).filter(move |&core| self.contains(core))
+    }
+}
+
+impl CoreQueue {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const EMPTY: CoreQueue = CoreQueue {
+        ready: Spinlock::new(RunQueue::new()),
+        load: AtomicUsize::new(0),
+        running_rank: AtomicU64::new(IDLE_RANK),
+        throttled: AtomicU64::new(0),
+        dl_utilization: AtomicU32::new(0),
+    };
+
+    fn load(&self) -> usize {
+        self.load.load(Ordering::Relaxed)
+    }
+
+    fn ready_count(&self) -> usize {
+        self.ready.lock().len
+    }
+
+    /// Whether a ready thread is allowed to run on `core`
+    fn has_runnable(&self, core: usize) -> bool {
+        self.first_runnable(core).is_some()
+    }
+
+    /// First ready thread allowed to run on `core`, left in the queue
+    fn first_runnable(&self, core: usize) -> Option<ThreadId> {
+        self.ready.lock().find(|id| id.thread().affinity().contains(core)).map(|(_, id)| id)
+    }
+
+    fn push(&self, id: ThreadId, rank: u64, front: bool) {
+        self.ready.lock().push(id, rank, front);
+        self.load.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn remove(&self, id: ThreadId) -> bool {
+        let removed = self.ready.lock().remove(id);
+        if removed {
+            self.load.fetch_sub(1, Ordering::Relaxed);
+        }
+        removed
+    }
+
+    /// Removes the first ready thread allowed to run on `core`, to run it there right away.
+    fn take(&self, core: usize) -> Option<ThreadId> {
+        let mut ready = self.ready.lock();
+        let (rank, id) = ready.find(|id| id.thread().affinity().contains(core))?;
+        ready.remove(id);
+        if !ptr::eq(self, &CORES[core]) {
+            // Stolen: the thread now runs on `core`
+            self.load.fetch_sub(1, Ordering::Relaxed);
+            CORES[core].load.fetch_add(1, Ordering::Relaxed);
+        }
+        claim(id, core);
+        CORES[core].running_rank.store(rank, Ordering::Relaxed);
+        Some(id)
+    }
+}
+
+impl RunQueue {
+    const fn new() -> Self {
+        Self {
+            slots: [(IDLE_RANK, ThreadId::idle(0)); MAX_THREADS],
+            len: 0,
+        }
+    }
+
+    /// Inserts `id` after the threads of lower or equal rank (before the equal ones if `front`).
+    fn push(&mut self, id: ThreadId, rank: u64, front: bool) {
+        assert!(self.len < MAX_THREADS, "Run queue overflow");
+        let position = self.slots[..self.len].partition_point(|&(other, _)| other < rank || (!front && other == rank));
+        self.slots.copy_within(position..self.len, position + 1);
+        self.slots[position] = (rank, id);
+        self.len += 1;
+    }
+
+    fn head_rank(&self) -> u64 {
+        if self.len == 0 { IDLE_RANK } else { self.slots[0].0 }
+    }
+
+    fn find(&self, mut f: impl FnMut(ThreadId) -> bool) -> Option<(u64, ThreadId)> {
+        self.slots[..self.len].iter().copied().find(|&(_, id)| f(id))
+    }
+
+    fn remove(&mut self, id: ThreadId) -> bool {
+        let Some(position) = self.slots[..self.len].iter().position(|&(_, other)| other == id) else {
+            return false;
+        };
+        self.slots.copy_within(position + 1..self.len, position);
+        self.len -= 1;
+        true
+    }
+}
+
+// Define helpers
+/// Puts the current thread back in a run queue if needed and picks the next one.
+///
+/// When preempting, a thread still preparing to wait is kept runnable: it
+/// re-checks its condition once resumed.
+fn pick_next(current: ThreadId, preempting: bool) -> ThreadId {
+    let core = cpu::this_core();
+    let idle = idle_thread();
+    {
+        let thread = current.thread();
+        let sched = thread.sched_lock();
+        let runnable = match thread.state() {
+            ThreadState::Running => true,
+            ThreadState::Blocked => preempting,
+            _ => false,
+        };
+        // From here, a blocked thread is woken by queueing it
+        thread.set_on_cpu(false);
+        if current == idle {
+            thread.set_state(ThreadState::Ready);
+        } else {
+            CORES[core].load.fetch_sub(1, Ordering::Relaxed);
+            if runnable && sched.dl_throttled {
+                // Out of budget: wait for the next period
+                thread.set_state(ThreadState::Ready);
+                CORES[sched.dl_core].throttled.fetch_or(1 << current.as_usize(), Ordering::Relaxed);
+            } else if runnable {
+                let target = if thread.affinity().contains(core) { core } else { select_core(current) };
+                // A preempted FIFO thread resumes before its peers
+                let front = preempting && matches!(sched.policy, SchedPolicy::Fifo(_));
+                enqueue(current, target, &sched, front);
+            }
+        }
+    }
+    // Idle-time balancing: pull work from the busiest core
+    let next = CORES[core].take(core).or_else(|| {
+        let busiest = busiest_core(core)?;
+        CORES[busiest].take(core)
+    });
+    match next {
+        Some(next) => next,
+        None => {
+            idle.thread().set_state(ThreadState::Running);
+            CORES[core].running_rank.store(IDLE_RANK, Ordering::Relaxed);
+            idle
+        }
+    }
+}
+
+/// Queues a ready thread on `core`, preempting the running thread if it has a lower rank.
+fn enqueue(id: ThreadId, core: usize, sched: &SchedEntity, front: bool) {
+    let rank = sched.rank();
+    id.thread().set_state(ThreadState::Ready);
+    CORES[core].push(id, rank, front);
+    if rank < CORES[core].running_rank.load(Ordering::Relaxed) {
+        if core == cpu::this_core() {
+            preempt::set_need_resched();
+        } else {
+            smp::send_ipi(core, Ipi::Reschedule);
+        }
+    }
+}
+
+/// Makes the throttled deadline threads of `core` whose new period started ready again.
+fn replenish(core: usize) {
+    let now = cpu::counter();
+    let mut throttled = CORES[core].throttled.load(Ordering::Relaxed);
+    while throttled != 0 {
+        let id = ThreadId::from_index(throttled.trailing_zeros() as usize);
+        throttled &= throttled - 1;
+        let thread = id.thread();
+        let mut sched = thread.sched_lock();
+        let next_period = sched.next_period();
+        if !sched.dl_throttled || now < next_period {
+            continue;
+        }
+        // Keep the activation times periodic, unless whole periods were missed
+        let period = next_period - sched.dl_period_start;
+        sched.replenish(if now - next_period < period { next_period } else { now });
+        CORES[core].throttled.fetch_and(!(1 << id.as_usize()), Ordering::Relaxed);
+        if thread.state() == ThreadState::Ready {
+            enqueue(id, core, &sched, false);
+        }
+    }
+}
+
+/// Returns the reservation of a deadline thread to its core.
+///
+/// Must be called with `ADMISSION` held.
+fn release_reservation(sched: &SchedEntity) {
+    if let SchedPolicy::Deadline(params) = sched.policy {
+        CORES[sched.dl_core].dl_utilization.fetch_sub(params.utilization(), Ordering::Relaxed);
+    }
+}
+
+/// Marks a thread taken from a run queue as running on `core`.
+fn claim(id: ThreadId, core: usize) {
+    let thread = id.thread();
+    thread.set_state(ThreadState::Running);
+    thread.set_on_cpu(true);
+    thread.set_last_core(core);
+}
+
+/// Chooses the core to queue a woken thread on, preferring its last core.
+fn select_core(id: ThreadId) -> usize {
+    let thread = id.thread();
+    let affinity = thread.affinity();
+    let last = thread.last_core();
+    let least_loaded = affinity.cores().min_by_key(|&core| CORES[core].load()).expect("Empty affinity mask");
+    // The last core likely still caches the thread's data, keep it unless clearly busier
+    if affinity.contains(last) && CORES[last].load() <= CORES[least_loaded].load() + 1 {
+        last
+    } else {
+        least_loaded
+    }
+}
+
+/// Other core with the most ready threads, if any is waiting.
+fn busiest_core(core: usize) -> Option<usize> {
+    (0..CORE_COUNT)
+        .filter(|&other| other != core)
+        .map(|other| (other, CORES[other].ready_count()))
+        .filter(|&(_, ready)| ready != 0)
+        .max_by_key(|&(_, ready)| ready)
+        .map(|(other, _)| other)
+}
+
+/// Periodic balancing: migrates a ready thread from the busiest core when it
+/// is clearly more loaded than `core`.
+fn rebalance(core: usize) {
+    let Some(busiest) = busiest_core(core) else {
+        return;
+    };
+    if CORES[busiest].load() <= CORES[core].load() + 1 {
+        return;
+    }
+    let Some(id) = CORES[busiest].first_runnable(core) else {
+        return;
+    };
+    let thread = id.thread();
+    let sched = thread.sched_lock();
+    // The thread may have run, been re-bound or re-ranked since it was found
+    if thread.state() != ThreadState::Ready || !thread.affinity().contains(core) || !CORES[busiest].remove(id) {
+        return;
+    }
+    CORES[core].push(id, sched.rank(), false);
+}
+
+/// Makes `next` the current thread of the core, starting a new time slice.
+fn enter(previous: ThreadId, next: ThreadId) {
+    charge(previous);
+    stats::account_switch();
+    *CURRENT.get() = next;
+    *SLICE_LEFT.get() = TIME_SLICE.load(Ordering::Relaxed);
+    // A thread resumed from an exception does not finish the switch, reap its predecessor now
+    if let Some(stale) = PREVIOUS.get().replace(previous) {
+        thread::reap(stale);
+    }
+}
+
+/// Adds the time elapsed since the last charge to the runtime of `id`.
+///
+/// Returns whether the deadline budget of the thread is exhausted.
+fn charge(id: ThreadId) -> bool {
+    let now = cpu::counter();
+    let elapsed = CHARGED_AT.with(|charged_at| now - mem::replace(charged_at, now));
+    let thread = id.thread();
+    thread.charge(elapsed);
+    stats::account_run(id.is_idle(), elapsed);
+    thread.sched_lock().consume(elapsed)
+}
+
+fn idle_thread() -> ThreadId {
+    ThreadId::idle(cpu::this_core())
+}
+
+fn switch(previous: ThreadId, next: ThreadId) {
+    let context = next.thread().take_context();
+    enter(previous, next);
+    // SAFETY: IRQs are masked and `next` is not running anywhere (its context was published)
+    unsafe { switch_to(previous.thread().context_slot(), context) };
+    // Resumed, on this or another core
+    finish_switch();
+}
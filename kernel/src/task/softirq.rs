@@ -0,0 +1,279 @@
+// Import dependencies
+use core::{cell::Cell, mem, ptr, sync::atomic::{AtomicU8, AtomicUsize, Ordering}};
+use crate::arch::cpu::{self, percpu::per_cpu, IrqGuard, CORE_COUNT};
+use crate::sync::rcu;
+use super::{preempt::{preempt_disable, PreemptGuard}, scheduler::{self, CpuMask}, thread, wait::WaitQueue};
+
+// Define constants
+/// Number of softirq lines
+pub const SOFTIRQ_COUNT: usize = 8;
+/// Runs the scheduled tasklets
+pub const SOFTIRQ_TASKLET: usize = 0;
+/// Runs the RCU callbacks whose grace period completed
+pub const SOFTIRQ_RCU: usize = 1;
+/// Rounds of pending softirqs run on IRQ exit before deferring to `ksoftirqd`
+const MAX_RESTARTS: usize = 10;
+/// Tasklet states
+const SCHEDULED: u8 = 1 << 0;
+const RUNNING: u8 = 1 << 1;
+
+// Define structs
+pub type SoftirqHandler = fn();
+
+/// Softirq-disabled section: softirqs raised meanwhile run when the outermost one ends.
+///
+/// Preemption is disabled too, so the thread stays on the core whose softirqs it disabled.
+pub struct SoftirqGuard {
+    _preempt: PreemptGuard,
+}
+
+/// Deferred callback scheduled from an IRQ handler, run in softirq context on
+/// the core that scheduled it.
+///
+/// A tasklet never runs on two cores at once, and scheduling it again
+/// before it runs has no effect.
+pub struct Tasklet {
+    callback: fn(usize),
+    data: AtomicUsize,
+    state: AtomicU8,
+    /// Link in the list of the scheduling core
+    next: Cell<*const Tasklet>,
+}
+
+/// FIFO of scheduled tasklets
+struct TaskletList {
+    head: *const Tasklet,
+    tail: *const Tasklet,
+}
+
+// Define globals
+/// Handlers stored as function addresses (0 when none)
+static HANDLERS: [AtomicUsize; SOFTIRQ_COUNT] = [ATOMIC_NONE; SOFTIRQ_COUNT];
+/// Wakes up the `ksoftirqd` thread of each core
+static KSOFTIRQD: [WaitQueue; CORE_COUNT] = [WAIT_QUEUE_EMPTY; CORE_COUNT];
+#[allow(clippy::declare_interior_mutable_const)]
+const ATOMIC_NONE: AtomicUsize = AtomicUsize::new(0);
+#[allow(clippy::declare_interior_mutable_const)]
+const WAIT_QUEUE_EMPTY: WaitQueue = WaitQueue::new();
+
+per_cpu! {
+    /// Raised softirqs, one bit per line
+    static PENDING: u32 = 0;
+    /// Nesting of softirq-disabled sections (running softirqs counts as one)
+    static DISABLE_COUNT: usize = 0;
+    static TASKLETS: TaskletList = TaskletList::new();
+}
+
+// Define procedures
+/// Installs the built-in softirqs and starts the `ksoftirqd` thread of the calling core.
+pub(super) fn init_core() {
+    for (softirq, handler) in [(SOFTIRQ_TASKLET, run_tasklets as SoftirqHandler), (SOFTIRQ_RCU, run_rcu_callbacks)] {
+        if let Err(installed) = register(softirq, handler) {
+            assert!(installed == handler, "Softirq {} already in use", softirq);
+        }
+    }
+    let core = cpu::this_core();
+    // Runs forever, nobody joins it
+    drop(thread::spawn(move || ksoftirqd(core)));
+}
+
+/// Installs `handler` for `softirq`.
+///
+/// Fails, returning the installed handler, if the line already has one.
+pub fn register(softirq: usize, handler: SoftirqHandler) -> Result<(), SoftirqHandler> {
+    HANDLERS[softirq]
+        .compare_exchange(0, handler as usize, Ordering::AcqRel, Ordering::Acquire)
+        .map(|_| ())
+        // SAFETY: Only function addresses are stored
+        .map_err(|installed| unsafe { to_handler(installed) })
+}
+
+/// Marks `softirq` pending on the calling core.
+///
+/// It runs with IRQs unmasked on the next IRQ exit, or in `ksoftirqd` when
+/// raised from a thread (no IRQ exit may come soon).
+pub fn raise(softirq: usize) {
+    assert!(softirq < SOFTIRQ_COUNT, "Invalid softirq {}", softirq);
+    // IRQ handlers run masked, and softirq-disabled sections catch up when they end
+    let from_thread = !cpu::irqs_masked();
+    // Stay on the core until its `ksoftirqd` is woken up
+    let _irq = IrqGuard::new();
+    *PENDING.get() |= 1 << softirq;
+    if from_thread && *DISABLE_COUNT.get() == 0 {
+        KSOFTIRQD[cpu::this_core()].notify_one();
+    }
+}
+
+/// Prevents softirqs from running on the calling core until the guard is dropped.
+///
+/// Needed around data shared with softirq handlers (like masking IRQs for IRQ handlers).
+pub fn softirq_disable() -> SoftirqGuard {
+    let preempt = preempt_disable();
+    *DISABLE_COUNT.get() += 1;
+    SoftirqGuard { _preempt: preempt }
+}
+
+/// Runs the pending softirqs before returning from an IRQ (called with IRQs masked).
+///
+/// Skipped if the interrupted code disabled softirqs or is running them.
+pub fn irq_exit() {
+    if *DISABLE_COUNT.get() == 0 && *PENDING.get() != 0 {
+        // Nested IRQs must not switch threads in the middle of the handler
+        let _preempt = preempt_disable();
+        run_pending();
+    }
+}
+
+// Implement structs
+impl Drop for SoftirqGuard {
+    fn drop(&mut self) {
+        let count = DISABLE_COUNT.with(|count| {
+            *count -= 1;
+            *count
+        });
+        // Catch up with softirqs raised during the section
+        // (the preemption guard is dropped afterwards, catching up with a reschedule)
+        if count == 0 && *PENDING.get() != 0 && !cpu::irqs_masked() {
+            let _irq = IrqGuard::new();
+            run_pending();
+        }
+    }
+}
+
+impl Tasklet {
+    pub const fn new(callback: fn(usize), data: usize) -> Self {
+        Self {
+            callback,
+            data: AtomicUsize::new(data),
+            state: AtomicU8::new(0),
+            next: Cell::new(ptr::null()),
+        }
+    }
+
+    /// Changes the argument given to the callback.
+    pub fn set_data(&self, data: usize) {
+        self.data.store(data, Ordering::Relaxed)
+    }
+
+    /// Schedules the tasklet on the calling core, returning `false` if it was already scheduled.
+    pub fn schedule(&'static self) -> bool {
+        if self.state.fetch_or(SCHEDULED, Ordering::AcqRel) & SCHEDULED != 0 {
+            return false;
+        }
+        enqueue_tasklet(self);
+        true
+    }
+
+    pub fn is_scheduled(&self) -> bool {
+        self.state.load(Ordering::Acquire) & SCHEDULED != 0
+    }
+}
+
+impl TaskletList {
+    const fn new() -> Self {
+        Self {
+            head: ptr::null(),
+            tail: ptr::null(),
+        }
+    }
+
+    fn push(&mut self, tasklet: &'static Tasklet) {
+        tasklet.next.set(ptr::null());
+        // SAFETY: Listed tasklets are 'static
+        match unsafe { self.tail.as_ref() } {
+            Some(tail) => tail.next.set(tasklet),
+            None => self.head = tasklet,
+        }
+        self.tail = tasklet;
+    }
+
+    fn pop(&mut self) -> Option<&'static Tasklet> {
+        // SAFETY: Listed tasklets are 'static
+        let tasklet = unsafe { self.head.as_ref::<'static>() }?;
+        self.head = tasklet.next.get();
+        if self.head.is_null() {
+            self.tail = ptr::null();
+        }
+        Some(tasklet)
+    }
+}
+
+// Implement thread safety
+// SAFETY: Each core only accesses its own list
+unsafe impl Send for TaskletList {}
+// SAFETY: The link is only accessed by the core listing the tasklet, with IRQs masked
+unsafe impl Sync for Tasklet {}
+
+// Define helpers
+/// Runs the pending softirqs, unmasking IRQs around the handlers (called with IRQs masked).
+///
+/// Softirqs that keep being raised are handed over to `ksoftirqd`.
+fn run_pending() {
+    *DISABLE_COUNT.get() += 1;
+    for _ in 0..MAX_RESTARTS {
+        let mut pending = PENDING.with(|pending| mem::replace(pending, 0));
+        if pending == 0 {
+            break;
+        }
+        // SAFETY: Softirqs are disabled, so nested IRQs do not run them again
+        unsafe { cpu::unmask_irq() };
+        while pending != 0 {
+            let softirq = pending.trailing_zeros() as usize;
+            match HANDLERS[softirq].load(Ordering::Acquire) {
+                0 => {}
+                handler => {
+                    // SAFETY: Only function addresses are stored
+                    let handler = unsafe { to_handler(handler) };
+                    handler()
+                }
+            }
+            pending &= pending - 1;
+        }
+        // SAFETY: Restores the masking of the caller
+        unsafe { cpu::mask_irq() };
+    }
+    *DISABLE_COUNT.get() -= 1;
+    if *PENDING.get() != 0 {
+        KSOFTIRQD[cpu::this_core()].notify_one();
+    }
+}
+
+/// Body of the `ksoftirqd` thread of `core`, running softirqs under load
+fn ksoftirqd(core: usize) -> usize {
+    scheduler::set_affinity(thread::current(), CpuMask::single(core));
+    loop {
+        KSOFTIRQD[core].wait_until(|| *PENDING.get() != 0);
+        // Dropping the guard runs the pending softirqs
+        drop(softirq_disable());
+    }
+}
+
+fn enqueue_tasklet(tasklet: &'static Tasklet) {
+    {
+        let _irq = IrqGuard::new();
+        TASKLETS.get().push(tasklet);
+    }
+    raise(SOFTIRQ_TASKLET);
+}
+
+fn run_tasklets() {
+    let mut list = TASKLETS.with(|list| mem::replace(list, TaskletList::new()));
+    while let Some(tasklet) = list.pop() {
+        // Running on another core: try again later
+        if tasklet.state.fetch_or(RUNNING, Ordering::AcqRel) & RUNNING != 0 {
+            enqueue_tasklet(tasklet);
+            continue;
+        }
+        tasklet.state.fetch_and(!SCHEDULED, Ordering::AcqRel);
+        (tasklet.callback)(tasklet.data.load(Ordering::Relaxed));
+        tasklet.state.fetch_and(!RUNNING, Ordering::Release);
+    }
+}
+
+fn run_rcu_callbacks() {
+    rcu::rcu_process_callbacks();
+}
+
+unsafe fn to_handler(address: usize) -> SoftirqHandler {
+    mem::transmute::<usize, SoftirqHandler>(address)
+}
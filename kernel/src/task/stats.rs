@@ -0,0 +1,159 @@
+// Import dependencies
+use core::{fmt, sync::atomic::{AtomicU64, Ordering}, time::Duration};
+use crate::arch::{cpu::{self, percpu::per_cpu, CORE_COUNT}, timer};
+use crate::time::TICK_HZ;
+
+// Define constants
+/// Fixed-point scale of the load averages (1.0)
+pub const LOAD_SCALE: u64 = 1 << LOAD_SHIFT;
+const LOAD_SHIFT: u32 = 11;
+/// Ticks between two load samples (5 s)
+const LOAD_INTERVAL: u64 = 5 * TICK_HZ;
+/// Decay factors of the 1, 5 and 15 minute averages (`LOAD_SCALE / e^(5 s / period)`)
+const LOAD_DECAY: [u64; 3] = [1884, 2014, 2037];
+
+// Define structs
+/// Time accounting of a core
+struct CoreStats {
+    /// Counter ticks spent in the idle thread
+    idle: AtomicU64,
+    /// Counter ticks spent in other threads
+    busy: AtomicU64,
+    /// Counter ticks spent in IRQ handlers (overlaps the two above)
+    irq: AtomicU64,
+    switches: AtomicU64,
+    idle_entries: AtomicU64,
+    /// Fixed-point averages of the runnable threads over 1, 5 and 15 minutes
+    load: [AtomicU64; 3],
+}
+
+/// Snapshot of the accounting of a core
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CpuStats {
+    pub idle: Duration,
+    pub busy: Duration,
+    /// Time in IRQ handlers, including the softirqs run on IRQ exit
+    pub irq: Duration,
+    pub context_switches: u64,
+    /// Times the core entered an idle state
+    pub idle_entries: u64,
+    pub load: LoadAverage,
+}
+
+/// Average number of runnable threads over 1, 5 and 15 minutes, scaled by `LOAD_SCALE`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct LoadAverage {
+    pub one: u64,
+    pub five: u64,
+    pub fifteen: u64,
+}
+
+// Define globals
+/// Accounting, indexed by `cpu::core_id()`
+static STATS: [CoreStats; CORE_COUNT] = [CoreStats::EMPTY; CORE_COUNT];
+
+per_cpu! {
+    /// Ticks left until the next load sample
+    static LOAD_LEFT: u64 = LOAD_INTERVAL;
+}
+
+// Define procedures
+/// Returns the accounting of `core`.
+pub fn cpu_stats(core: usize) -> CpuStats {
+    let stats = &STATS[core];
+    CpuStats {
+        idle: timer::ticks_to_duration(stats.idle.load(Ordering::Relaxed)),
+        busy: timer::ticks_to_duration(stats.busy.load(Ordering::Relaxed)),
+        irq: timer::ticks_to_duration(stats.irq.load(Ordering::Relaxed)),
+        context_switches: stats.switches.load(Ordering::Relaxed),
+        idle_entries: stats.idle_entries.load(Ordering::Relaxed),
+        load: stats.load_average(),
+    }
+}
+
+/// System-wide load average (sum of the cores' ones).
+pub fn load_average() -> LoadAverage {
+    STATS.iter().map(CoreStats::load_average).fold(LoadAverage::default(), |total, load| LoadAverage {
+        one: total.one + load.one,
+        five: total.five + load.five,
+        fifteen: total.fifteen + load.fifteen,
+    })
+}
+
+/// Charges `ticks` of counter time to the calling core, as idle or busy time.
+pub(super) fn account_run(idle: bool, ticks: u64) {
+    let stats = &STATS[cpu::this_core()];
+    let counter = if idle { &stats.idle } else { &stats.busy };
+    counter.fetch_add(ticks, Ordering::Relaxed);
+}
+
+pub(super) fn account_switch() {
+    STATS[cpu::this_core()].switches.fetch_add(1, Ordering::Relaxed);
+}
+
+pub(super) fn account_idle_entry() {
+    STATS[cpu::this_core()].idle_entries.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Charges the time since `start` (a counter value) to the IRQ time of the calling core.
+pub fn account_irq(start: u64) {
+    STATS[cpu::this_core()].irq.fetch_add(cpu::counter() - start, Ordering::Relaxed);
+}
+
+/// Samples the number of runnable threads of the calling core every `LOAD_INTERVAL` ticks.
+pub(super) fn tick(runnable: usize) {
+    let sample = LOAD_LEFT.with(|left| {
+        *left -= 1;
+        if *left == 0 {
+            *left = LOAD_INTERVAL;
+        }
+        *left == LOAD_INTERVAL
+    });
+    if !sample {
+        return;
+    }
+    let active = runnable as u64 * LOAD_SCALE;
+    for (load, decay) in STATS[cpu::this_core()].load.iter().zip(LOAD_DECAY) {
+        let average = load.load(Ordering::Relaxed);
+        let average = (average * decay + active * (LOAD_SCALE - decay) + LOAD_SCALE / 2) >> LOAD_SHIFT;
+        load.store(average, Ordering::Relaxed);
+    }
+}
+
+// Implement structs
+impl CoreStats {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const EMPTY: CoreStats = CoreStats {
+        idle: AtomicU64::new(0),
+        busy: AtomicU64::new(0),
+        irq: AtomicU64::new(0),
+        switches: AtomicU64::new(0),
+        idle_entries: AtomicU64::new(0),
+        load: [AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0)],
+    };
+
+    fn load_average(&self) -> LoadAverage {
+        LoadAverage {
+            one: self.load[0].load(Ordering::Relaxed),
+            five: self.load[1].load(Ordering::Relaxed),
+            fifteen: self.load[2].load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl CpuStats {
+    /// Busy share of the accounted time, in percent
+    pub fn utilization(&self) -> u32 {
+        let total = (self.idle + self.busy).as_nanos();
+        if total == 0 { 0 } else { (self.busy.as_nanos() * 100 / total) as u32 }
+    }
+}
+
+impl fmt::Display for LoadAverage {
+    /// Formats like `/proc/loadavg`: `0.52 0.40 0.33`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let hundredths = |load: u64| (load * 100 + LOAD_SCALE / 2) >> LOAD_SHIFT;
+        let [one, five, fifteen] = [self.one, self.five, self.fifteen].map(hundredths);
+        write!(f, "{}.{:02} {}.{:02} {}.{:02}", one / 100, one % 100, five / 100, five % 100, fifteen / 100, fifteen % 100)
+    }
+}
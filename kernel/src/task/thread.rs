@@ -0,0 +1,448 @@
+// Import dependencies
+use core::{fmt, mem, ptr::{self, NonNull}, slice, sync::atomic::{AtomicBool, AtomicPtr, AtomicU64, AtomicU8, AtomicUsize, Ordering}, hint::spin_loop, time::Duration};
+use crate::arch::{cpu::{self, context::Context, IrqGuard, CORE_COUNT}, timer};
+use crate::console::println;
+use crate::mm;
+use crate::sync::spin::{Spinlock, SpinlockGuard};
+use crate::time::Timer;
+use super::{policy::{SchedEntity, SchedPolicy}, scheduler::{self, CpuMask}, wait::WaitQueue};
+
+// Define constants
+/// Maximum number of threads (the first `CORE_COUNT` are the idle threads)
+pub const MAX_THREADS: usize = 32;
+/// Stack size of the threads created by `spawn`
+pub const STACK_SIZE: usize = 16 * 1024;
+const STACK_ALIGNMENT: usize = 16;
+/// Fill pattern of new stacks, bytes still holding it were never used
+const STACK_PAINT: u8 = 0xA5;
+/// Stack usage (in percent) above which exiting threads are reported, unless configured otherwise
+const DEFAULT_STACK_WARNING: u8 = 75;
+
+// Define structs
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThreadState {
+    /// Slot not in use
+    Free = 0,
+    /// Waiting in the run queue
+    Ready = 1,
+    Running = 2,
+    /// Waiting for an event (or being created)
+    Blocked = 3,
+    /// Finished, waiting to be joined
+    Exited = 4,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ThreadId(usize);
+
+/// Kernel thread control block
+pub struct Thread {
+    /// Scheduling parameters, also serializing the state changes of the thread
+    /// (taken before run queue locks)
+    sched: Spinlock<SchedEntity>,
+    state: AtomicU8,
+    /// Not switched out yet, even if no longer running
+    on_cpu: AtomicBool,
+    affinity: AtomicU8,
+    last_core: AtomicU8,
+    /// Saved context (on the thread's stack), null while the thread is running
+    context: AtomicPtr<Context>,
+    exit_code: AtomicUsize,
+    detached: AtomicBool,
+    /// Time spent running, in counter ticks
+    runtime: AtomicU64,
+    joiners: WaitQueue,
+    /// Wakes the thread up when a timed wait expires
+    timeout: Timer,
+    /// Lowest address of the stack (null for idle threads, which keep their boot stacks)
+    stack: AtomicPtr<u8>,
+    /// Page order of the stack
+    stack_order: AtomicU8,
+}
+
+/// Owned permission to join a thread (dropping it detaches the thread)
+#[must_use = "dropping the handle detaches the thread"]
+pub struct JoinHandle {
+    id: ThreadId,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpawnError {
+    /// All thread slots are in use
+    NoSlot,
+    /// No memory is left for the stack
+    NoMemory,
+    /// The stack is larger than the biggest page block
+    StackTooLarge,
+    /// The closure and the initial context do not fit in the stack
+    ClosureTooLarge,
+}
+
+// Define globals
+static THREADS: [Thread; MAX_THREADS] = [Thread::EMPTY; MAX_THREADS];
+/// Stack usage (in percent) above which exiting threads are reported
+static STACK_WARNING: AtomicU8 = AtomicU8::new(DEFAULT_STACK_WARNING);
+
+// Define procedures
+/// Spawns a kernel thread running `f`, whose return value is the exit code.
+///
+/// The closure is moved to the top of the new thread's stack, so no
+/// other allocation is needed.
+///
+/// # Panics
+///
+/// Panics if the thread cannot be created (see [`SpawnError`]).
+pub fn spawn<F>(f: F) -> JoinHandle
+where
+    F: FnOnce() -> usize + Send + 'static,
+{
+    spawn_with_stack(STACK_SIZE, f).unwrap_or_else(|error| panic!("Cannot spawn a thread: {}", error))
+}
+
+/// Like `spawn`, with a stack of at least `stack_size` bytes (rounded up to a power of two pages),
+/// failing instead of panicking.
+pub fn spawn_with_stack<F>(stack_size: usize, f: F) -> Result<JoinHandle, SpawnError>
+where
+    F: FnOnce() -> usize + Send + 'static,
+{
+    let order = mm::order_for(stack_size);
+    if order > mm::page::MAX_ORDER {
+        return Err(SpawnError::StackTooLarge);
+    }
+    // Worst case of the placement below, checked before anything is allocated
+    let align = mem::align_of::<F>().max(STACK_ALIGNMENT);
+    if mem::size_of::<F>() + (align - 1) + mem::size_of::<Context>() > mm::PAGE_SIZE << order {
+        return Err(SpawnError::ClosureTooLarge);
+    }
+    let id = allocate().ok_or(SpawnError::NoSlot)?;
+    let thread = id.thread();
+    let Some(base) = mm::alloc_pages(order) else {
+        thread.set_state(ThreadState::Free);
+        return Err(SpawnError::NoMemory);
+    };
+    thread.stack.store(base.as_ptr(), Ordering::Relaxed);
+    thread.stack_order.store(order as u8, Ordering::Relaxed);
+    // SAFETY: The slot (and its stack) is owned by this call until the thread is woken
+    unsafe {
+        let stack = slice::from_raw_parts_mut(base.as_ptr(), mm::PAGE_SIZE << order);
+        // Paint it to measure the usage later
+        stack.fill(STACK_PAINT);
+        let top = stack.as_mut_ptr_range().end;
+        // Place the closure at the top of the stack, followed by the initial context
+        let closure = top.wrapping_sub(mem::size_of::<F>()).map_addr(|addr| addr & !(align - 1)).cast::<F>();
+        closure.write(f);
+        let context = closure.cast::<Context>().sub(1);
+        context.write(Context::new_kernel(thread_entry::<F> as usize, closure.addr()));
+        thread.context.store(context, Ordering::Release);
+    }
+    thread.exit_code.store(0, Ordering::Relaxed);
+    thread.detached.store(false, Ordering::Relaxed);
+    thread.runtime.store(0, Ordering::Relaxed);
+    thread.affinity.store(CpuMask::ALL.bits(), Ordering::Relaxed);
+    thread.last_core.store(cpu::this_core() as u8, Ordering::Relaxed);
+    thread.on_cpu.store(false, Ordering::Relaxed);
+    {
+        let _irq = IrqGuard::new();
+        *thread.sched_lock() = SchedEntity::new();
+    }
+    scheduler::wake(id);
+    Ok(JoinHandle { id })
+}
+
+/// Sets the stack usage (in percent) above which exiting threads are reported.
+pub fn set_stack_warning_threshold(percent: u8) {
+    STACK_WARNING.store(percent.min(100), Ordering::Relaxed)
+}
+
+/// Terminates the calling thread.
+pub fn exit(code: usize) -> ! {
+    let id = current();
+    if let (Some(size), Some(used)) = (id.stack_size(), id.stack_high_water()) {
+        if used * 100 > size * STACK_WARNING.load(Ordering::Relaxed) as usize {
+            println!("Warning: {} used {} of its {} bytes of stack", id, used, size);
+        }
+    }
+    // Never preempted from here: the exiting thread must switch away through `schedule`
+    // SAFETY: The next thread restores its own interrupt mask
+    unsafe { cpu::mask_irq() };
+    let thread = id.thread();
+    thread.exit_code.store(code, Ordering::Relaxed);
+    scheduler::exit_current();
+    thread.joiners.notify_all();
+    scheduler::schedule();
+    unreachable!("Exited thread resumed")
+}
+
+/// Gives up the CPU to the next ready thread.
+///
+/// A deadline thread waits for its next period.
+pub fn yield_now() {
+    scheduler::yield_current()
+}
+
+/// Returns the calling thread's id.
+pub fn current() -> ThreadId {
+    scheduler::current()
+}
+
+/// Releases the slot of an exited thread once nobody can join it.
+///
+/// Called after switching away from `id`, when its stack is no longer in use.
+pub(super) fn reap(id: ThreadId) {
+    let thread = id.thread();
+    if thread.detached.load(Ordering::SeqCst) {
+        thread.try_release();
+    }
+}
+
+// Implement structs
+impl ThreadId {
+    /// Id of the idle thread of `core`
+    pub(super) const fn idle(core: usize) -> Self {
+        Self(core)
+    }
+
+    pub(super) const fn from_index(index: usize) -> Self {
+        Self(index)
+    }
+
+    pub(super) fn thread(self) -> &'static Thread {
+        &THREADS[self.0]
+    }
+
+    pub const fn as_usize(self) -> usize {
+        self.0
+    }
+
+    pub const fn is_idle(self) -> bool {
+        self.0 < CORE_COUNT
+    }
+
+    /// Cores the thread may run on
+    pub fn affinity(self) -> CpuMask {
+        self.thread().affinity()
+    }
+
+    pub fn policy(self) -> SchedPolicy {
+        let _irq = IrqGuard::new();
+        self.thread().sched_lock().policy
+    }
+
+    /// Core the thread last ran on
+    pub fn last_core(self) -> usize {
+        self.thread().last_core()
+    }
+
+    /// Timer waking the thread up at the end of timed waits
+    pub(super) fn timeout_timer(self) -> &'static Timer {
+        let timer = &self.thread().timeout;
+        timer.set_data(self.0);
+        timer
+    }
+
+    /// Size of the stack in bytes (`None` for idle threads, which run on their boot stacks)
+    pub fn stack_size(self) -> Option<usize> {
+        let thread = self.thread();
+        let stack = thread.stack.load(Ordering::Relaxed);
+        (!stack.is_null()).then(|| mm::PAGE_SIZE << thread.stack_order.load(Ordering::Relaxed))
+    }
+
+    /// Most bytes of stack the thread used so far (high-water mark).
+    ///
+    /// Found by looking for the deepest byte no longer holding the paint.
+    pub fn stack_high_water(self) -> Option<usize> {
+        let size = self.stack_size()?;
+        let stack = self.thread().stack.load(Ordering::Relaxed);
+        // SAFETY: The stack stays allocated while the slot is in use
+        let stack = unsafe { slice::from_raw_parts(stack, size) };
+        let untouched = stack.iter().take_while(|&&byte| byte == STACK_PAINT).count();
+        Some(size - untouched)
+    }
+
+    /// Time the thread spent running
+    pub fn runtime(self) -> Duration {
+        timer::ticks_to_duration(self.thread().runtime.load(Ordering::Relaxed))
+    }
+}
+
+impl fmt::Display for ThreadId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "thread#{}", self.0)
+    }
+}
+
+impl Thread {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const EMPTY: Thread = Thread {
+        sched: Spinlock::new(SchedEntity::new()),
+        state: AtomicU8::new(ThreadState::Free as u8),
+        on_cpu: AtomicBool::new(false),
+        affinity: AtomicU8::new(CpuMask::ALL.bits()),
+        last_core: AtomicU8::new(0),
+        context: AtomicPtr::new(ptr::null_mut()),
+        exit_code: AtomicUsize::new(0),
+        detached: AtomicBool::new(false),
+        runtime: AtomicU64::new(0),
+        joiners: WaitQueue::new(),
+        timeout: Timer::new(wake_on_timeout, 0),
+        stack: AtomicPtr::new(ptr::null_mut()),
+        stack_order: AtomicU8::new(0),
+    };
+
+    pub fn state(&self) -> ThreadState {
+        match self.state.load(Ordering::Acquire) {
+            0 => ThreadState::Free,
+            1 => ThreadState::Ready,
+            2 => ThreadState::Running,
+            3 => ThreadState::Blocked,
+            _ => ThreadState::Exited,
+        }
+    }
+
+    /// State changes are serialized by the scheduler
+    pub(super) fn set_state(&self, state: ThreadState) {
+        self.state.store(state as u8, Ordering::Release)
+    }
+
+    /// Location where `switch_to` publishes the saved context
+    pub(super) fn context_slot(&self) -> *mut *mut Context {
+        &self.context as *const AtomicPtr<Context> as *mut *mut Context
+    }
+
+    /// Must be taken with IRQs masked (the timer interrupt takes it too)
+    pub(super) fn sched_lock(&self) -> SpinlockGuard<'_, SchedEntity> {
+        self.sched.lock()
+    }
+
+    pub(super) fn on_cpu(&self) -> bool {
+        self.on_cpu.load(Ordering::Acquire)
+    }
+
+    pub(super) fn set_on_cpu(&self, on_cpu: bool) {
+        self.on_cpu.store(on_cpu, Ordering::Release)
+    }
+
+    pub(super) fn affinity(&self) -> CpuMask {
+        CpuMask::from_bits(self.affinity.load(Ordering::Relaxed))
+    }
+
+    pub(super) fn set_affinity(&self, mask: CpuMask) {
+        self.affinity.store(mask.bits(), Ordering::Relaxed)
+    }
+
+    pub(super) fn last_core(&self) -> usize {
+        self.last_core.load(Ordering::Relaxed) as usize
+    }
+
+    pub(super) fn set_last_core(&self, core: usize) {
+        self.last_core.store(core as u8, Ordering::Relaxed)
+    }
+
+    pub(super) fn charge(&self, ticks: u64) {
+        self.runtime.fetch_add(ticks, Ordering::Relaxed);
+    }
+
+    /// Takes the saved context, waiting for the core running the thread to publish it.
+    pub(super) fn take_context(&self) -> *mut Context {
+        loop {
+            let context = self.context.swap(ptr::null_mut(), Ordering::Acquire);
+            if !context.is_null() {
+                return context;
+            }
+            spin_loop()
+        }
+    }
+
+    /// Frees the slot (and the stack) if the thread exited and switched away for good.
+    fn try_release(&self) -> bool {
+        // Read before the slot can be reused
+        let (stack, order) = (self.stack.load(Ordering::Relaxed), self.stack_order.load(Ordering::Relaxed));
+        let released = !self.context.load(Ordering::SeqCst).is_null()
+            && self.state.compare_exchange(ThreadState::Exited as u8, ThreadState::Free as u8, Ordering::AcqRel, Ordering::Relaxed).is_ok();
+        if let (true, Some(stack)) = (released, NonNull::new(stack)) {
+            // SAFETY: The thread switched away from its stack for good
+            unsafe { mm::free_pages(stack, order as usize) };
+        }
+        released
+    }
+}
+
+impl JoinHandle {
+    pub fn id(&self) -> ThreadId {
+        self.id
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.id.thread().state() == ThreadState::Exited
+    }
+
+    /// Waits for the thread to exit and returns its exit code.
+    pub fn join(self) -> usize {
+        let thread = self.id.thread();
+        thread.joiners.wait_until(|| thread.state() == ThreadState::Exited);
+        self.collect()
+    }
+
+    /// Like `join`, giving the handle back if the thread is still running after `timeout`.
+    pub fn join_timeout(self, timeout: Duration) -> Result<usize, JoinHandle> {
+        let thread = self.id.thread();
+        if thread.joiners.wait_until_timeout(|| thread.state() == ThreadState::Exited, timeout) {
+            Ok(self.collect())
+        } else {
+            Err(self)
+        }
+    }
+
+    /// Takes the exit code of the exited thread and releases its slot.
+    fn collect(self) -> usize {
+        let thread = self.id.thread();
+        let code = thread.exit_code.load(Ordering::Relaxed);
+        // The exiting core may still be leaving the thread's stack
+        while !thread.try_release() {
+            spin_loop()
+        }
+        mem::forget(self);
+        code
+    }
+}
+
+impl Drop for JoinHandle {
+    fn drop(&mut self) {
+        // Detach: the slot is released by whoever sees the thread exited last
+        let thread = self.id.thread();
+        thread.detached.store(true, Ordering::SeqCst);
+        thread.try_release();
+    }
+}
+
+impl fmt::Display for SpawnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpawnError::NoSlot => write!(f, "no free thread slot"),
+            SpawnError::NoMemory => write!(f, "no memory for the stack"),
+            SpawnError::StackTooLarge => write!(f, "stack larger than the biggest page block"),
+            SpawnError::ClosureTooLarge => write!(f, "closure too large for the stack"),
+        }
+    }
+}
+
+// Define helpers
+/// First code run by a spawned thread
+extern "C" fn thread_entry<F: FnOnce() -> usize>(closure: *mut F) -> ! {
+    scheduler::finish_switch();
+    // SAFETY: Written by `spawn`, read once
+    let f = unsafe { closure.read() };
+    exit(f())
+}
+
+fn wake_on_timeout(index: usize) {
+    scheduler::wake(ThreadId(index));
+}
+
+fn allocate() -> Option<ThreadId> {
+    (CORE_COUNT..MAX_THREADS).map(ThreadId).find(|id| {
+        // Blocked until fully initialized
+        id.thread().state.compare_exchange(ThreadState::Free as u8, ThreadState::Blocked as u8, Ordering::Acquire, Ordering::Relaxed).is_ok()
+    })
+}
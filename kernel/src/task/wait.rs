@@ -0,0 +1,113 @@
+// Import dependencies
+use core::{sync::atomic::{AtomicU64, Ordering}, time::Duration};
+use crate::time::Instant;
+use super::{scheduler, thread::{ThreadId, MAX_THREADS}};
+
+/// Set of threads blocked until some condition becomes true.
+///
+/// Waiters are kept as a bitmap of thread ids, so the queue needs no
+/// storage besides one word (wake-up order is by thread id).
+pub struct WaitQueue {
+    waiters: AtomicU64,
+}
+
+// Check configuration
+const _: () = assert!(MAX_THREADS <= u64::BITS as usize, "Wait queues hold one bit per thread");
+
+// Implement structs
+impl WaitQueue {
+    pub const fn new() -> Self {
+        Self {
+            waiters: AtomicU64::new(0),
+        }
+    }
+
+    /// Blocks the calling thread until `condition` returns true.
+    ///
+    /// Code making the condition true must call `notify_one`/`notify_all` afterwards.
+    pub fn wait_until(&self, condition: impl FnMut() -> bool) {
+        self.wait(condition, None);
+    }
+
+    /// Like `wait_until`, giving up after `timeout`.
+    ///
+    /// Returns whether the condition became true.
+    pub fn wait_until_timeout(&self, condition: impl FnMut() -> bool, timeout: Duration) -> bool {
+        self.wait(condition, Some(Instant::now() + timeout))
+    }
+
+    /// Like `wait_until`, giving up once `deadline` has passed.
+    ///
+    /// Returns whether the condition became true.
+    pub fn wait_until_deadline(&self, condition: impl FnMut() -> bool, deadline: Instant) -> bool {
+        self.wait(condition, Some(deadline))
+    }
+
+    /// Wakes up one waiter, returning whether there was one.
+    pub fn notify_one(&self) -> bool {
+        let mut waiters = self.waiters.load(Ordering::SeqCst);
+        while waiters != 0 {
+            let lowest = waiters & waiters.wrapping_neg();
+            match self.waiters.compare_exchange_weak(waiters, waiters & !lowest, Ordering::SeqCst, Ordering::SeqCst) {
+                Ok(_) => {
+                    scheduler::wake(ThreadId::from_index(lowest.trailing_zeros() as usize));
+                    return true;
+                }
+                Err(current) => waiters = current,
+            }
+        }
+        false
+    }
+
+    /// Wakes up every waiter, returning how many there were.
+    pub fn notify_all(&self) -> usize {
+        let mut waiters = self.waiters.swap(0, Ordering::SeqCst);
+        let count = waiters.count_ones() as usize;
+        while waiters != 0 {
+            let index = waiters.trailing_zeros() as usize;
+            scheduler::wake(ThreadId::from_index(index));
+            waiters &= waiters - 1;
+        }
+        count
+    }
+
+    pub fn has_waiters(&self) -> bool {
+        self.waiters.load(Ordering::SeqCst) != 0
+    }
+
+    fn wait(&self, mut condition: impl FnMut() -> bool, deadline: Option<Instant>) -> bool {
+        let current = scheduler::current();
+        let bit = 1 << current.as_usize();
+        let expired = || deadline.map_or(false, |deadline| Instant::now() >= deadline);
+        // The timeout timer wakes the thread up, which then sees the deadline passed
+        if let Some(deadline) = deadline {
+            current.timeout_timer().start_at(deadline);
+        }
+        let satisfied = loop {
+            if condition() {
+                break true;
+            }
+            if expired() {
+                break false;
+            }
+            scheduler::prepare_to_wait();
+            self.waiters.fetch_or(bit, Ordering::SeqCst);
+            // Re-check, a notification may have been sent before registering
+            if condition() || expired() {
+                self.waiters.fetch_and(!bit, Ordering::SeqCst);
+                scheduler::finish_wait();
+                continue;
+            }
+            scheduler::schedule();
+        };
+        if deadline.is_some() {
+            current.timeout_timer().cancel();
+            let notified = self.waiters.fetch_and(!bit, Ordering::SeqCst) & bit == 0;
+            // Pass on a notification consumed while timing out
+            if !satisfied && notified {
+                self.notify_one();
+            }
+        }
+        satisfied
+    }
+}
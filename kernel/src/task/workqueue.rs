@@ -0,0 +1,174 @@
+// Import dependencies
+use core::{ptr, sync::atomic::{AtomicPtr, AtomicU8, AtomicUsize, Ordering}, time::Duration};
+use crate::sync::{mpsc::{MpscLink, MpscNode, MpscQueue}, once::Once};
+use crate::time::Timer;
+use super::{thread, wait::WaitQueue};
+
+// Define constants
+/// Work state: in the queue of the worker (or about to be pushed there)
+const QUEUED: u8 = 1 << 0;
+/// Work state: the callback runs when the worker pops the item (cleared by `cancel`)
+const PENDING: u8 = 1 << 1;
+
+// Define structs
+/// Item of deferred work, running `callback(data)` in the worker thread of a [`WorkQueue`].
+///
+/// Work runs in thread context, so unlike tasklets it may block.
+#[repr(C)]
+pub struct Work {
+    link: MpscLink,
+    callback: fn(usize),
+    data: AtomicUsize,
+    state: AtomicU8,
+    /// Queue targeted by the delayed and periodic runs
+    queue: AtomicPtr<WorkQueue>,
+    timer: Timer,
+}
+
+/// Named queue of [`Work`] items, run one at a time by a dedicated kernel thread.
+///
+/// Items can be queued from IRQ handlers once the worker is started (it
+/// starts on first use from a thread, or with `start`).
+pub struct WorkQueue {
+    name: &'static str,
+    items: MpscQueue<Work>,
+    /// Items pushed and not run yet (waited for by `flush`)
+    outstanding: AtomicUsize,
+    worker: WaitQueue,
+    flushers: WaitQueue,
+    started: Once,
+}
+
+// Define globals
+/// Shared queue for short work items of drivers
+pub static SYSTEM_WQ: WorkQueue = WorkQueue::new("events");
+
+// Implement structs
+impl Work {
+    pub const fn new(callback: fn(usize), data: usize) -> Self {
+        Self {
+            link: MpscLink::new(),
+            callback,
+            data: AtomicUsize::new(data),
+            state: AtomicU8::new(0),
+            queue: AtomicPtr::new(ptr::null_mut()),
+            timer: Timer::new(queue_expired, 0),
+        }
+    }
+
+    /// Changes the argument given to the callback.
+    pub fn set_data(&self, data: usize) {
+        self.data.store(data, Ordering::Relaxed)
+    }
+
+    /// Whether the work is waiting in a queue or for its delay.
+    pub fn is_pending(&self) -> bool {
+        self.state.load(Ordering::Acquire) & PENDING != 0 || self.timer.is_pending()
+    }
+
+    /// Cancels the pending runs of the work (including the periodic ones).
+    ///
+    /// Returns whether it was pending. A running callback is not waited for.
+    pub fn cancel(&self) -> bool {
+        let delayed = self.timer.cancel();
+        let queued = self.state.fetch_and(!PENDING, Ordering::AcqRel) & PENDING != 0;
+        delayed || queued
+    }
+}
+
+impl WorkQueue {
+    pub const fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            items: MpscQueue::new(),
+            outstanding: AtomicUsize::new(0),
+            worker: WaitQueue::new(),
+            flushers: WaitQueue::new(),
+            started: Once::new(),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Spawns the worker thread, if not done yet (must not be called from IRQ handlers).
+    pub fn start(&'static self) {
+        self.started.call_once(|| {
+            // The worker runs forever, nobody joins it
+            drop(thread::spawn(move || self.run()));
+        })
+    }
+
+    /// Queues `work` to run as soon as possible.
+    ///
+    /// Returns `false` if it was already queued (it then runs only once).
+    pub fn queue(&'static self, work: &'static Work) -> bool {
+        self.start();
+        work.queue.store(self as *const WorkQueue as *mut WorkQueue, Ordering::Relaxed);
+        let state = work.state.fetch_or(QUEUED | PENDING, Ordering::AcqRel);
+        if state & QUEUED == 0 {
+            self.outstanding.fetch_add(1, Ordering::AcqRel);
+            self.items.push(work);
+            self.worker.notify_one();
+        }
+        state & PENDING == 0
+    }
+
+    /// Queues `work` after `delay` (re-arming a pending delay).
+    pub fn queue_delayed(&'static self, work: &'static Work, delay: Duration) {
+        self.start();
+        work.queue.store(self as *const WorkQueue as *mut WorkQueue, Ordering::Relaxed);
+        work.timer.set_data(work as *const Work as usize);
+        work.timer.start(delay);
+    }
+
+    /// Queues `work` every `period` until cancelled.
+    ///
+    /// A run is skipped if the previous one is still queued.
+    pub fn queue_periodic(&'static self, work: &'static Work, period: Duration) {
+        self.start();
+        work.queue.store(self as *const WorkQueue as *mut WorkQueue, Ordering::Relaxed);
+        work.timer.set_data(work as *const Work as usize);
+        work.timer.start_periodic(period);
+    }
+
+    /// Blocks the calling thread until every queued item has run.
+    pub fn flush(&self) {
+        self.flushers.wait_until(|| self.outstanding.load(Ordering::Acquire) == 0)
+    }
+
+    /// Body of the worker thread
+    fn run(&self) -> usize {
+        let mut items = self.items.take_consumer().expect("Work queue worker already running");
+        loop {
+            self.worker.wait_until(|| self.outstanding.load(Ordering::Acquire) != 0);
+            let Some(work) = items.pop() else {
+                // A push is in progress
+                thread::yield_now();
+                continue;
+            };
+            // Queuing it again from now on pushes it again
+            if work.state.fetch_and(!(QUEUED | PENDING), Ordering::AcqRel) & PENDING != 0 {
+                (work.callback)(work.data.load(Ordering::Relaxed));
+            }
+            if self.outstanding.fetch_sub(1, Ordering::AcqRel) == 1 {
+                self.flushers.notify_all();
+            }
+        }
+    }
+}
+
+// Implement thread safety
+// SAFETY: `#[repr(C)]` with the link first
+unsafe impl MpscNode for Work {}
+
+// Define helpers
+/// Timer callback of delayed and periodic work
+fn queue_expired(address: usize) {
+    // SAFETY: Set by `queue_delayed`/`queue_periodic` from a &'static Work
+    let work = unsafe { &*(address as *const Work) };
+    // SAFETY: Set from a &'static WorkQueue before arming the timer
+    let queue = unsafe { &*work.queue.load(Ordering::Relaxed) };
+    queue.queue(work);
+}
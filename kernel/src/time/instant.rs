@@ -0,0 +1,66 @@
+// Import dependencies
+use core::{fmt, ops::{Add, AddAssign, Sub}, time::Duration};
+use crate::arch::{cpu, timer};
+
+// Define structs
+/// Point in time, measured with the system counter (shared by every core)
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Instant(u64);
+
+// Implement structs
+impl Instant {
+    pub fn now() -> Self {
+        Self(cpu::counter())
+    }
+
+    pub const fn from_ticks(ticks: u64) -> Self {
+        Self(ticks)
+    }
+
+    /// Counter value of the instant
+    pub const fn ticks(self) -> u64 {
+        self.0
+    }
+
+    /// Time elapsed from `earlier` to `self`, zero if `earlier` is later.
+    pub fn duration_since(self, earlier: Instant) -> Duration {
+        timer::ticks_to_duration(self.0.saturating_sub(earlier.0))
+    }
+
+    pub fn elapsed(self) -> Duration {
+        Instant::now().duration_since(self)
+    }
+
+    pub fn checked_add(self, duration: Duration) -> Option<Instant> {
+        self.0.checked_add(timer::duration_to_ticks(duration)).map(Instant)
+    }
+}
+
+impl Add<Duration> for Instant {
+    type Output = Instant;
+
+    /// Saturates at the end of time instead of overflowing
+    fn add(self, duration: Duration) -> Instant {
+        Instant(self.0.saturating_add(timer::duration_to_ticks(duration)))
+    }
+}
+
+impl AddAssign<Duration> for Instant {
+    fn add_assign(&mut self, duration: Duration) {
+        *self = *self + duration;
+    }
+}
+
+impl Sub<Instant> for Instant {
+    type Output = Duration;
+
+    fn sub(self, earlier: Instant) -> Duration {
+        self.duration_since(earlier)
+    }
+}
+
+impl fmt::Debug for Instant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Instant({:?})", timer::ticks_to_duration(self.0))
+    }
+}
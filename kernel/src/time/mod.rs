@@ -0,0 +1,68 @@
+// Import dependencies
+use core::time::Duration;
+use crate::arch::{interrupts::irq, timer};
+#[cfg(feature = "gic")]
+use crate::drivers::gic::{self, IRQ_CNTPNS};
+#[cfg(not(feature = "gic"))]
+use crate::drivers::local_intc::{self, IRQ_CNTPNS};
+use crate::sync::rcu;
+use crate::task::{scheduler, softirq, WaitQueue};
+// Define modules
+pub mod instant;
+pub mod wheel;
+// Export definitions
+pub use instant::Instant;
+pub use wheel::Timer;
+
+// Define constants
+/// Frequency of the kernel tick
+pub const TICK_HZ: u64 = 100;
+
+// Define globals
+/// Threads in `sleep` (only woken by their timeout)
+static SLEEPERS: WaitQueue = WaitQueue::new();
+
+// Define procedures
+/// Starts the periodic kernel tick on the calling core.
+pub fn init_core() {
+    // Every core shares the same handler
+    if let Err(installed) = irq::register(IRQ_CNTPNS, handle_tick) {
+        assert!(installed == handle_tick as irq::IrqHandler, "Timer IRQ already in use");
+    }
+    wheel::init_core();
+    #[cfg(not(feature = "gic"))]
+    local_intc::enable_timer_irq(IRQ_CNTPNS);
+    #[cfg(feature = "gic")]
+    gic::enable(IRQ_CNTPNS);
+    timer::arm(tick_ticks());
+}
+
+/// Blocks the calling thread for at least `duration`.
+pub fn sleep(duration: Duration) {
+    sleep_until(Instant::now() + duration)
+}
+
+/// Blocks the calling thread until `deadline` has passed.
+pub fn sleep_until(deadline: Instant) {
+    SLEEPERS.wait_until_deadline(|| false, deadline);
+}
+
+/// Period of the kernel tick
+pub fn tick_period() -> Duration {
+    Duration::from_nanos(1_000_000_000 / TICK_HZ)
+}
+
+// Define helpers
+fn tick_ticks() -> u64 {
+    timer::duration_to_ticks(tick_period())
+}
+
+fn handle_tick() {
+    // Re-arming acknowledges the interrupt
+    timer::arm(tick_ticks());
+    wheel::run_expired();
+    if rcu::rcu_has_callbacks() {
+        softirq::raise(softirq::SOFTIRQ_RCU);
+    }
+    scheduler::tick();
+}
@@ -0,0 +1,318 @@
+// Import dependencies
+use core::{cell::Cell, hint::spin_loop, mem, ptr, sync::atomic::{AtomicU8, AtomicUsize, Ordering}, time::Duration};
+use crate::arch::{cpu::{self, IrqGuard, CORE_COUNT}, timer};
+use crate::sync::spin::{Spinlock, SpinlockGuard};
+use super::{Instant, TICK_HZ};
+
+// Define constants
+const LEVEL_BITS: u32 = 6;
+const SLOTS: usize = 1 << LEVEL_BITS;
+const LEVELS: usize = 4;
+/// Longest delay a timer can be placed at (longer ones are re-placed when cascaded)
+const MAX_DELTA: u64 = (1 << (LEVEL_BITS * LEVELS as u32)) - 1;
+const NO_CORE: usize = usize::MAX;
+/// Timer states
+const IDLE: u8 = 0;
+const PENDING: u8 = 1;
+const RUNNING: u8 = 2;
+/// Cancelled while running: a periodic timer is not re-armed
+const RUNNING_CANCELLED: u8 = 3;
+
+// Define structs
+/// Kernel timer calling `callback(data)` from the tick interrupt of the core
+/// it was armed on, once or periodically.
+///
+/// Timers are intrusive and `'static`, so arming one never allocates.
+/// Callbacks run with IRQs masked and must not block.
+pub struct Timer {
+    callback: fn(usize),
+    data: AtomicUsize,
+    state: AtomicU8,
+    /// Wheel holding (or running) the timer
+    core: AtomicUsize,
+    // Protected by the lock of the wheel holding the timer
+    /// Wheel tick at which the timer fires
+    expires: Cell<u64>,
+    /// Re-arming period in wheel ticks (0 for one-shot timers)
+    period: Cell<u64>,
+    prev: Cell<*const Timer>,
+    next: Cell<*const Timer>,
+    /// Head of the list holding the timer
+    slot: Cell<*mut *const Timer>,
+}
+
+/// Hierarchical timer wheel: level `n` slots span `SLOTS^n` ticks and are
+/// cascaded into the lower level when the wheel reaches them.
+struct Wheel {
+    /// Last processed wheel tick
+    current: u64,
+    slots: [[*const Timer; SLOTS]; LEVELS],
+}
+
+// Define globals
+/// Timer wheels, indexed by `cpu::core_id()`
+static WHEELS: [Spinlock<Wheel>; CORE_COUNT] = [WHEEL_EMPTY; CORE_COUNT];
+#[allow(clippy::declare_interior_mutable_const)]
+const WHEEL_EMPTY: Spinlock<Wheel> = Spinlock::new(Wheel::new());
+
+// Define procedures
+/// Aligns the wheel of the calling core with the current time.
+pub(super) fn init_core() {
+    let _irq = IrqGuard::new();
+    WHEELS[cpu::this_core()].lock().current = wheel_ticks(Instant::now());
+}
+
+/// Runs the timers of the calling core that expired (called from the tick interrupt).
+pub(super) fn run_expired() {
+    let core = cpu::this_core();
+    let now = wheel_ticks(Instant::now());
+    loop {
+        let timer = {
+            let mut wheel = WHEELS[core].lock();
+            match wheel.pop_expired(now) {
+                Some(timer) => timer,
+                None => return,
+            }
+        };
+        (timer.callback)(timer.data.load(Ordering::Relaxed));
+        let mut wheel = WHEELS[core].lock();
+        match timer.state.load(Ordering::Acquire) {
+            RUNNING if timer.period.get() != 0 => {
+                timer.expires.set(timer.expires.get() + timer.period.get());
+                timer.state.store(PENDING, Ordering::Release);
+                wheel.insert(timer);
+            }
+            RUNNING | RUNNING_CANCELLED => {
+                timer.core.store(NO_CORE, Ordering::Relaxed);
+                timer.state.store(IDLE, Ordering::Release);
+            }
+            // Re-armed by the callback
+            _ => {}
+        }
+    }
+}
+
+// Implement structs
+impl Timer {
+    pub const fn new(callback: fn(usize), data: usize) -> Self {
+        Self {
+            callback,
+            data: AtomicUsize::new(data),
+            state: AtomicU8::new(IDLE),
+            core: AtomicUsize::new(NO_CORE),
+            expires: Cell::new(0),
+            period: Cell::new(0),
+            prev: Cell::new(ptr::null()),
+            next: Cell::new(ptr::null()),
+            slot: Cell::new(ptr::null_mut()),
+        }
+    }
+
+    /// Changes the argument given to the callback.
+    pub fn set_data(&self, data: usize) {
+        self.data.store(data, Ordering::Relaxed)
+    }
+
+    /// Fires once after `delay` (re-arming a pending timer).
+    pub fn start(&'static self, delay: Duration) {
+        self.start_at(Instant::now() + delay)
+    }
+
+    /// Fires once at `deadline` (at the first tick after it).
+    pub fn start_at(&'static self, deadline: Instant) {
+        self.arm(wheel_ticks(deadline), 0)
+    }
+
+    /// Fires every `period` (rounded to whole ticks), starting one period from now.
+    pub fn start_periodic(&'static self, period: Duration) {
+        let period = ticks_for(period).max(1);
+        self.arm(wheel_ticks(Instant::now()) + period, period)
+    }
+
+    /// Stops the timer, returning whether it was pending or running.
+    ///
+    /// A callback running on another core is waited for, so it is finished
+    /// when this returns (unless called by the callback itself).
+    pub fn cancel(&self) -> bool {
+        let _irq = IrqGuard::new();
+        let Some((core, mut wheel)) = self.lock_wheel() else {
+            return false;
+        };
+        match self.state.load(Ordering::Acquire) {
+            PENDING => {
+                wheel.unlink(self);
+                self.core.store(NO_CORE, Ordering::Relaxed);
+                self.state.store(IDLE, Ordering::Release);
+                true
+            }
+            RUNNING | RUNNING_CANCELLED => {
+                self.state.store(RUNNING_CANCELLED, Ordering::Release);
+                drop(wheel);
+                self.wait_callback(core);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.state.load(Ordering::Acquire) == PENDING
+    }
+
+    fn arm(&'static self, expires: u64, period: u64) {
+        let _irq = IrqGuard::new();
+        // Remove it from the wheel holding it (again if another core re-armed it meanwhile)
+        while let Some((core, mut wheel)) = self.lock_wheel() {
+            if self.state.load(Ordering::Acquire) == PENDING {
+                wheel.unlink(self);
+                self.core.store(NO_CORE, Ordering::Relaxed);
+                self.state.store(IDLE, Ordering::Release);
+                break;
+            }
+            // Called by the callback: `run_expired` sees the timer re-armed
+            if core == cpu::this_core() {
+                break;
+            }
+            // Keep a periodic callback from re-inserting it
+            self.state.store(RUNNING_CANCELLED, Ordering::Release);
+            drop(wheel);
+            self.wait_callback(core);
+        }
+        let core = cpu::this_core();
+        let mut wheel = WHEELS[core].lock();
+        self.expires.set(expires);
+        self.period.set(period);
+        self.core.store(core, Ordering::Relaxed);
+        self.state.store(PENDING, Ordering::Release);
+        wheel.insert(self);
+    }
+
+    /// Waits for the callback running on `core` to return.
+    fn wait_callback(&self, core: usize) {
+        // Callbacks run with IRQs masked: on this core, we are the callback
+        if core == cpu::this_core() {
+            return;
+        }
+        while matches!(self.state.load(Ordering::Acquire), RUNNING | RUNNING_CANCELLED) {
+            spin_loop()
+        }
+    }
+
+    /// Locks the wheel holding the timer (`None` if it is idle).
+    fn lock_wheel(&self) -> Option<(usize, SpinlockGuard<'static, Wheel>)> {
+        loop {
+            let core = self.core.load(Ordering::Acquire);
+            if core == NO_CORE {
+                return None;
+            }
+            let wheel = WHEELS[core].lock();
+            // The timer may have moved before the lock was taken
+            if self.core.load(Ordering::Acquire) == core {
+                return Some((core, wheel));
+            }
+        }
+    }
+}
+
+impl Wheel {
+    const fn new() -> Self {
+        Self {
+            current: 0,
+            slots: [[ptr::null(); SLOTS]; LEVELS],
+        }
+    }
+
+    fn insert(&mut self, timer: &Timer) {
+        // Expired timers fire on the next tick
+        self.insert_at(timer, timer.expires.get().max(self.current + 1));
+    }
+
+    /// Links `timer` in the slot of `expires` (not before `current`).
+    fn insert_at(&mut self, timer: &Timer, expires: u64) {
+        let delta = (expires - self.current).min(MAX_DELTA);
+        let level = (0..LEVELS).find(|&level| delta < 1 << (LEVEL_BITS * (level as u32 + 1))).unwrap_or(LEVELS - 1);
+        let slot = &mut self.slots[level][((self.current + delta) >> (LEVEL_BITS * level as u32)) as usize % SLOTS];
+        timer.prev.set(ptr::null());
+        timer.next.set(*slot);
+        timer.slot.set(slot);
+        // SAFETY: Linked timers are 'static
+        if let Some(next) = unsafe { slot.as_ref() } {
+            next.prev.set(timer);
+        }
+        *slot = timer;
+    }
+
+    fn unlink(&mut self, timer: &Timer) {
+        let (prev, next) = (timer.prev.get(), timer.next.get());
+        // SAFETY: Linked timers are 'static and only touched with the wheel locked
+        unsafe {
+            if let Some(next) = next.as_ref() {
+                next.prev.set(prev);
+            }
+            match prev.as_ref() {
+                Some(prev) => prev.next.set(next),
+                // The slot lives in this (locked) wheel
+                None => *timer.slot.get() = next,
+            }
+        }
+    }
+
+    /// Advances up to `now`, returning the next expired timer (marked running).
+    fn pop_expired(&mut self, now: u64) -> Option<&'static Timer> {
+        loop {
+            let index = (self.current % SLOTS as u64) as usize;
+            let head = self.slots[0][index];
+            // SAFETY: Linked timers are 'static
+            if let Some(timer) = unsafe { head.as_ref::<'static>() } {
+                self.unlink(timer);
+                timer.state.store(RUNNING, Ordering::Release);
+                return Some(timer);
+            }
+            if self.current >= now {
+                return None;
+            }
+            self.current += 1;
+            self.cascade();
+        }
+    }
+
+    /// Moves the timers of the higher level slots reached by `current` down.
+    fn cascade(&mut self) {
+        for level in 1..LEVELS {
+            let shift = LEVEL_BITS * level as u32;
+            if self.current & ((1 << shift) - 1) != 0 {
+                break;
+            }
+            let index = ((self.current >> shift) % SLOTS as u64) as usize;
+            let mut timer = mem::replace(&mut self.slots[level][index], ptr::null());
+            // SAFETY: Linked timers are 'static
+            while let Some(entry) = unsafe { timer.as_ref() } {
+                timer = entry.next.get();
+                // Timers due now land in the level 0 slot about to be processed
+                self.insert_at(entry, entry.expires.get().max(self.current));
+            }
+        }
+    }
+}
+
+// Implement thread safety
+// SAFETY: Timer links are only accessed with the lock of the wheel holding them
+unsafe impl Sync for Timer {}
+unsafe impl Send for Wheel {}
+
+// Define helpers
+/// Counter ticks per wheel tick
+fn tick_length() -> u64 {
+    cpu::counter_frequency() / TICK_HZ
+}
+
+/// First wheel tick at or after `instant` (timers never fire early)
+fn wheel_ticks(instant: Instant) -> u64 {
+    let length = tick_length();
+    (instant.ticks() + length - 1) / length
+}
+
+fn ticks_for(duration: Duration) -> u64 {
+    let length = tick_length();
+    (timer::duration_to_ticks(duration) + length - 1) / length
+}